@@ -0,0 +1,570 @@
+//! Store integrity check: rehash blobs against their sha256 names, find index entries whose
+//! blob is gone or corrupt, and find `.artifacts/out` files no longer hardlinked to their blob.
+//! Entries and blob paths come from the store API; only the directory walks read its layout.
+
+use crate::registry;
+use anyhow::{Context, Result};
+use distro_builder::artifact_store::{ArtifactFormat, ArtifactStore};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize)]
+pub struct FsckReport {
+    pub store_root: String,
+    pub repair: bool,
+    pub blobs_checked: u64,
+    pub bytes_checked: u64,
+    pub corrupt_blobs: Vec<CorruptBlob>,
+    /// Blobs that could not be read (permissions, I/O errors). Never repaired: the bytes may
+    /// well be fine.
+    pub unreadable_blobs: Vec<UnreadableBlob>,
+    pub dangling_entries: Vec<DanglingEntry>,
+    pub outputs: Vec<OutputLink>,
+}
+
+#[derive(Serialize)]
+pub struct CorruptBlob {
+    pub sha256: String,
+    pub actual_sha256: String,
+    pub quarantined_to: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct UnreadableBlob {
+    pub sha256: String,
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Serialize)]
+pub struct DanglingEntry {
+    pub kind: String,
+    pub input_key: String,
+    pub blob_sha256: String,
+    /// "missing blob" or "corrupt blob".
+    pub reason: &'static str,
+    /// Output the blob was re-ingested from, when one with the recorded bytes still exists.
+    pub restored_from: Option<String>,
+    /// Removed from the index by `--repair` because no output could restore its blob.
+    pub dropped: bool,
+}
+
+#[derive(Serialize)]
+pub struct OutputLink {
+    pub distro: String,
+    pub kind: String,
+    pub path: String,
+    /// "unlinked" (same bytes as the blob), "relinked", or "diverged" (different bytes, left alone).
+    pub status: &'static str,
+}
+
+/// What rehashing found wrong with one blob.
+#[derive(Debug, PartialEq, Eq)]
+enum BlobFault {
+    /// Readable, but the bytes hash to this sha256 instead of the blob's name.
+    Corrupt(String),
+    Unreadable(String),
+}
+
+impl FsckReport {
+    /// Problems still present after this run (everything found, minus what `--repair` fixed).
+    pub fn remaining(&self) -> usize {
+        self.corrupt_blobs
+            .iter()
+            .filter(|b| b.quarantined_to.is_none())
+            .count()
+            + self.unreadable_blobs.len()
+            + self
+                .dangling_entries
+                .iter()
+                .filter(|e| e.restored_from.is_none() && !e.dropped)
+                .count()
+            + self
+                .outputs
+                .iter()
+                .filter(|o| o.status == "unlinked")
+                .count()
+    }
+
+    pub fn outputs_with(&self, status: &str) -> usize {
+        self.outputs.iter().filter(|o| o.status == status).count()
+    }
+}
+
+/// Runs the check; with `repair`, quarantines corrupt blobs, re-ingests dangling entries from
+/// outputs that still hold the recorded bytes, drops the dangling entries nothing could restore
+/// and re-links outputs whose bytes match their blob.
+pub fn fsck(
+    repo_root: &Path,
+    store: &ArtifactStore,
+    repair: bool,
+    progress: bool,
+) -> Result<FsckReport> {
+    let root = store.root().to_path_buf();
+
+    let blobs = scan_blobs(&root)?;
+    let (faults, bytes_checked) = rehash_blobs(&blobs, progress);
+    let (corrupt_blobs, unreadable_blobs) = sort_faults(&root, faults, repair)?;
+    let corrupt: Vec<&str> = corrupt_blobs.iter().map(|b| b.sha256.as_str()).collect();
+
+    let mut dangling_entries = vec![];
+    for kind in index_kinds(&root)? {
+        for entry in store.list_kind(&kind)? {
+            let reason = if corrupt.contains(&entry.blob_sha256.as_str()) {
+                "corrupt blob"
+            } else if store
+                .get(&kind, &entry.input_key)?
+                .is_some_and(|stored| !stored.blob_path.is_file())
+            {
+                "missing blob"
+            } else {
+                continue;
+            };
+            dangling_entries.push(DanglingEntry {
+                kind: kind.clone(),
+                input_key: entry.input_key,
+                blob_sha256: entry.blob_sha256,
+                reason,
+                restored_from: None,
+                dropped: false,
+            });
+        }
+    }
+
+    let outputs = check_outputs(repo_root, store, &corrupt, &mut dangling_entries, repair)?;
+    if repair {
+        drop_unrestored(&mut dangling_entries, |kind, key| store.remove(kind, key))?;
+    }
+
+    Ok(FsckReport {
+        store_root: root.display().to_string(),
+        repair,
+        blobs_checked: blobs.len() as u64,
+        bytes_checked,
+        corrupt_blobs,
+        unreadable_blobs,
+        dangling_entries,
+        outputs,
+    })
+}
+
+#[cfg(unix)]
+pub fn hardlink_info(out_file: &Path, blob_file: &Path) -> Option<(bool, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    let out_md = std::fs::metadata(out_file).ok()?;
+    let blob_md = std::fs::metadata(blob_file).ok()?;
+    let same = out_md.dev() == blob_md.dev() && out_md.ino() == blob_md.ino();
+    Some((same, out_md.nlink()))
+}
+
+#[cfg(not(unix))]
+pub fn hardlink_info(_out_file: &Path, _blob_file: &Path) -> Option<(bool, u64)> {
+    None
+}
+
+/// All `(sha256, path)` blobs; in-flight temp files (not named by a sha256) are skipped.
+fn scan_blobs(store_root: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let dir = store_root.join("blobs/sha256");
+    let mut blobs = vec![];
+    if !dir.exists() {
+        return Ok(blobs);
+    }
+    for prefix in std::fs::read_dir(&dir).with_context(|| format!("reading {}", dir.display()))? {
+        let prefix = prefix?.path();
+        if !prefix.is_dir() {
+            continue;
+        }
+        for ent in std::fs::read_dir(&prefix)? {
+            let path = ent?.path();
+            let Some(name) = path.file_name().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if name.len() == 64 && name.bytes().all(|b| b.is_ascii_hexdigit()) && path.is_file() {
+                blobs.push((name.to_ascii_lowercase(), path));
+            }
+        }
+    }
+    blobs.sort();
+    Ok(blobs)
+}
+
+/// Rehashes blobs on all available cores. Returns every blob that did not verify and the
+/// number of bytes read.
+fn rehash_blobs(
+    blobs: &[(String, PathBuf)],
+    progress: bool,
+) -> (Vec<(String, PathBuf, BlobFault)>, u64) {
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let bytes = AtomicU64::new(0);
+    let mismatches = Mutex::new(vec![]);
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(blobs.len().max(1));
+
+    std::thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some((expected, path)) = blobs.get(i) else {
+                    break;
+                };
+                let fault = match sha256_file(path) {
+                    Ok((actual, n)) => {
+                        bytes.fetch_add(n, Ordering::Relaxed);
+                        (actual != *expected).then_some(BlobFault::Corrupt(actual))
+                    }
+                    Err(err) => Some(BlobFault::Unreadable(format!("{:#}", err))),
+                };
+                if let Some(fault) = fault {
+                    mismatches
+                        .lock()
+                        .unwrap()
+                        .push((expected.clone(), path.clone(), fault));
+                }
+                let n = done.fetch_add(1, Ordering::Relaxed) + 1;
+                if progress && (n.is_multiple_of(16) || n == blobs.len()) {
+                    eprint!(
+                        "\r  Rehashing blobs: {}/{} ({})",
+                        n,
+                        blobs.len(),
+                        crate::fmt_bytes(bytes.load(Ordering::Relaxed))
+                    );
+                }
+            });
+        }
+    });
+    if progress && !blobs.is_empty() {
+        eprintln!();
+    }
+
+    let mut mismatches = mismatches.into_inner().unwrap();
+    mismatches.sort_by(|a, b| a.0.cmp(&b.0));
+    (mismatches, bytes.into_inner())
+}
+
+/// Splits rehash faults into corrupt and unreadable blobs. With `repair`, corrupt blobs are
+/// quarantined; unreadable ones are only reported.
+fn sort_faults(
+    store_root: &Path,
+    faults: Vec<(String, PathBuf, BlobFault)>,
+    repair: bool,
+) -> Result<(Vec<CorruptBlob>, Vec<UnreadableBlob>)> {
+    let mut corrupt = vec![];
+    let mut unreadable = vec![];
+    for (sha256, path, fault) in faults {
+        match fault {
+            BlobFault::Corrupt(actual_sha256) => {
+                let quarantined_to = if repair {
+                    Some(
+                        quarantine_blob(store_root, &sha256, &path)?
+                            .display()
+                            .to_string(),
+                    )
+                } else {
+                    None
+                };
+                corrupt.push(CorruptBlob {
+                    sha256,
+                    actual_sha256,
+                    quarantined_to,
+                });
+            }
+            BlobFault::Unreadable(error) => unreadable.push(UnreadableBlob {
+                sha256,
+                path: path.display().to_string(),
+                error,
+            }),
+        }
+    }
+    Ok((corrupt, unreadable))
+}
+
+fn sha256_file(path: &Path) -> Result<(String, u64)> {
+    let mut f = std::fs::File::open(path).with_context(|| format!("opening {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    let mut total = 0u64;
+    loop {
+        let n = f.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        total += n as u64;
+    }
+    Ok((format!("{:x}", hasher.finalize()), total))
+}
+
+/// Moves a corrupt blob out of the content-addressed tree so gc/restore never see it again.
+fn quarantine_blob(store_root: &Path, sha256: &str, src: &Path) -> Result<PathBuf> {
+    let dir = store_root.join("quarantine");
+    std::fs::create_dir_all(&dir)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let dest = dir.join(format!("{}.{}", sha256, now));
+    std::fs::rename(src, &dest)
+        .with_context(|| format!("quarantining {} to {}", src.display(), dest.display()))?;
+    Ok(dest)
+}
+
+fn index_kinds(store_root: &Path) -> Result<Vec<String>> {
+    let idx = store_root.join("index");
+    let mut kinds = vec![];
+    if !idx.exists() {
+        return Ok(kinds);
+    }
+    for ent in std::fs::read_dir(&idx)? {
+        let path = ent?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if let Some(name) = path.file_name().and_then(|s| s.to_str()) {
+            kinds.push(name.to_string());
+        }
+    }
+    kinds.sort();
+    Ok(kinds)
+}
+
+/// Outputs whose stored blob is a plain file but which are no longer the same inode. With
+/// `repair`, an output that still holds the bytes of a dangling entry is re-ingested, which
+/// puts the blob back.
+fn check_outputs(
+    repo_root: &Path,
+    store: &ArtifactStore,
+    corrupt: &[&str],
+    dangling: &mut [DanglingEntry],
+    repair: bool,
+) -> Result<Vec<OutputLink>> {
+    let mut outputs = vec![];
+    for distro in registry::DISTROS {
+        let base_dir = distro.base_dir(repo_root);
+        let out_dir = distro_builder::artifact_store::central_output_dir_for_distro(&base_dir);
+        if !out_dir.exists() {
+            continue;
+        }
+        let artifacts = distro.load_artifacts(repo_root)?;
+        let mut seen = BTreeSet::new();
+        for kind in artifacts.kinds() {
+            let path = artifacts.output_path(kind, &out_dir);
+            if !path.is_file() || !seen.insert(path.clone()) {
+                continue;
+            }
+            let Some(key) = artifacts.input_key(kind, &out_dir)? else {
+                continue;
+            };
+            let Some(stored) = store.get(&kind.name, &key)? else {
+                continue;
+            };
+            if stored.entry.format != ArtifactFormat::File {
+                continue;
+            }
+            let same_bytes = || {
+                sha256_file(&path)
+                    .map(|(sha, _)| sha == stored.entry.blob_sha256)
+                    .unwrap_or(false)
+            };
+            if let Some(entry) = dangling
+                .iter_mut()
+                .find(|e| e.kind == kind.name && e.input_key == key)
+            {
+                if repair && same_bytes() {
+                    store.ingest_file_move_and_link(&kind.name, &key, &path, BTreeMap::new())?;
+                    entry.restored_from = Some(path.display().to_string());
+                }
+                continue;
+            }
+            if corrupt.contains(&stored.entry.blob_sha256.as_str()) {
+                continue;
+            }
+            let Some((false, _)) = hardlink_info(&path, &stored.blob_path) else {
+                continue;
+            };
+
+            let status = if !same_bytes() {
+                "diverged"
+            } else if repair {
+                relink(&stored.blob_path, &path)?;
+                "relinked"
+            } else {
+                "unlinked"
+            };
+            outputs.push(OutputLink {
+                distro: distro.dir.to_string(),
+                kind: kind.name.clone(),
+                path: path.display().to_string(),
+                status,
+            });
+        }
+    }
+    Ok(outputs)
+}
+
+/// Removes every dangling entry that was not re-ingested, so `restore` stops resolving keys to a
+/// missing or quarantined blob.
+fn drop_unrestored(
+    dangling: &mut [DanglingEntry],
+    mut remove: impl FnMut(&str, &str) -> Result<bool>,
+) -> Result<()> {
+    for entry in dangling.iter_mut().filter(|e| e.restored_from.is_none()) {
+        remove(&entry.kind, &entry.input_key).with_context(|| {
+            format!(
+                "dropping index entry {} key={}",
+                entry.kind, entry.input_key
+            )
+        })?;
+        entry.dropped = true;
+    }
+    Ok(())
+}
+
+/// Replaces `out_file` with a hardlink to `blob` (link to a sibling temp name, then rename).
+fn relink(blob: &Path, out_file: &Path) -> Result<()> {
+    let tmp = PathBuf::from(format!("{}.recart-fsck", out_file.display()));
+    let _ = std::fs::remove_file(&tmp);
+    std::fs::hard_link(blob, &tmp)
+        .with_context(|| format!("linking {} to {}", tmp.display(), blob.display()))?;
+    std::fs::rename(&tmp, out_file)
+        .with_context(|| format!("replacing {} with its blob", out_file.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_store(tag: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("recart-fsck-{}-{}", tag, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        root
+    }
+
+    fn write_blob(root: &Path, sha: &str, bytes: &[u8]) -> PathBuf {
+        let path = root.join("blobs/sha256").join(&sha[..2]).join(sha);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn rehash_reports_blobs_whose_bytes_changed() {
+        let root = test_store("rehash");
+        let good = format!("{:x}", Sha256::digest(b"good"));
+        let bad = format!("{:x}", Sha256::digest(b"original"));
+        let good_path = write_blob(&root, &good, b"good");
+        write_blob(&root, &bad, b"tampered");
+        std::fs::write(good_path.with_file_name("tmp-upload"), b"x").unwrap();
+
+        let mut blobs = scan_blobs(&root).unwrap();
+        let (mismatches, bytes) = rehash_blobs(&blobs, false);
+        // A blob that cannot be read is not evidence of corruption.
+        let gone = format!("{:x}", Sha256::digest(b"gone"));
+        blobs.push((gone.clone(), root.join("blobs/sha256/gone")));
+        let (with_unreadable, _) = rehash_blobs(&blobs, false);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(blobs.len(), 3);
+        assert_eq!(bytes, 12);
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].0, bad);
+        assert_eq!(
+            mismatches[0].2,
+            BlobFault::Corrupt(format!("{:x}", Sha256::digest(b"tampered")))
+        );
+        assert!(matches!(
+            with_unreadable.iter().find(|m| m.0 == gone).map(|m| &m.2),
+            Some(BlobFault::Unreadable(_))
+        ));
+    }
+
+    #[test]
+    fn repair_quarantines_corrupt_blobs_and_relinks_outputs() {
+        let root = test_store("repair");
+        let bad = format!("{:x}", Sha256::digest(b"original"));
+        let bad_path = write_blob(&root, &bad, b"tampered");
+        let unreadable = format!("{:x}", Sha256::digest(b"unreadable"));
+        let unreadable_path = write_blob(&root, &unreadable, b"unreadable");
+        let faults = vec![
+            (
+                bad.clone(),
+                bad_path.clone(),
+                BlobFault::Corrupt("whatever".to_string()),
+            ),
+            (
+                unreadable.clone(),
+                unreadable_path.clone(),
+                BlobFault::Unreadable("permission denied".to_string()),
+            ),
+        ];
+        let (corrupt, skipped) = sort_faults(&root, faults, true).unwrap();
+
+        let good = format!("{:x}", Sha256::digest(b"good"));
+        let blob = write_blob(&root, &good, b"good");
+        let output = root.join("out/filesystem.erofs");
+        std::fs::create_dir_all(output.parent().unwrap()).unwrap();
+        std::fs::write(&output, b"good").unwrap();
+        let before = hardlink_info(&output, &blob);
+        relink(&blob, &output).unwrap();
+        let after = hardlink_info(&output, &blob);
+
+        let quarantined = corrupt[0].quarantined_to.clone().map(PathBuf::from);
+        let quarantined_exists = quarantined.as_ref().is_some_and(|p| p.is_file());
+        let unreadable_kept = unreadable_path.is_file();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(corrupt.len(), 1);
+        assert!(!bad_path.exists());
+        assert!(quarantined_exists);
+        assert_eq!(skipped.len(), 1);
+        assert!(unreadable_kept);
+        assert_eq!(before.map(|(same, _)| same), Some(false));
+        assert_eq!(after, Some((true, 2)));
+    }
+
+    #[test]
+    fn repair_drops_dangling_entries_that_were_not_restored() {
+        let entry = |key: &str, restored_from: Option<&str>| DanglingEntry {
+            kind: "rootfs".to_string(),
+            input_key: key.to_string(),
+            blob_sha256: "ab".repeat(32),
+            reason: "missing blob",
+            restored_from: restored_from.map(str::to_string),
+            dropped: false,
+        };
+        let mut dangling = vec![
+            entry("restored", Some("/out/filesystem.erofs")),
+            entry("gone", None),
+        ];
+        let mut removed = vec![];
+        drop_unrestored(&mut dangling, |kind, key| {
+            removed.push(format!("{kind}/{key}"));
+            Ok(true)
+        })
+        .unwrap();
+
+        assert_eq!(removed, ["rootfs/gone"]);
+        assert!(!dangling[0].dropped);
+        assert!(dangling[1].dropped);
+        let report = FsckReport {
+            store_root: String::new(),
+            repair: true,
+            blobs_checked: 0,
+            bytes_checked: 0,
+            corrupt_blobs: vec![],
+            unreadable_blobs: vec![],
+            dangling_entries: dangling,
+            outputs: vec![],
+        };
+        assert_eq!(report.remaining(), 0);
+    }
+}
@@ -1,9 +1,12 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 use distro_builder::artifact_store::ArtifactStore;
+use registry::IngestOutcome;
 use std::path::Path;
 use std::path::PathBuf;
 
+mod fsck;
+mod registry;
 mod server;
 
 #[derive(Parser)]
@@ -36,6 +39,19 @@ enum Command {
         keep_last: usize,
     },
 
+    /// Verify store integrity: rehash blobs, find dangling index entries and outputs whose
+    /// hardlink to their blob was broken.
+    Fsck {
+        /// Quarantine corrupt blobs, re-ingest dangling entries from matching outputs, drop the
+        /// rest from the index and re-link outputs
+        #[arg(long)]
+        repair: bool,
+
+        /// Print the report as JSON instead of a summary
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Ingest existing distro build artifacts into the centralized store (no builds).
     ///
     /// This will only ingest artifacts that already exist on disk.
@@ -103,6 +119,26 @@ async fn main() -> Result<()> {
             println!("Removed {} index entry(s).", removed_idx);
             println!("Removed {} unreferenced blob(s).", removed_blobs);
         }
+        Command::Fsck { repair, json } => {
+            let report = fsck::fsck(&repo_root, &store, repair, true)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print_fsck_report(&report);
+            }
+            let left = report.remaining();
+            if left > 0 && repair {
+                anyhow::bail!(
+                    "{} problem(s) remain that --repair cannot fix (unreadable blobs, or dangling entries with no matching output)",
+                    left
+                );
+            } else if left > 0 {
+                anyhow::bail!(
+                    "{} problem(s) found; rerun with --repair to fix what it can",
+                    left
+                );
+            }
+        }
         Command::Ingest => {
             ingest_all(&repo_root, &store)?;
         }
@@ -121,35 +157,36 @@ async fn main() -> Result<()> {
 fn ingest_all(repo_root: &Path, store: &ArtifactStore) -> Result<()> {
     let mut any = false;
 
-    let leviso = repo_root.join("leviso");
-    if leviso.exists() {
-        any = true;
-        ingest_leviso(&leviso, store)?;
-    }
-
-    let acorn = repo_root.join("AcornOS");
-    if acorn.exists() {
-        any = true;
-        ingest_acorn(&acorn, store)?;
-    }
-
-    let iuppiter = repo_root.join("IuppiterOS");
-    if iuppiter.exists() {
-        any = true;
-        ingest_iuppiter(&iuppiter, store)?;
+    for distro in registry::DISTROS {
+        let base_dir = distro.base_dir(repo_root);
+        if base_dir.exists() {
+            any = true;
+            ingest_distro(repo_root, distro, &base_dir, store)?;
+        }
     }
 
     if !any {
+        let expected: Vec<String> = registry::DISTROS
+            .iter()
+            .map(|d| format!("{}/", d.dir))
+            .collect();
         anyhow::bail!(
-            "No distro directories found at repo root (expected leviso/, AcornOS/, IuppiterOS/)"
+            "No distro directories found at repo root (expected {})",
+            expected.join(", ")
         );
     }
 
     Ok(())
 }
 
-fn ingest_leviso(base_dir: &Path, store: &ArtifactStore) -> Result<()> {
-    println!("== Ingest leviso ==");
+fn ingest_distro(
+    repo_root: &Path,
+    distro: &registry::Distro,
+    base_dir: &Path,
+    store: &ArtifactStore,
+) -> Result<()> {
+    println!("== Ingest {} ==", distro.label);
+    let artifacts = distro.load_artifacts(repo_root)?;
     let out = distro_builder::artifact_store::central_output_dir_for_distro(base_dir);
     if !out.exists() {
         println!("  [SKIP] No output dir at {}", out.display());
@@ -157,512 +194,73 @@ fn ingest_leviso(base_dir: &Path, store: &ArtifactStore) -> Result<()> {
     }
 
     // Ensure hash keys exist (no builds).
-    leviso::rebuild::cache_kernel_hash(base_dir);
-    leviso::rebuild::cache_rootfs_hash(base_dir);
-    leviso::rebuild::cache_initramfs_hash(base_dir);
-    leviso::rebuild::cache_install_initramfs_hash(base_dir);
-
-    // Kernel payload (vmlinuz + modules)
-    let staging = out.join("staging");
-    let kernel_key = out.join(".kernel-inputs.hash");
-    if staging.join("boot/vmlinuz").exists() {
-        match distro_builder::artifact_store::read_input_key_file(&kernel_key)? {
-            Some(key) => {
-                if store.get("kernel_payload", &key)?.is_some() {
-                    println!("  [SKIP] kernel_payload (already stored)");
-                } else {
-                    match store.put_kernel_payload(
-                        &key,
-                        &staging,
-                        std::collections::BTreeMap::new(),
-                    ) {
-                        Ok(sha) => println!("  kernel_payload  stored blob={}", &sha[..16]),
-                        Err(e) => eprintln!("  [WARN] kernel_payload ingest failed: {:#}", e),
-                    }
-                }
-            }
-            None => println!(
-                "  [SKIP] kernel_payload (missing key {})",
-                kernel_key.display()
-            ),
-        }
-    } else {
-        println!("  [SKIP] kernel_payload (missing vmlinuz)");
-    }
-
-    // Rootfs
-    let rootfs = out.join(distro_spec::levitate::ROOTFS_NAME);
-    let rootfs_key = out.join(".rootfs-inputs.hash");
-    if rootfs.exists() {
-        match distro_builder::artifact_store::read_input_key_file(&rootfs_key)? {
-            Some(key) => {
-                if store.get("rootfs_erofs", &key)?.is_some() {
-                    println!("  [SKIP] rootfs_erofs (already stored)");
-                } else {
-                    match store.ingest_file_move_and_link(
-                        "rootfs_erofs",
-                        &key,
-                        &rootfs,
-                        std::collections::BTreeMap::new(),
-                    ) {
-                        Ok(sha) => println!("  rootfs_erofs    ingested blob={}", &sha[..16]),
-                        Err(e) => eprintln!("  [WARN] rootfs ingest failed: {:#}", e),
-                    }
-                }
-            }
-            None => println!(
-                "  [SKIP] rootfs_erofs (missing key {})",
-                rootfs_key.display()
-            ),
-        }
-    } else {
-        println!("  [SKIP] rootfs_erofs (missing filesystem.erofs)");
-    }
-
-    // Initramfs (live)
-    let initramfs = out.join(distro_spec::levitate::INITRAMFS_LIVE_OUTPUT);
-    let initramfs_key = out.join(".initramfs-inputs.hash");
-    if initramfs.exists() {
-        match distro_builder::artifact_store::read_input_key_file(&initramfs_key)? {
-            Some(key) => {
-                if store.get("initramfs", &key)?.is_some() {
-                    println!("  [SKIP] initramfs (already stored)");
-                } else {
-                    match store.ingest_file_move_and_link(
-                        "initramfs",
-                        &key,
-                        &initramfs,
-                        std::collections::BTreeMap::new(),
-                    ) {
-                        Ok(sha) => println!("  initramfs       ingested blob={}", &sha[..16]),
-                        Err(e) => eprintln!("  [WARN] initramfs ingest failed: {:#}", e),
-                    }
-                }
-            }
-            None => println!(
-                "  [SKIP] initramfs (missing key {})",
-                initramfs_key.display()
-            ),
-        }
-    } else {
-        println!("  [SKIP] initramfs (missing initramfs-live.cpio.gz)");
-    }
-
-    // Install initramfs
-    let install_initramfs = out.join(distro_spec::levitate::INITRAMFS_INSTALLED_OUTPUT);
-    let install_key = out.join(".install-initramfs-inputs.hash");
-    if install_initramfs.exists() {
-        match distro_builder::artifact_store::read_input_key_file(&install_key)? {
-            Some(key) => {
-                if store.get("install_initramfs", &key)?.is_some() {
-                    println!("  [SKIP] install_initramfs (already stored)");
-                } else {
-                    match store.ingest_file_move_and_link(
-                        "install_initramfs",
-                        &key,
-                        &install_initramfs,
-                        std::collections::BTreeMap::new(),
-                    ) {
-                        Ok(sha) => println!("  install_initramfs ingested blob={}", &sha[..16]),
-                        Err(e) => eprintln!("  [WARN] install initramfs ingest failed: {:#}", e),
-                    }
-                }
-            }
-            None => println!(
-                "  [SKIP] install_initramfs (missing key {})",
-                install_key.display()
-            ),
-        }
-    } else {
-        println!("  [SKIP] install_initramfs (missing initramfs-installed.img)");
-    }
-
-    // ISO (+ checksum, if present)
-    let iso = out.join(distro_spec::levitate::ISO_FILENAME);
-    if iso.exists() {
-        let iso_key = iso_input_key(&[
-            out.join(".kernel-inputs.hash"),
-            out.join(".rootfs-inputs.hash"),
-            out.join(".initramfs-inputs.hash"),
-            out.join(".install-initramfs-inputs.hash"),
-        ]);
-
-        match iso_key {
-            Some(key) => {
-                if store.get("iso", &key)?.is_some() {
-                    println!("  [SKIP] iso (already stored)");
-                } else {
-                    match store.ingest_file_move_and_link(
-                        "iso",
-                        &key,
-                        &iso,
-                        std::collections::BTreeMap::new(),
-                    ) {
-                        Ok(sha) => println!("  iso            ingested blob={}", &sha[..16]),
-                        Err(e) => eprintln!("  [WARN] iso ingest failed: {:#}", e),
-                    }
-                }
-
-                if let Some(checksum) = find_iso_checksum_file(&iso) {
-                    if checksum.exists() {
-                        if store.get("iso_checksum", &key)?.is_some() {
-                            println!("  [SKIP] iso_checksum (already stored)");
-                        } else {
-                            match store.ingest_file_move_and_link(
-                                "iso_checksum",
-                                &key,
-                                &checksum,
-                                std::collections::BTreeMap::new(),
-                            ) {
-                                Ok(sha) => {
-                                    println!("  iso_checksum    ingested blob={}", &sha[..16])
-                                }
-                                Err(e) => eprintln!("  [WARN] iso checksum ingest failed: {:#}", e),
-                            }
-                        }
-                    }
-                } else {
-                    println!("  [SKIP] iso_checksum (not found)");
-                }
-            }
-            None => println!("  [SKIP] iso (missing inputs-hash key)"),
-        }
-    } else {
-        println!("  [SKIP] iso (missing ISO file)");
-    }
-
-    Ok(())
-}
-
-fn ingest_acorn(base_dir: &Path, store: &ArtifactStore) -> Result<()> {
-    println!("== Ingest AcornOS ==");
-    let out = distro_builder::artifact_store::central_output_dir_for_distro(base_dir);
-    if !out.exists() {
-        println!("  [SKIP] No output dir at {}", out.display());
-        return Ok(());
-    }
-
-    // Ensure hash keys exist (no builds).
-    acornos::rebuild::cache_kernel_hash(base_dir);
-    acornos::rebuild::cache_rootfs_hash(base_dir);
-    acornos::rebuild::cache_initramfs_hash(base_dir);
-
-    // Kernel payload
-    let staging = out.join("staging");
-    let kernel_key = out.join(".kernel-inputs.hash");
-    if staging.join("boot/vmlinuz").exists() {
-        match distro_builder::artifact_store::read_input_key_file(&kernel_key)? {
-            Some(key) => {
-                if store.get("kernel_payload", &key)?.is_some() {
-                    println!("  [SKIP] kernel_payload (already stored)");
-                } else {
-                    match store.put_kernel_payload(
-                        &key,
-                        &staging,
-                        std::collections::BTreeMap::new(),
-                    ) {
-                        Ok(sha) => println!("  kernel_payload  stored blob={}", &sha[..16]),
-                        Err(e) => eprintln!("  [WARN] kernel_payload ingest failed: {:#}", e),
-                    }
-                }
-            }
-            None => println!(
-                "  [SKIP] kernel_payload (missing key {})",
-                kernel_key.display()
-            ),
-        }
-    } else {
-        println!("  [SKIP] kernel_payload (missing vmlinuz)");
-    }
+    distro.cache_input_hashes(base_dir);
 
-    // Rootfs
-    let rootfs = out.join(distro_spec::acorn::ROOTFS_NAME);
-    let rootfs_key = out.join(".rootfs-inputs.hash");
-    if rootfs.exists() {
-        match distro_builder::artifact_store::read_input_key_file(&rootfs_key)? {
-            Some(key) => {
-                if store.get("rootfs_erofs", &key)?.is_some() {
-                    println!("  [SKIP] rootfs_erofs (already stored)");
-                } else {
-                    match store.ingest_file_move_and_link(
-                        "rootfs_erofs",
-                        &key,
-                        &rootfs,
-                        std::collections::BTreeMap::new(),
-                    ) {
-                        Ok(sha) => println!("  rootfs_erofs    ingested blob={}", &sha[..16]),
-                        Err(e) => eprintln!("  [WARN] rootfs ingest failed: {:#}", e),
-                    }
-                }
+    for kind in artifacts.kinds() {
+        match artifacts.ingest(store, &out, kind, std::collections::BTreeMap::new()) {
+            Ok(IngestOutcome::Stored(sha)) => {
+                println!("  {:<17} ingested blob={}", kind.name, &sha[..16])
             }
-            None => println!(
-                "  [SKIP] rootfs_erofs (missing key {})",
-                rootfs_key.display()
-            ),
+            Ok(IngestOutcome::Skipped(why)) => println!("  [SKIP] {} ({})", kind.name, why),
+            Err(e) => eprintln!("  [WARN] {} ingest failed: {:#}", kind.name, e),
         }
-    } else {
-        println!("  [SKIP] rootfs_erofs (missing filesystem.erofs)");
-    }
-
-    // Initramfs (live)
-    let initramfs = out.join(distro_spec::acorn::INITRAMFS_LIVE_OUTPUT);
-    let initramfs_key = out.join(".initramfs-inputs.hash");
-    if initramfs.exists() {
-        match distro_builder::artifact_store::read_input_key_file(&initramfs_key)? {
-            Some(key) => {
-                if store.get("initramfs", &key)?.is_some() {
-                    println!("  [SKIP] initramfs (already stored)");
-                } else {
-                    match store.ingest_file_move_and_link(
-                        "initramfs",
-                        &key,
-                        &initramfs,
-                        std::collections::BTreeMap::new(),
-                    ) {
-                        Ok(sha) => println!("  initramfs       ingested blob={}", &sha[..16]),
-                        Err(e) => eprintln!("  [WARN] initramfs ingest failed: {:#}", e),
-                    }
-                }
-            }
-            None => println!(
-                "  [SKIP] initramfs (missing key {})",
-                initramfs_key.display()
-            ),
-        }
-    } else {
-        println!("  [SKIP] initramfs (missing initramfs-live.cpio.gz)");
-    }
-
-    // ISO (+ checksum, if present)
-    let iso = out.join(distro_spec::acorn::ISO_FILENAME);
-    if iso.exists() {
-        let iso_key = iso_input_key(&[
-            out.join(".kernel-inputs.hash"),
-            out.join(".rootfs-inputs.hash"),
-            out.join(".initramfs-inputs.hash"),
-        ]);
-
-        match iso_key {
-            Some(key) => {
-                if store.get("iso", &key)?.is_some() {
-                    println!("  [SKIP] iso (already stored)");
-                } else {
-                    match store.ingest_file_move_and_link(
-                        "iso",
-                        &key,
-                        &iso,
-                        std::collections::BTreeMap::new(),
-                    ) {
-                        Ok(sha) => println!("  iso            ingested blob={}", &sha[..16]),
-                        Err(e) => eprintln!("  [WARN] iso ingest failed: {:#}", e),
-                    }
-                }
-
-                if let Some(checksum) = find_iso_checksum_file(&iso) {
-                    if checksum.exists() {
-                        if store.get("iso_checksum", &key)?.is_some() {
-                            println!("  [SKIP] iso_checksum (already stored)");
-                        } else {
-                            match store.ingest_file_move_and_link(
-                                "iso_checksum",
-                                &key,
-                                &checksum,
-                                std::collections::BTreeMap::new(),
-                            ) {
-                                Ok(sha) => {
-                                    println!("  iso_checksum    ingested blob={}", &sha[..16])
-                                }
-                                Err(e) => eprintln!("  [WARN] iso checksum ingest failed: {:#}", e),
-                            }
-                        }
-                    }
-                } else {
-                    println!("  [SKIP] iso_checksum (not found)");
-                }
-            }
-            None => println!("  [SKIP] iso (missing inputs-hash key)"),
-        }
-    } else {
-        println!("  [SKIP] iso (missing ISO file)");
     }
 
     Ok(())
 }
 
-fn ingest_iuppiter(base_dir: &Path, store: &ArtifactStore) -> Result<()> {
-    println!("== Ingest IuppiterOS ==");
-    let out = distro_builder::artifact_store::central_output_dir_for_distro(base_dir);
-    if !out.exists() {
-        println!("  [SKIP] No output dir at {}", out.display());
-        return Ok(());
-    }
-
-    // Ensure hash keys exist (no builds).
-    iuppiteros::rebuild::cache_kernel_hash(base_dir);
-    iuppiteros::rebuild::cache_rootfs_hash(base_dir);
-    iuppiteros::rebuild::cache_initramfs_hash(base_dir);
-
-    // Kernel payload
-    let staging = out.join("staging");
-    let kernel_key = out.join(".kernel-inputs.hash");
-    if staging.join("boot/vmlinuz").exists() {
-        match distro_builder::artifact_store::read_input_key_file(&kernel_key)? {
-            Some(key) => {
-                if store.get("kernel_payload", &key)?.is_some() {
-                    println!("  [SKIP] kernel_payload (already stored)");
-                } else {
-                    match store.put_kernel_payload(
-                        &key,
-                        &staging,
-                        std::collections::BTreeMap::new(),
-                    ) {
-                        Ok(sha) => println!("  kernel_payload  stored blob={}", &sha[..16]),
-                        Err(e) => eprintln!("  [WARN] kernel_payload ingest failed: {:#}", e),
-                    }
-                }
-            }
-            None => println!(
-                "  [SKIP] kernel_payload (missing key {})",
-                kernel_key.display()
+fn print_fsck_report(report: &fsck::FsckReport) {
+    println!("Artifact store: {}", report.store_root);
+    println!(
+        "  Blobs checked:      {} ({})",
+        report.blobs_checked,
+        fmt_bytes(report.bytes_checked)
+    );
+    println!("  Corrupt blobs:      {}", report.corrupt_blobs.len());
+    println!("  Unreadable blobs:   {}", report.unreadable_blobs.len());
+    println!("  Dangling entries:   {}", report.dangling_entries.len());
+    println!(
+        "  Outputs:            {} unlinked, {} relinked, {} diverged",
+        report.outputs_with("unlinked"),
+        report.outputs_with("relinked"),
+        report.outputs_with("diverged")
+    );
+
+    for b in &report.corrupt_blobs {
+        match &b.quarantined_to {
+            Some(to) => println!(
+                "  [CORRUPT] {} (actual {}) quarantined to {}",
+                b.sha256, b.actual_sha256, to
             ),
+            None => println!("  [CORRUPT] {} (actual {})", b.sha256, b.actual_sha256),
         }
-    } else {
-        println!("  [SKIP] kernel_payload (missing vmlinuz)");
-    }
-
-    // Rootfs
-    let rootfs = out.join(distro_spec::iuppiter::ROOTFS_NAME);
-    let rootfs_key = out.join(".rootfs-inputs.hash");
-    if rootfs.exists() {
-        match distro_builder::artifact_store::read_input_key_file(&rootfs_key)? {
-            Some(key) => {
-                if store.get("rootfs_erofs", &key)?.is_some() {
-                    println!("  [SKIP] rootfs_erofs (already stored)");
-                } else {
-                    match store.ingest_file_move_and_link(
-                        "rootfs_erofs",
-                        &key,
-                        &rootfs,
-                        std::collections::BTreeMap::new(),
-                    ) {
-                        Ok(sha) => println!("  rootfs_erofs    ingested blob={}", &sha[..16]),
-                        Err(e) => eprintln!("  [WARN] rootfs ingest failed: {:#}", e),
-                    }
-                }
-            }
-            None => println!(
-                "  [SKIP] rootfs_erofs (missing key {})",
-                rootfs_key.display()
-            ),
-        }
-    } else {
-        println!("  [SKIP] rootfs_erofs (missing filesystem.erofs)");
     }
-
-    // Initramfs (live)
-    let initramfs = out.join(distro_spec::iuppiter::INITRAMFS_LIVE_OUTPUT);
-    let initramfs_key = out.join(".initramfs-inputs.hash");
-    if initramfs.exists() {
-        match distro_builder::artifact_store::read_input_key_file(&initramfs_key)? {
-            Some(key) => {
-                if store.get("initramfs", &key)?.is_some() {
-                    println!("  [SKIP] initramfs (already stored)");
-                } else {
-                    match store.ingest_file_move_and_link(
-                        "initramfs",
-                        &key,
-                        &initramfs,
-                        std::collections::BTreeMap::new(),
-                    ) {
-                        Ok(sha) => println!("  initramfs       ingested blob={}", &sha[..16]),
-                        Err(e) => eprintln!("  [WARN] initramfs ingest failed: {:#}", e),
-                    }
-                }
-            }
-            None => println!(
-                "  [SKIP] initramfs (missing key {})",
-                initramfs_key.display()
-            ),
-        }
-    } else {
-        println!("  [SKIP] initramfs (missing initramfs-live.cpio.gz)");
+    for b in &report.unreadable_blobs {
+        println!("  [UNREADABLE] {} {}: {}", b.sha256, b.path, b.error);
     }
-
-    // ISO (+ checksum, if present)
-    let iso = out.join(distro_spec::iuppiter::ISO_FILENAME);
-    if iso.exists() {
-        let iso_key = iso_input_key(&[
-            out.join(".kernel-inputs.hash"),
-            out.join(".rootfs-inputs.hash"),
-            out.join(".initramfs-inputs.hash"),
-        ]);
-
-        match iso_key {
-            Some(key) => {
-                if store.get("iso", &key)?.is_some() {
-                    println!("  [SKIP] iso (already stored)");
-                } else {
-                    match store.ingest_file_move_and_link(
-                        "iso",
-                        &key,
-                        &iso,
-                        std::collections::BTreeMap::new(),
-                    ) {
-                        Ok(sha) => println!("  iso            ingested blob={}", &sha[..16]),
-                        Err(e) => eprintln!("  [WARN] iso ingest failed: {:#}", e),
-                    }
-                }
-
-                if let Some(checksum) = find_iso_checksum_file(&iso) {
-                    if checksum.exists() {
-                        if store.get("iso_checksum", &key)?.is_some() {
-                            println!("  [SKIP] iso_checksum (already stored)");
-                        } else {
-                            match store.ingest_file_move_and_link(
-                                "iso_checksum",
-                                &key,
-                                &checksum,
-                                std::collections::BTreeMap::new(),
-                            ) {
-                                Ok(sha) => {
-                                    println!("  iso_checksum    ingested blob={}", &sha[..16])
-                                }
-                                Err(e) => eprintln!("  [WARN] iso checksum ingest failed: {:#}", e),
-                            }
-                        }
-                    }
-                } else {
-                    println!("  [SKIP] iso_checksum (not found)");
-                }
+    for e in &report.dangling_entries {
+        println!(
+            "  [DANGLING] {} key={} blob={} ({}){}",
+            e.kind,
+            e.input_key,
+            &e.blob_sha256[..16.min(e.blob_sha256.len())],
+            e.reason,
+            match (&e.restored_from, e.dropped) {
+                (Some(from), _) => format!(" restored from {}", from),
+                (None, true) => " dropped from index".to_string(),
+                (None, false) => String::new(),
             }
-            None => println!("  [SKIP] iso (missing inputs-hash key)"),
-        }
-    } else {
-        println!("  [SKIP] iso (missing ISO file)");
-    }
-
-    Ok(())
-}
-
-fn iso_input_key(inputs_hash_files: &[PathBuf]) -> Option<String> {
-    let refs: Vec<&Path> = inputs_hash_files.iter().map(|p| p.as_path()).collect();
-    distro_builder::cache::hash_files(&refs)
-}
-
-fn find_iso_checksum_file(iso_path: &Path) -> Option<PathBuf> {
-    // Expected: replace extension (foo.iso -> foo.sha512)
-    let replaced = iso_path.with_extension("sha512");
-    if replaced.exists() {
-        return Some(replaced);
+        );
     }
-
-    // Older/buggy case: appended suffix (foo.iso -> foo.iso.sha512)
-    let appended = PathBuf::from(format!("{}.sha512", iso_path.display()));
-    if appended.exists() {
-        return Some(appended);
+    for o in &report.outputs {
+        println!(
+            "  [{}] {} {} ({})",
+            o.status.to_uppercase(),
+            o.distro,
+            o.kind,
+            o.path
+        );
     }
-
-    None
 }
 
 fn find_repo_root(start: PathBuf) -> Result<PathBuf> {
@@ -0,0 +1,585 @@
+//! Table of distros recart manages and the artifact kinds each one stores.
+//!
+//! Kinds are not hard-coded: they are enumerated from the variant contract's ring1 transforms
+//! and ring0 release entries (`logical_name` + `output_names`). Input keys stay anchored to the
+//! input-hash files the distro crates record, so they move whenever a build input does.
+
+use anyhow::{bail, Context, Result};
+use distro_builder::artifact_store::{self, ArtifactStore};
+use distro_contract::load_variant_contract_bundle_for_distro_from;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub struct Distro {
+    /// Crate directory under the repo root (also the web UI / API identifier).
+    pub dir: &'static str,
+    pub label: &'static str,
+    /// Directory under `distro-variants/` holding the ring contract.
+    pub variant: &'static str,
+    /// Name the ISO builder writes; the contract's `artifact.iso` output is stored under it.
+    pub iso_filename: &'static str,
+    cache_input_hashes: fn(&Path),
+}
+
+pub static DISTROS: &[Distro] = &[
+    Distro {
+        dir: "leviso",
+        label: "LevitateOS",
+        variant: "levitate",
+        iso_filename: distro_spec::levitate::ISO_FILENAME,
+        cache_input_hashes: cache_leviso_hashes,
+    },
+    Distro {
+        dir: "AcornOS",
+        label: "AcornOS",
+        variant: "acorn",
+        iso_filename: distro_spec::acorn::ISO_FILENAME,
+        cache_input_hashes: cache_acorn_hashes,
+    },
+    Distro {
+        dir: "IuppiterOS",
+        label: "IuppiterOS",
+        variant: "iuppiter",
+        iso_filename: distro_spec::iuppiter::ISO_FILENAME,
+        cache_input_hashes: cache_iuppiter_hashes,
+    },
+    Distro {
+        dir: "RalphOS",
+        label: "RalphOS",
+        variant: "ralph",
+        iso_filename: distro_spec::ralph::ISO_FILENAME,
+        cache_input_hashes: cache_ralph_hashes,
+    },
+];
+
+fn cache_leviso_hashes(base_dir: &Path) {
+    leviso::rebuild::cache_kernel_hash(base_dir);
+    leviso::rebuild::cache_rootfs_hash(base_dir);
+    leviso::rebuild::cache_initramfs_hash(base_dir);
+    leviso::rebuild::cache_install_initramfs_hash(base_dir);
+}
+
+fn cache_acorn_hashes(base_dir: &Path) {
+    acornos::rebuild::cache_kernel_hash(base_dir);
+    acornos::rebuild::cache_rootfs_hash(base_dir);
+    acornos::rebuild::cache_initramfs_hash(base_dir);
+}
+
+fn cache_iuppiter_hashes(base_dir: &Path) {
+    iuppiteros::rebuild::cache_kernel_hash(base_dir);
+    iuppiteros::rebuild::cache_rootfs_hash(base_dir);
+    iuppiteros::rebuild::cache_initramfs_hash(base_dir);
+}
+
+fn cache_ralph_hashes(base_dir: &Path) {
+    ralphos::rebuild::cache_kernel_hash(base_dir);
+    ralphos::rebuild::cache_rootfs_hash(base_dir);
+    ralphos::rebuild::cache_initramfs_hash(base_dir);
+}
+
+pub fn find(dir: &str) -> Option<&'static Distro> {
+    DISTROS.iter().find(|d| d.dir == dir)
+}
+
+impl Distro {
+    pub fn base_dir(&self, repo_root: &Path) -> PathBuf {
+        repo_root.join(self.dir)
+    }
+
+    /// Best-effort only: writes `.hash` keys for whatever inputs exist, never builds.
+    pub fn cache_input_hashes(&self, base_dir: &Path) {
+        (self.cache_input_hashes)(base_dir)
+    }
+
+    /// Loads the storable kinds and dependency graph from the variant's ring contract.
+    pub fn load_artifacts(&self, repo_root: &Path) -> Result<DistroArtifacts> {
+        let bundle = load_variant_contract_bundle_for_distro_from(repo_root, self.variant)
+            .with_context(|| {
+                format!("loading canonical variant contract for '{}'", self.variant)
+            })?;
+        let ring2 = &bundle.contract.ring2_products;
+        let ring1 = &bundle.contract.ring1_transforms;
+        let ring0 = &bundle.contract.ring0_release;
+
+        // Every ring2 product is carved out of the base rootfs tree, so one without `extends`
+        // implicitly depends on it.
+        macro_rules! product {
+            ($p:expr) => {
+                Declaration {
+                    logical_name: $p.logical_name.clone(),
+                    dependencies: match &$p.extends {
+                        Some(parent) => vec![parent.clone()],
+                        None if $p.logical_name != ROOTFS_BASE => vec![ROOTFS_BASE.to_string()],
+                        None => vec![],
+                    },
+                    output_names: vec![],
+                    iso: false,
+                }
+            };
+        }
+        macro_rules! artifact {
+            ($t:expr) => {
+                Declaration {
+                    logical_name: $t.logical_name.clone(),
+                    dependencies: $t.dependencies.clone(),
+                    output_names: $t.output_names.clone(),
+                    iso: false,
+                }
+            };
+        }
+        let declarations = vec![
+            product!(ring2.rootfs_base),
+            product!(ring2.live_overlay),
+            product!(ring2.boot_live),
+            product!(ring2.live_tools),
+            product!(ring2.boot_installed),
+            product!(ring2.kernel_staging),
+            artifact!(ring1.rootfs_image),
+            artifact!(ring1.overlay_image),
+            artifact!(ring1.initramfs_live),
+            artifact!(ring1.initramfs_installed),
+            artifact!(ring1.live_uki),
+            artifact!(ring1.installed_uki),
+            Declaration {
+                output_names: vec![self.iso_filename.to_string()],
+                iso: true,
+                ..artifact!(ring0.iso)
+            },
+            artifact!(ring0.disk_image),
+        ];
+        DistroArtifacts::from_declarations(declarations)
+            .with_context(|| format!("deriving artifact kinds for {}", self.label))
+    }
+}
+
+/// The staged kernel (vmlinuz + modules) is a ring2 input rather than a declared artifact,
+/// so the contract never lists it; it is always storable.
+const KERNEL_PAYLOAD: &str = "kernel_payload";
+const KERNEL_STAGING: &str = "product.kernel.staging";
+const KERNEL_KEY_FILE: &str = ".kernel-inputs.hash";
+const ROOTFS_BASE: &str = "product.rootfs.base";
+const ISO: &str = "artifact.iso";
+
+/// Graph nodes whose input keys the distro crates already record (`rebuild::cache_*_hash`),
+/// in the order the ISO builders combine them into the ISO key.
+const RECORDED_KEYS: &[(&str, &str)] = &[
+    (KERNEL_STAGING, KERNEL_KEY_FILE),
+    (ROOTFS_BASE, ".rootfs-inputs.hash"),
+    ("artifact.rootfs.erofs", ".rootfs-inputs.hash"),
+    ("artifact.initramfs.live", ".initramfs-inputs.hash"),
+    (
+        "artifact.initramfs.installed",
+        ".install-initramfs-inputs.hash",
+    ),
+];
+
+/// Store index names that predate contract-derived kinds, kept so existing entries stay valid.
+const LEGACY_KIND_NAMES: &[(&str, &str)] = &[
+    ("artifact.initramfs.live", "initramfs"),
+    ("artifact.initramfs.installed", "install_initramfs"),
+];
+
+/// Deepest dependency chain followed before the graph is treated as cyclic.
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KindSource {
+    KernelPayload,
+    Output,
+    /// `.sha512` sidecar of an ISO output; shares the ISO's key.
+    Checksum,
+}
+
+#[derive(Debug, Clone)]
+pub struct ArtifactKind {
+    /// Store index name.
+    pub name: String,
+    /// Contract node the input key is computed for.
+    pub logical_name: String,
+    /// File in the output dir (for checksums, the ISO being checksummed).
+    output: String,
+    source: KindSource,
+}
+
+/// One `logical_name` entry of the contract, reduced to what kinds and keys need.
+/// Entries a variant does not declare come through with an empty logical name.
+struct Declaration {
+    logical_name: String,
+    dependencies: Vec<String>,
+    output_names: Vec<String>,
+    iso: bool,
+}
+
+struct Node {
+    dependencies: Vec<String>,
+    /// Declared shape, so edits to the contract itself change the key.
+    fingerprint: String,
+}
+
+pub struct DistroArtifacts {
+    kinds: Vec<ArtifactKind>,
+    nodes: BTreeMap<String, Node>,
+    /// Recorded input-hash files of every declared leaf, mixed into each derived key.
+    leaves: Vec<&'static str>,
+}
+
+/// Result of ingesting one artifact kind.
+pub enum IngestOutcome {
+    /// Moved into the store; carries the blob sha256.
+    Stored(String),
+    Skipped(&'static str),
+}
+
+impl DistroArtifacts {
+    fn from_declarations(declarations: Vec<Declaration>) -> Result<Self> {
+        let mut nodes = BTreeMap::new();
+        let mut kinds = vec![ArtifactKind {
+            name: KERNEL_PAYLOAD.to_string(),
+            logical_name: KERNEL_STAGING.to_string(),
+            output: "staging/boot/vmlinuz".to_string(),
+            source: KindSource::KernelPayload,
+        }];
+
+        for decl in declarations {
+            if decl.logical_name.is_empty() {
+                continue;
+            }
+            let base = kind_base_name(&decl.logical_name);
+            for output in &decl.output_names {
+                let name = if decl.output_names.len() == 1 {
+                    base.clone()
+                } else {
+                    format!("{base}.{output}")
+                };
+                kinds.push(ArtifactKind {
+                    name: name.clone(),
+                    logical_name: decl.logical_name.clone(),
+                    output: output.clone(),
+                    source: KindSource::Output,
+                });
+                if decl.iso {
+                    kinds.push(ArtifactKind {
+                        name: format!("{name}_checksum"),
+                        logical_name: decl.logical_name.clone(),
+                        output: output.clone(),
+                        source: KindSource::Checksum,
+                    });
+                }
+            }
+
+            let fingerprint = format!(
+                "dependencies={}\noutputs={}\n",
+                decl.dependencies.join(","),
+                decl.output_names.join(",")
+            );
+            nodes.insert(
+                decl.logical_name,
+                Node {
+                    dependencies: decl.dependencies,
+                    fingerprint,
+                },
+            );
+        }
+
+        for (name, node) in &nodes {
+            for dep in &node.dependencies {
+                if !nodes.contains_key(dep) && recorded_key_file(dep).is_none() {
+                    bail!(
+                        "'{}' depends on '{}', which the contract never declares",
+                        name,
+                        dep
+                    );
+                }
+            }
+        }
+
+        let mut leaves: Vec<&'static str> = Vec::new();
+        for (logical, file) in RECORDED_KEYS {
+            if (*logical == KERNEL_STAGING || nodes.contains_key(*logical))
+                && !leaves.contains(file)
+            {
+                leaves.push(file);
+            }
+        }
+
+        Ok(Self {
+            kinds,
+            nodes,
+            leaves,
+        })
+    }
+
+    pub fn kinds(&self) -> &[ArtifactKind] {
+        &self.kinds
+    }
+
+    pub fn find(&self, name: &str) -> Option<&ArtifactKind> {
+        self.kinds.iter().find(|k| k.name == name)
+    }
+
+    /// On-disk location of `kind` in the output dir. The kernel payload is represented by
+    /// `staging/boot/vmlinuz`; the rest of the payload lives alongside it in `staging/`.
+    pub fn output_path(&self, kind: &ArtifactKind, out_dir: &Path) -> PathBuf {
+        let path = out_dir.join(&kind.output);
+        match kind.source {
+            KindSource::Checksum => {
+                find_iso_checksum_file(&path).unwrap_or_else(|| path.with_extension("sha512"))
+            }
+            KindSource::KernelPayload | KindSource::Output => path,
+        }
+    }
+
+    /// Recorded key for nodes the distro crates hash themselves. The ISO and its checksum keep
+    /// the key the ISO builders use (the combined leaf hash files); every other derived kind
+    /// hashes its declared dependency graph together with those leaves, since the contract
+    /// does not list every input a builder reads. `None` when any recorded key is missing.
+    pub fn input_key(&self, kind: &ArtifactKind, out_dir: &Path) -> Result<Option<String>> {
+        if let Some(file) = recorded_key_file(&kind.logical_name) {
+            return artifact_store::read_input_key_file(&out_dir.join(file));
+        }
+        let files: Vec<PathBuf> = self.leaves.iter().map(|f| out_dir.join(f)).collect();
+        let refs: Vec<&Path> = files.iter().map(|p| p.as_path()).collect();
+        let Some(leaf_key) = distro_builder::cache::hash_files(&refs) else {
+            return Ok(None);
+        };
+        if kind.logical_name == ISO {
+            return Ok(Some(leaf_key));
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(self.node_key(&kind.logical_name, 0)?.as_bytes());
+        hasher.update(b"\n");
+        hasher.update(leaf_key.as_bytes());
+        Ok(Some(format!("{:x}", hasher.finalize())))
+    }
+
+    /// sha256 over a node's declaration and its dependencies' graph keys.
+    fn node_key(&self, name: &str, depth: usize) -> Result<String> {
+        if depth > MAX_DEPTH {
+            bail!("dependency cycle through '{}' in the ring contract", name);
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(name.as_bytes());
+        hasher.update(b"\n");
+        match self.nodes.get(name) {
+            Some(node) => {
+                hasher.update(node.fingerprint.as_bytes());
+                for dep in &node.dependencies {
+                    let key = self.node_key(dep, depth + 1)?;
+                    hasher.update(format!("{}={}\n", dep, key).as_bytes());
+                }
+            }
+            None if recorded_key_file(name).is_some() => {}
+            None => bail!("'{}' is not declared in the ring contract", name),
+        }
+        Ok(format!("{:x}", hasher.finalize()))
+    }
+
+    /// Move an existing output into the store (no builds).
+    pub fn ingest(
+        &self,
+        store: &ArtifactStore,
+        out_dir: &Path,
+        kind: &ArtifactKind,
+        meta: BTreeMap<String, serde_json::Value>,
+    ) -> Result<IngestOutcome> {
+        let path = self.output_path(kind, out_dir);
+        if !path.exists() {
+            return Ok(IngestOutcome::Skipped(match kind.source {
+                KindSource::KernelPayload => "missing vmlinuz",
+                KindSource::Checksum => "missing checksum",
+                KindSource::Output => "missing file",
+            }));
+        }
+        let Some(key) = self.input_key(kind, out_dir)? else {
+            return Ok(IngestOutcome::Skipped("missing key"));
+        };
+        if store.get(&kind.name, &key)?.is_some() {
+            return Ok(IngestOutcome::Skipped("already stored"));
+        }
+        let sha = match kind.source {
+            KindSource::KernelPayload => {
+                store.put_kernel_payload(&key, &out_dir.join("staging"), meta)?
+            }
+            KindSource::Output | KindSource::Checksum => {
+                store.ingest_file_move_and_link(&kind.name, &key, &path, meta)?
+            }
+        };
+        Ok(IngestOutcome::Stored(sha))
+    }
+
+    /// Materialize `kind` back into the output dir if it is missing there and stored.
+    pub fn restore(
+        &self,
+        store: &ArtifactStore,
+        out_dir: &Path,
+        kind: &ArtifactKind,
+    ) -> Result<bool> {
+        if kind.source == KindSource::KernelPayload {
+            return artifact_store::try_restore_kernel_payload_from_key(
+                store,
+                &out_dir.join(KERNEL_KEY_FILE),
+                &out_dir.join("staging"),
+            );
+        }
+        let path = self.output_path(kind, out_dir);
+        let Some(key) = self.input_key(kind, out_dir)? else {
+            return Ok(false);
+        };
+        if path.exists() || store.get(&kind.name, &key)?.is_none() {
+            return Ok(false);
+        }
+        store.materialize_to(&kind.name, &key, &path)?;
+        Ok(true)
+    }
+}
+
+fn recorded_key_file(logical_name: &str) -> Option<&'static str> {
+    RECORDED_KEYS
+        .iter()
+        .find(|(n, _)| *n == logical_name)
+        .map(|(_, f)| *f)
+}
+
+/// `artifact.overlay.erofs` -> `overlay_erofs`, unless a legacy index name exists.
+fn kind_base_name(logical_name: &str) -> String {
+    if let Some((_, legacy)) = LEGACY_KIND_NAMES.iter().find(|(n, _)| *n == logical_name) {
+        return legacy.to_string();
+    }
+    logical_name
+        .strip_prefix("artifact.")
+        .unwrap_or(logical_name)
+        .replace('.', "_")
+}
+
+pub fn find_iso_checksum_file(iso_path: &Path) -> Option<PathBuf> {
+    // Expected: replace extension (foo.iso -> foo.sha512)
+    let replaced = iso_path.with_extension("sha512");
+    if replaced.exists() {
+        return Some(replaced);
+    }
+
+    // Older/buggy case: appended suffix (foo.iso -> foo.iso.sha512)
+    let appended = PathBuf::from(format!("{}.sha512", iso_path.display()));
+    if appended.exists() {
+        return Some(appended);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decl(logical_name: &str, dependencies: &[&str], output_names: &[&str]) -> Declaration {
+        Declaration {
+            logical_name: logical_name.to_string(),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            output_names: output_names.iter().map(|o| o.to_string()).collect(),
+            iso: false,
+        }
+    }
+
+    fn demo_contract() -> Vec<Declaration> {
+        vec![
+            decl("product.rootfs.base", &[], &[]),
+            decl("product.payload.boot.live", &["product.rootfs.base"], &[]),
+            decl("product.kernel.staging", &[], &[]),
+            decl(
+                "artifact.rootfs.erofs",
+                &["product.rootfs.base"],
+                &["filesystem.erofs"],
+            ),
+            decl(
+                "artifact.initramfs.live",
+                &["product.payload.boot.live", "product.kernel.staging"],
+                &["initramfs-live.cpio.gz"],
+            ),
+            decl(
+                "artifact.uki.live",
+                &["product.payload.boot.live", "product.kernel.staging"],
+                &["demo-live.efi", "demo-debug.efi"],
+            ),
+            // Not declared by this variant.
+            decl("", &[], &[]),
+            Declaration {
+                iso: true,
+                ..decl(
+                    "artifact.iso",
+                    &["artifact.rootfs.erofs", "artifact.uki.live"],
+                    &["demo-x86_64.iso"],
+                )
+            },
+        ]
+    }
+
+    #[test]
+    fn kinds_come_from_ring_contract() {
+        let artifacts = DistroArtifacts::from_declarations(demo_contract()).unwrap();
+        let mut names: Vec<&str> = artifacts.kinds().iter().map(|k| k.name.as_str()).collect();
+        names.sort();
+        assert_eq!(
+            names,
+            [
+                "initramfs",
+                "iso",
+                "iso_checksum",
+                "kernel_payload",
+                "rootfs_erofs",
+                "uki_live.demo-debug.efi",
+                "uki_live.demo-live.efi",
+            ]
+        );
+
+        let broken = vec![decl(
+            "artifact.overlay.erofs",
+            &["product.payload.live_overlay"],
+            &["overlayfs.erofs"],
+        )];
+        assert!(DistroArtifacts::from_declarations(broken).is_err());
+    }
+
+    #[test]
+    fn keys_follow_recorded_input_hashes() {
+        let out = std::env::temp_dir().join(format!("recart-registry-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&out);
+        std::fs::create_dir_all(&out).unwrap();
+        for (file, key) in [
+            (".kernel-inputs.hash", "k1"),
+            (".rootfs-inputs.hash", "r1"),
+            (".initramfs-inputs.hash", "i1"),
+        ] {
+            std::fs::write(out.join(file), key).unwrap();
+        }
+        let artifacts = DistroArtifacts::from_declarations(demo_contract()).unwrap();
+        let key = |name: &str| {
+            artifacts
+                .input_key(artifacts.find(name).unwrap(), &out)
+                .unwrap()
+        };
+
+        // The ISO keeps the builders' key over the component key files.
+        let legacy = distro_builder::cache::hash_files(&[
+            out.join(".kernel-inputs.hash").as_path(),
+            out.join(".rootfs-inputs.hash").as_path(),
+            out.join(".initramfs-inputs.hash").as_path(),
+        ]);
+        assert!(legacy.is_some());
+        assert_eq!(key("iso"), legacy);
+        assert_eq!(key("iso_checksum"), legacy);
+
+        // The UKI embeds the initramfs, which its declared dependencies do not name.
+        let uki = key("uki_live.demo-live.efi");
+        std::fs::write(out.join(".initramfs-inputs.hash"), "i2").unwrap();
+        let uki_after = key("uki_live.demo-live.efi");
+        std::fs::remove_file(out.join(".kernel-inputs.hash")).unwrap();
+        let uki_missing = key("uki_live.demo-live.efi");
+        std::fs::remove_dir_all(&out).unwrap();
+
+        assert!(uki.is_some());
+        assert_ne!(uki, uki_after);
+        assert_eq!(uki_missing, None);
+    }
+}
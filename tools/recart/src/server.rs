@@ -1,4 +1,5 @@
-use crate::{find_iso_checksum_file, iso_input_key};
+use crate::fsck::{self, hardlink_info};
+use crate::registry::{self, IngestOutcome};
 use anyhow::{Context, Result};
 use axum::body::Body;
 use axum::extract::{Path as AxPath, Query, State};
@@ -110,6 +111,7 @@ pub async fn serve(
         .route("/api/v1/blob/:sha256", get(api_blob_download))
         .route("/api/v1/actions/gc", post(api_gc))
         .route("/api/v1/actions/prune", post(api_prune))
+        .route("/api/v1/actions/fsck", post(api_fsck))
         .route(
             "/api/v1/distro/:distro/ingest_existing",
             post(api_ingest_existing),
@@ -220,20 +222,20 @@ struct DistroInfo {
 }
 
 async fn api_distros() -> Json<Vec<DistroInfo>> {
-    Json(vec![
-        DistroInfo {
-            dir: "leviso".to_string(),
-            label: "LevitateOS".to_string(),
-        },
-        DistroInfo {
-            dir: "AcornOS".to_string(),
-            label: "AcornOS".to_string(),
-        },
-        DistroInfo {
-            dir: "IuppiterOS".to_string(),
-            label: "IuppiterOS".to_string(),
-        },
-    ])
+    Json(
+        registry::DISTROS
+            .iter()
+            .map(|d| DistroInfo {
+                dir: d.dir.to_string(),
+                label: d.label.to_string(),
+            })
+            .collect(),
+    )
+}
+
+fn lookup_distro(distro_dir: &str) -> Result<&'static registry::Distro, ApiError> {
+    registry::find(distro_dir)
+        .ok_or_else(|| ApiError::BadRequest(format!("Unknown distro dir '{}'", distro_dir)))
 }
 
 #[derive(Serialize)]
@@ -268,21 +270,13 @@ async fn api_distro_summary(
     State(st): State<Arc<AppState>>,
     AxPath(distro_dir): AxPath<String>,
 ) -> Result<Json<DistroSummaryResp>, ApiError> {
-    let base_dir = st.repo_root.join(&distro_dir);
+    let distro = lookup_distro(&distro_dir)?;
+    let base_dir = distro.base_dir(&st.repo_root);
     let out_dir = distro_builder::artifact_store::central_output_dir_for_distro(&base_dir);
 
     // Build rows even if out_dir is missing; the UI should show missing artifacts.
-    let rows = match distro_dir.as_str() {
-        "leviso" => summary_leviso(&st.store, &out_dir)?,
-        "AcornOS" => summary_acorn(&st.store, &out_dir)?,
-        "IuppiterOS" => summary_iuppiter(&st.store, &out_dir)?,
-        _ => {
-            return Err(ApiError::BadRequest(format!(
-                "Unknown distro dir '{}'",
-                distro_dir
-            )));
-        }
-    };
+    let artifacts = distro.load_artifacts(&st.repo_root)?;
+    let rows = summary_rows(&st.store, &artifacts, &out_dir)?;
 
     Ok(Json(DistroSummaryResp {
         distro: distro_dir,
@@ -305,10 +299,6 @@ fn file_info(path: &Path) -> (bool, Option<u64>, Option<u64>) {
     (true, Some(size), mtime)
 }
 
-fn read_key_file(path: &Path) -> Result<Option<String>, ApiError> {
-    Ok(distro_builder::artifact_store::read_input_key_file(path)?)
-}
-
 fn store_presence(
     store: &ArtifactStore,
     kind: &str,
@@ -356,251 +346,26 @@ fn out_rel_path(out_dir: &Path, path: &Path) -> String {
     }
 }
 
-fn summary_leviso(store: &ArtifactStore, out_dir: &Path) -> Result<Vec<ArtifactRow>, ApiError> {
-    use distro_spec::levitate::{
-        INITRAMFS_INSTALLED_OUTPUT, INITRAMFS_LIVE_OUTPUT, ISO_FILENAME, ROOTFS_NAME,
-    };
-
-    let rootfs = out_dir.join(ROOTFS_NAME);
-    let initramfs = out_dir.join(INITRAMFS_LIVE_OUTPUT);
-    let install_initramfs = out_dir.join(INITRAMFS_INSTALLED_OUTPUT);
-    let iso = out_dir.join(ISO_FILENAME);
-    let checksum = find_iso_checksum_file(&iso).unwrap_or_else(|| iso.with_extension("sha512"));
-    let vmlinuz = out_dir.join("staging/boot/vmlinuz");
-
-    let k_kernel = read_key_file(&out_dir.join(".kernel-inputs.hash"))?;
-    let k_rootfs = read_key_file(&out_dir.join(".rootfs-inputs.hash"))?;
-    let k_initramfs = read_key_file(&out_dir.join(".initramfs-inputs.hash"))?;
-    let k_install = read_key_file(&out_dir.join(".install-initramfs-inputs.hash"))?;
-
-    let iso_key = iso_input_key(&[
-        out_dir.join(".kernel-inputs.hash"),
-        out_dir.join(".rootfs-inputs.hash"),
-        out_dir.join(".initramfs-inputs.hash"),
-        out_dir.join(".install-initramfs-inputs.hash"),
-    ]);
-
-    let mut out = vec![];
-
-    {
-        let (exists, size, mtime) = file_info(&vmlinuz);
-        out.push(ArtifactRow {
-            kind: "kernel_payload".to_string(),
-            rel_path: out_rel_path(out_dir, &vmlinuz),
-            exists,
-            size_bytes: size,
-            mtime_unix: mtime,
-            input_key: k_kernel.clone(),
-            store: store_presence(store, "kernel_payload", k_kernel.as_deref(), Some(&vmlinuz))?,
-        });
-    }
-
-    for (kind, path, key, store_kind) in [
-        ("rootfs_erofs", &rootfs, k_rootfs.clone(), "rootfs_erofs"),
-        ("initramfs", &initramfs, k_initramfs.clone(), "initramfs"),
-        (
-            "install_initramfs",
-            &install_initramfs,
-            k_install.clone(),
-            "install_initramfs",
-        ),
-    ] {
-        let (exists, size, mtime) = file_info(path);
-        out.push(ArtifactRow {
-            kind: kind.to_string(),
-            rel_path: out_rel_path(out_dir, path),
-            exists,
-            size_bytes: size,
-            mtime_unix: mtime,
-            input_key: key.clone(),
-            store: store_presence(store, store_kind, key.as_deref(), Some(path))?,
-        });
-    }
-
-    {
-        let (exists, size, mtime) = file_info(&iso);
-        out.push(ArtifactRow {
-            kind: "iso".to_string(),
-            rel_path: out_rel_path(out_dir, &iso),
-            exists,
-            size_bytes: size,
-            mtime_unix: mtime,
-            input_key: iso_key.clone(),
-            store: store_presence(store, "iso", iso_key.as_deref(), Some(&iso))?,
-        });
-    }
-
-    {
-        let (exists, size, mtime) = file_info(&checksum);
-        out.push(ArtifactRow {
-            kind: "iso_checksum".to_string(),
-            rel_path: out_rel_path(out_dir, &checksum),
-            exists,
-            size_bytes: size,
-            mtime_unix: mtime,
-            input_key: iso_key.clone(),
-            store: store_presence(store, "iso_checksum", iso_key.as_deref(), Some(&checksum))?,
-        });
-    }
-
-    Ok(out)
-}
-
-fn summary_acorn(store: &ArtifactStore, out_dir: &Path) -> Result<Vec<ArtifactRow>, ApiError> {
-    use distro_spec::acorn::{INITRAMFS_LIVE_OUTPUT, ISO_FILENAME, ROOTFS_NAME};
-
-    let rootfs = out_dir.join(ROOTFS_NAME);
-    let initramfs = out_dir.join(INITRAMFS_LIVE_OUTPUT);
-    let iso = out_dir.join(ISO_FILENAME);
-    let checksum = find_iso_checksum_file(&iso).unwrap_or_else(|| iso.with_extension("sha512"));
-    let vmlinuz = out_dir.join("staging/boot/vmlinuz");
-
-    let k_kernel = read_key_file(&out_dir.join(".kernel-inputs.hash"))?;
-    let k_rootfs = read_key_file(&out_dir.join(".rootfs-inputs.hash"))?;
-    let k_initramfs = read_key_file(&out_dir.join(".initramfs-inputs.hash"))?;
-
-    let iso_key = iso_input_key(&[
-        out_dir.join(".kernel-inputs.hash"),
-        out_dir.join(".rootfs-inputs.hash"),
-        out_dir.join(".initramfs-inputs.hash"),
-    ]);
-
-    let mut out = vec![];
-
-    {
-        let (exists, size, mtime) = file_info(&vmlinuz);
-        out.push(ArtifactRow {
-            kind: "kernel_payload".to_string(),
-            rel_path: out_rel_path(out_dir, &vmlinuz),
-            exists,
-            size_bytes: size,
-            mtime_unix: mtime,
-            input_key: k_kernel.clone(),
-            store: store_presence(store, "kernel_payload", k_kernel.as_deref(), Some(&vmlinuz))?,
-        });
-    }
-
-    for (kind, path, key, store_kind) in [
-        ("rootfs_erofs", &rootfs, k_rootfs.clone(), "rootfs_erofs"),
-        ("initramfs", &initramfs, k_initramfs.clone(), "initramfs"),
-    ] {
-        let (exists, size, mtime) = file_info(path);
-        out.push(ArtifactRow {
-            kind: kind.to_string(),
-            rel_path: out_rel_path(out_dir, path),
-            exists,
-            size_bytes: size,
-            mtime_unix: mtime,
-            input_key: key.clone(),
-            store: store_presence(store, store_kind, key.as_deref(), Some(path))?,
-        });
-    }
-
-    {
-        let (exists, size, mtime) = file_info(&iso);
-        out.push(ArtifactRow {
-            kind: "iso".to_string(),
-            rel_path: out_rel_path(out_dir, &iso),
-            exists,
-            size_bytes: size,
-            mtime_unix: mtime,
-            input_key: iso_key.clone(),
-            store: store_presence(store, "iso", iso_key.as_deref(), Some(&iso))?,
-        });
-    }
-
-    {
-        let (exists, size, mtime) = file_info(&checksum);
-        out.push(ArtifactRow {
-            kind: "iso_checksum".to_string(),
-            rel_path: out_rel_path(out_dir, &checksum),
-            exists,
-            size_bytes: size,
-            mtime_unix: mtime,
-            input_key: iso_key.clone(),
-            store: store_presence(store, "iso_checksum", iso_key.as_deref(), Some(&checksum))?,
-        });
-    }
-
-    Ok(out)
-}
-
-fn summary_iuppiter(store: &ArtifactStore, out_dir: &Path) -> Result<Vec<ArtifactRow>, ApiError> {
-    use distro_spec::iuppiter::{INITRAMFS_LIVE_OUTPUT, ISO_FILENAME, ROOTFS_NAME};
-
-    let rootfs = out_dir.join(ROOTFS_NAME);
-    let initramfs = out_dir.join(INITRAMFS_LIVE_OUTPUT);
-    let iso = out_dir.join(ISO_FILENAME);
-    let checksum = find_iso_checksum_file(&iso).unwrap_or_else(|| iso.with_extension("sha512"));
-    let vmlinuz = out_dir.join("staging/boot/vmlinuz");
-
-    let k_kernel = read_key_file(&out_dir.join(".kernel-inputs.hash"))?;
-    let k_rootfs = read_key_file(&out_dir.join(".rootfs-inputs.hash"))?;
-    let k_initramfs = read_key_file(&out_dir.join(".initramfs-inputs.hash"))?;
-
-    let iso_key = iso_input_key(&[
-        out_dir.join(".kernel-inputs.hash"),
-        out_dir.join(".rootfs-inputs.hash"),
-        out_dir.join(".initramfs-inputs.hash"),
-    ]);
-
+fn summary_rows(
+    store: &ArtifactStore,
+    artifacts: &registry::DistroArtifacts,
+    out_dir: &Path,
+) -> Result<Vec<ArtifactRow>, ApiError> {
     let mut out = vec![];
-
-    {
-        let (exists, size, mtime) = file_info(&vmlinuz);
-        out.push(ArtifactRow {
-            kind: "kernel_payload".to_string(),
-            rel_path: out_rel_path(out_dir, &vmlinuz),
-            exists,
-            size_bytes: size,
-            mtime_unix: mtime,
-            input_key: k_kernel.clone(),
-            store: store_presence(store, "kernel_payload", k_kernel.as_deref(), Some(&vmlinuz))?,
-        });
-    }
-
-    for (kind, path, key, store_kind) in [
-        ("rootfs_erofs", &rootfs, k_rootfs.clone(), "rootfs_erofs"),
-        ("initramfs", &initramfs, k_initramfs.clone(), "initramfs"),
-    ] {
-        let (exists, size, mtime) = file_info(path);
-        out.push(ArtifactRow {
-            kind: kind.to_string(),
-            rel_path: out_rel_path(out_dir, path),
-            exists,
-            size_bytes: size,
-            mtime_unix: mtime,
-            input_key: key.clone(),
-            store: store_presence(store, store_kind, key.as_deref(), Some(path))?,
-        });
-    }
-
-    {
-        let (exists, size, mtime) = file_info(&iso);
-        out.push(ArtifactRow {
-            kind: "iso".to_string(),
-            rel_path: out_rel_path(out_dir, &iso),
-            exists,
-            size_bytes: size,
-            mtime_unix: mtime,
-            input_key: iso_key.clone(),
-            store: store_presence(store, "iso", iso_key.as_deref(), Some(&iso))?,
-        });
-    }
-
-    {
-        let (exists, size, mtime) = file_info(&checksum);
+    for kind in artifacts.kinds() {
+        let path = artifacts.output_path(kind, out_dir);
+        let key = artifacts.input_key(kind, out_dir)?;
+        let (exists, size, mtime) = file_info(&path);
         out.push(ArtifactRow {
-            kind: "iso_checksum".to_string(),
-            rel_path: out_rel_path(out_dir, &checksum),
+            kind: kind.name.clone(),
+            rel_path: out_rel_path(out_dir, &path),
             exists,
             size_bytes: size,
             mtime_unix: mtime,
-            input_key: iso_key.clone(),
-            store: store_presence(store, "iso_checksum", iso_key.as_deref(), Some(&checksum))?,
+            store: store_presence(store, &kind.name, key.as_deref(), Some(&path))?,
+            input_key: key,
         });
     }
-
     Ok(out)
 }
 
@@ -903,6 +668,30 @@ async fn api_prune(
     }))
 }
 
+#[derive(Deserialize)]
+struct FsckReq {
+    /// Check only unless set; repairing requires mutations to be enabled.
+    #[serde(default)]
+    repair: bool,
+}
+
+async fn api_fsck(
+    State(st): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<FsckReq>,
+) -> Result<Json<fsck::FsckReport>, ApiError> {
+    if req.repair {
+        require_mutation(&st, &headers)?;
+    }
+    // Rehashing reads every blob; keep it off the async workers.
+    let report = tokio::task::spawn_blocking(move || {
+        fsck::fsck(&st.repo_root, &st.store, req.repair, false)
+    })
+    .await
+    .map_err(|e| ApiError::Internal(e.into()))??;
+    Ok(Json(report))
+}
+
 #[derive(Deserialize)]
 struct IngestReq {
     /// Optional subset of kinds to ingest. When omitted, ingests everything known for the distro.
@@ -930,24 +719,21 @@ async fn api_ingest_existing(
 ) -> Result<Json<IngestResp>, ApiError> {
     require_mutation(&st, &headers)?;
 
-    let base_dir = st.repo_root.join(&distro_dir);
+    let distro = lookup_distro(&distro_dir)?;
+    let base_dir = distro.base_dir(&st.repo_root);
     let out_dir = distro_builder::artifact_store::central_output_dir_for_distro(&base_dir);
 
-    ensure_hash_keys(&distro_dir, &base_dir);
+    distro.cache_input_hashes(&base_dir);
+    let artifacts = distro.load_artifacts(&st.repo_root)?;
 
     let kinds = req
         .kinds
-        .unwrap_or_else(|| default_kinds_for_distro(&distro_dir));
+        .unwrap_or_else(|| artifacts.kinds().iter().map(|k| k.name.clone()).collect());
     let mut results = vec![];
     for kind in kinds {
-        let r = ingest_one_kind(&st.store, &distro_dir, &out_dir, &kind).unwrap_or_else(|e| {
-            IngestKindResult {
-                kind,
-                status: "error".to_string(),
-                detail: Some(e),
-            }
-        });
-        results.push(r);
+        results.push(ingest_one_kind(
+            &st.store, distro, &artifacts, &out_dir, kind,
+        ));
     }
 
     Ok(Json(IngestResp {
@@ -975,10 +761,15 @@ async fn api_restore_kind(
     Json(req): Json<RestoreReq>,
 ) -> Result<Json<RestoreResp>, ApiError> {
     require_mutation(&st, &headers)?;
-    let base_dir = st.repo_root.join(&distro_dir);
+    let distro = lookup_distro(&distro_dir)?;
+    let base_dir = distro.base_dir(&st.repo_root);
     let out_dir = distro_builder::artifact_store::central_output_dir_for_distro(&base_dir);
 
-    let restored = restore_one_kind(&st.store, &distro_dir, &out_dir, &req.kind)?;
+    let artifacts = distro.load_artifacts(&st.repo_root)?;
+    let Some(kind) = artifacts.find(&req.kind) else {
+        return Err(ApiError::BadRequest(format!("Unknown kind '{}'", req.kind)));
+    };
+    let restored = artifacts.restore(&st.store, &out_dir, kind)?;
     Ok(Json(RestoreResp {
         distro: distro_dir,
         kind: req.kind,
@@ -986,356 +777,36 @@ async fn api_restore_kind(
     }))
 }
 
-fn default_kinds_for_distro(distro_dir: &str) -> Vec<String> {
-    match distro_dir {
-        "leviso" => vec![
-            "kernel_payload",
-            "rootfs_erofs",
-            "initramfs",
-            "install_initramfs",
-            "iso",
-            "iso_checksum",
-        ]
-        .into_iter()
-        .map(|s| s.to_string())
-        .collect(),
-        "AcornOS" | "IuppiterOS" => vec![
-            "kernel_payload",
-            "rootfs_erofs",
-            "initramfs",
-            "iso",
-            "iso_checksum",
-        ]
-        .into_iter()
-        .map(|s| s.to_string())
-        .collect(),
-        _ => vec![],
-    }
-}
-
-fn ensure_hash_keys(distro_dir: &str, base_dir: &Path) {
-    // Best-effort only. These functions do not build; they only write .hash keys if inputs exist.
-    match distro_dir {
-        "leviso" => {
-            leviso::rebuild::cache_kernel_hash(base_dir);
-            leviso::rebuild::cache_rootfs_hash(base_dir);
-            leviso::rebuild::cache_initramfs_hash(base_dir);
-            leviso::rebuild::cache_install_initramfs_hash(base_dir);
-        }
-        "AcornOS" => {
-            acornos::rebuild::cache_kernel_hash(base_dir);
-            acornos::rebuild::cache_rootfs_hash(base_dir);
-            acornos::rebuild::cache_initramfs_hash(base_dir);
-        }
-        "IuppiterOS" => {
-            iuppiteros::rebuild::cache_kernel_hash(base_dir);
-            iuppiteros::rebuild::cache_rootfs_hash(base_dir);
-            iuppiteros::rebuild::cache_initramfs_hash(base_dir);
-        }
-        _ => {}
-    }
-}
-
 fn ingest_one_kind(
     store: &ArtifactStore,
-    distro_dir: &str,
+    distro: &registry::Distro,
+    artifacts: &registry::DistroArtifacts,
     out_dir: &Path,
-    kind: &str,
-) -> std::result::Result<IngestKindResult, String> {
-    let mut meta = BTreeMap::new();
-    meta.insert(
-        "distro".to_string(),
-        serde_json::Value::String(distro_dir.to_string()),
-    );
-
-    match kind {
-        "kernel_payload" => {
-            let staging = out_dir.join("staging");
-            let key_file = out_dir.join(".kernel-inputs.hash");
-            let vmlinuz = staging.join("boot/vmlinuz");
-            if !vmlinuz.exists() {
-                return Ok(IngestKindResult {
-                    kind: kind.to_string(),
-                    status: "skipped".to_string(),
-                    detail: Some("missing vmlinuz".to_string()),
-                });
-            }
-            let Some(key) = distro_builder::artifact_store::read_input_key_file(&key_file)
-                .map_err(|e| e.to_string())?
-            else {
-                return Ok(IngestKindResult {
-                    kind: kind.to_string(),
-                    status: "skipped".to_string(),
-                    detail: Some("missing key".to_string()),
-                });
-            };
-            if store
-                .get("kernel_payload", &key)
-                .map_err(|e| e.to_string())?
-                .is_some()
-            {
-                return Ok(IngestKindResult {
-                    kind: kind.to_string(),
-                    status: "skipped".to_string(),
-                    detail: Some("already stored".to_string()),
-                });
-            }
-            store
-                .put_kernel_payload(&key, &staging, meta)
-                .map_err(|e| e.to_string())?;
-            Ok(IngestKindResult {
-                kind: kind.to_string(),
-                status: "stored".to_string(),
-                detail: None,
-            })
-        }
-        "rootfs_erofs" => ingest_file_from_key(
-            store,
-            kind,
-            out_dir.join(".rootfs-inputs.hash"),
-            out_dir.join(distro_rootfs_name(distro_dir)),
-            meta,
-        ),
-        "initramfs" => ingest_file_from_key(
-            store,
+    kind: String,
+) -> IngestKindResult {
+    let Some(parsed) = artifacts.find(&kind) else {
+        return IngestKindResult {
             kind,
-            out_dir.join(".initramfs-inputs.hash"),
-            out_dir.join(distro_initramfs_name(distro_dir)),
-            meta,
-        ),
-        "install_initramfs" => {
-            if distro_dir != "leviso" {
-                return Ok(IngestKindResult {
-                    kind: kind.to_string(),
-                    status: "skipped".to_string(),
-                    detail: Some("not applicable".to_string()),
-                });
-            }
-            ingest_file_from_key(
-                store,
-                kind,
-                out_dir.join(".install-initramfs-inputs.hash"),
-                out_dir.join(distro_spec::levitate::INITRAMFS_INSTALLED_OUTPUT),
-                meta,
-            )
-        }
-        "iso" | "iso_checksum" => {
-            let iso_path = out_dir.join(distro_iso_name(distro_dir));
-            if !iso_path.exists() {
-                return Ok(IngestKindResult {
-                    kind: kind.to_string(),
-                    status: "skipped".to_string(),
-                    detail: Some("missing ISO".to_string()),
-                });
-            }
-            let key_files = iso_key_files(distro_dir, out_dir);
-            let key = iso_input_key(&key_files).ok_or_else(|| "missing ISO key".to_string())?;
-            if kind == "iso" {
-                if store.get("iso", &key).map_err(|e| e.to_string())?.is_some() {
-                    return Ok(IngestKindResult {
-                        kind: kind.to_string(),
-                        status: "skipped".to_string(),
-                        detail: Some("already stored".to_string()),
-                    });
-                }
-                store
-                    .ingest_file_move_and_link("iso", &key, &iso_path, meta)
-                    .map_err(|e| e.to_string())?;
-                return Ok(IngestKindResult {
-                    kind: kind.to_string(),
-                    status: "stored".to_string(),
-                    detail: None,
-                });
-            }
-
-            let checksum = find_iso_checksum_file(&iso_path)
-                .unwrap_or_else(|| iso_path.with_extension("sha512"));
-            if !checksum.exists() {
-                return Ok(IngestKindResult {
-                    kind: kind.to_string(),
-                    status: "skipped".to_string(),
-                    detail: Some("missing checksum".to_string()),
-                });
-            }
-            if store
-                .get("iso_checksum", &key)
-                .map_err(|e| e.to_string())?
-                .is_some()
-            {
-                return Ok(IngestKindResult {
-                    kind: kind.to_string(),
-                    status: "skipped".to_string(),
-                    detail: Some("already stored".to_string()),
-                });
-            }
-            store
-                .ingest_file_move_and_link("iso_checksum", &key, &checksum, meta)
-                .map_err(|e| e.to_string())?;
-            Ok(IngestKindResult {
-                kind: kind.to_string(),
-                status: "stored".to_string(),
-                detail: None,
-            })
-        }
-        _ => Ok(IngestKindResult {
-            kind: kind.to_string(),
             status: "skipped".to_string(),
             detail: Some("unknown kind".to_string()),
-        }),
-    }
-}
-
-fn ingest_file_from_key(
-    store: &ArtifactStore,
-    kind: &str,
-    key_file: PathBuf,
-    src_file: PathBuf,
-    meta: BTreeMap<String, serde_json::Value>,
-) -> std::result::Result<IngestKindResult, String> {
-    if !src_file.exists() {
-        return Ok(IngestKindResult {
-            kind: kind.to_string(),
-            status: "skipped".to_string(),
-            detail: Some("missing file".to_string()),
-        });
-    }
-    let Some(key) = distro_builder::artifact_store::read_input_key_file(&key_file)
-        .map_err(|e| e.to_string())?
-    else {
-        return Ok(IngestKindResult {
-            kind: kind.to_string(),
-            status: "skipped".to_string(),
-            detail: Some("missing key".to_string()),
-        });
+        };
     };
-    if store.get(kind, &key).map_err(|e| e.to_string())?.is_some() {
-        return Ok(IngestKindResult {
-            kind: kind.to_string(),
-            status: "skipped".to_string(),
-            detail: Some("already stored".to_string()),
-        });
-    }
-    store
-        .ingest_file_move_and_link(kind, &key, &src_file, meta)
-        .map_err(|e| e.to_string())?;
-    Ok(IngestKindResult {
-        kind: kind.to_string(),
-        status: "stored".to_string(),
-        detail: None,
-    })
-}
-
-fn restore_one_kind(
-    store: &ArtifactStore,
-    distro_dir: &str,
-    out_dir: &Path,
-    kind: &str,
-) -> Result<bool, ApiError> {
-    match kind {
-        "kernel_payload" => Ok(
-            distro_builder::artifact_store::try_restore_kernel_payload_from_key(
-                store,
-                &out_dir.join(".kernel-inputs.hash"),
-                &out_dir.join("staging"),
-            )?,
-        ),
-        "rootfs_erofs" => Ok(distro_builder::artifact_store::try_restore_file_from_key(
-            store,
-            "rootfs_erofs",
-            &out_dir.join(".rootfs-inputs.hash"),
-            &out_dir.join(distro_rootfs_name(distro_dir)),
-        )?),
-        "initramfs" => Ok(distro_builder::artifact_store::try_restore_file_from_key(
-            store,
-            "initramfs",
-            &out_dir.join(".initramfs-inputs.hash"),
-            &out_dir.join(distro_initramfs_name(distro_dir)),
-        )?),
-        "install_initramfs" => {
-            if distro_dir != "leviso" {
-                return Ok(false);
-            }
-            Ok(distro_builder::artifact_store::try_restore_file_from_key(
-                store,
-                "install_initramfs",
-                &out_dir.join(".install-initramfs-inputs.hash"),
-                &out_dir.join(distro_spec::levitate::INITRAMFS_INSTALLED_OUTPUT),
-            )?)
-        }
-        "iso" | "iso_checksum" => {
-            let iso_path = out_dir.join(distro_iso_name(distro_dir));
-            let key_files = iso_key_files(distro_dir, out_dir);
-            let Some(key) = iso_input_key(&key_files) else {
-                return Ok(false);
-            };
-
-            if kind == "iso" {
-                if iso_path.exists() {
-                    return Ok(false);
-                }
-                if store.get("iso", &key)?.is_none() {
-                    return Ok(false);
-                }
-                store.materialize_to("iso", &key, &iso_path)?;
-                return Ok(true);
-            }
-
-            let checksum = find_iso_checksum_file(&iso_path)
-                .unwrap_or_else(|| iso_path.with_extension("sha512"));
-            if checksum.exists() {
-                return Ok(false);
-            }
-            if store.get("iso_checksum", &key)?.is_none() {
-                return Ok(false);
-            }
-            store.materialize_to("iso_checksum", &key, &checksum)?;
-            Ok(true)
-        }
-        _ => Err(ApiError::BadRequest(format!("Unknown kind '{}'", kind))),
-    }
-}
-
-fn iso_key_files(distro_dir: &str, out_dir: &Path) -> Vec<PathBuf> {
-    match distro_dir {
-        "leviso" => vec![
-            out_dir.join(".kernel-inputs.hash"),
-            out_dir.join(".rootfs-inputs.hash"),
-            out_dir.join(".initramfs-inputs.hash"),
-            out_dir.join(".install-initramfs-inputs.hash"),
-        ],
-        "AcornOS" | "IuppiterOS" => vec![
-            out_dir.join(".kernel-inputs.hash"),
-            out_dir.join(".rootfs-inputs.hash"),
-            out_dir.join(".initramfs-inputs.hash"),
-        ],
-        _ => vec![],
-    }
-}
-
-fn distro_rootfs_name(distro_dir: &str) -> &'static str {
-    match distro_dir {
-        "leviso" => distro_spec::levitate::ROOTFS_NAME,
-        "AcornOS" => distro_spec::acorn::ROOTFS_NAME,
-        "IuppiterOS" => distro_spec::iuppiter::ROOTFS_NAME,
-        _ => "filesystem.erofs",
-    }
-}
 
-fn distro_initramfs_name(distro_dir: &str) -> &'static str {
-    match distro_dir {
-        "leviso" => distro_spec::levitate::INITRAMFS_LIVE_OUTPUT,
-        "AcornOS" => distro_spec::acorn::INITRAMFS_LIVE_OUTPUT,
-        "IuppiterOS" => distro_spec::iuppiter::INITRAMFS_LIVE_OUTPUT,
-        _ => "initramfs-live.cpio.gz",
-    }
-}
+    let mut meta = BTreeMap::new();
+    meta.insert(
+        "distro".to_string(),
+        serde_json::Value::String(distro.dir.to_string()),
+    );
 
-fn distro_iso_name(distro_dir: &str) -> &'static str {
-    match distro_dir {
-        "leviso" => distro_spec::levitate::ISO_FILENAME,
-        "AcornOS" => distro_spec::acorn::ISO_FILENAME,
-        "IuppiterOS" => distro_spec::iuppiter::ISO_FILENAME,
-        _ => "distro.iso",
+    let (status, detail) = match artifacts.ingest(store, out_dir, parsed, meta) {
+        Ok(IngestOutcome::Stored(_)) => ("stored", None),
+        Ok(IngestOutcome::Skipped(why)) => ("skipped", Some(why.to_string())),
+        Err(e) => ("error", Some(format!("{:#}", e))),
+    };
+    IngestKindResult {
+        kind,
+        status: status.to_string(),
+        detail,
     }
 }
 
@@ -1346,20 +817,6 @@ fn validate_hex_64(s: &str) -> Result<(), ApiError> {
     Ok(())
 }
 
-#[cfg(unix)]
-fn hardlink_info(out_file: &Path, blob_file: &Path) -> Option<(bool, u64)> {
-    use std::os::unix::fs::MetadataExt;
-    let out_md = std::fs::metadata(out_file).ok()?;
-    let blob_md = std::fs::metadata(blob_file).ok()?;
-    let same = out_md.dev() == blob_md.dev() && out_md.ino() == blob_md.ino();
-    Some((same, out_md.nlink()))
-}
-
-#[cfg(not(unix))]
-fn hardlink_info(_out_file: &Path, _blob_file: &Path) -> Option<(bool, u64)> {
-    None
-}
-
 fn sanitize_relative_path(rel: &str) -> Result<PathBuf, ApiError> {
     let p = PathBuf::from(rel);
     for c in p.components() {
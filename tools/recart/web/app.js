@@ -576,6 +576,20 @@ async function init() {
     await loadStore();
   };
 
+  qs("#fsck-btn").onclick = async () => {
+    setStatus("Fsck running (rehashing blobs)…");
+    const r = await api("/api/v1/actions/fsck", {
+      method: "POST",
+      headers: { "content-type": "application/json", ...tokenHeader() },
+      body: JSON.stringify({ repair: false }),
+    });
+    setStatus(
+      `Fsck: ${r.blobs_checked} blobs checked, ${r.corrupt_blobs.length} corrupt, ` +
+        `${r.unreadable_blobs.length} unreadable, ${r.dangling_entries.length} dangling entries, ` +
+        `${r.outputs.filter((o) => o.status === "unlinked").length} unlinked outputs`,
+    );
+  };
+
   qs("#gc-btn").onclick = async () => {
     if (!state.mutationsEnabled) return;
     setStatus("GC running…");
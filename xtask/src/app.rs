@@ -8,6 +8,7 @@ pub fn run(cli: crate::cli::Cli) -> Result<()> {
         crate::cli::Cmd::Kernels { cmd } => match cmd {
             crate::cli::KernelsCmd::Build {
                 distro,
+                arch,
                 rebuild,
                 autofix,
                 autofix_attempts,
@@ -15,6 +16,7 @@ pub fn run(cli: crate::cli::Cli) -> Result<()> {
                 llm_profile,
             } => crate::tasks::kernels::build::run(
                 distro,
+                arch,
                 rebuild,
                 crate::tasks::kernels::common::AutoFixOptions {
                     enabled: autofix,
@@ -24,12 +26,14 @@ pub fn run(cli: crate::cli::Cli) -> Result<()> {
                 },
             ),
             crate::cli::KernelsCmd::BuildAll {
+                arch,
                 rebuild,
                 autofix,
                 autofix_attempts,
                 autofix_prompt_file,
                 llm_profile,
             } => crate::tasks::kernels::build_all::run(
+                arch,
                 rebuild,
                 crate::tasks::kernels::common::AutoFixOptions {
                     enabled: autofix,
@@ -41,7 +45,9 @@ pub fn run(cli: crate::cli::Cli) -> Result<()> {
             crate::cli::KernelsCmd::Prebuilt { distro, refresh } => {
                 crate::tasks::kernels::prebuilt::run(distro, refresh)
             }
-            crate::cli::KernelsCmd::Check { distro } => crate::tasks::kernels::check::run(distro),
+            crate::cli::KernelsCmd::Check { distro, arch } => {
+                crate::tasks::kernels::check::run(distro, arch)
+            }
         },
         crate::cli::Cmd::Hooks { cmd } => match cmd {
             crate::cli::HooksCmd::Install => crate::tasks::tooling::hooks::install(),
@@ -53,43 +59,182 @@ pub fn run(cli: crate::cli::Cli) -> Result<()> {
                 distro,
                 inject,
                 inject_file,
+                inject_manifest,
                 ssh,
                 ssh_port,
                 ssh_timeout,
                 no_shell,
                 window,
                 ssh_private_key,
+                accel,
+                firmware,
+                arch,
+                hw_profile,
+                pvpanic,
+                share,
+                record,
+                visual,
+                persist,
+                snapshot,
+                direct,
+                cmdline_append,
+                rebase,
             } => crate::tasks::testing::scenarios::boot(
                 target,
                 distro,
-                inject,
-                inject_file,
-                ssh,
-                ssh_port,
-                ssh_timeout,
-                no_shell,
-                window,
-                ssh_private_key,
+                crate::tasks::testing::scenarios::BootOptions {
+                    inject,
+                    inject_file,
+                    inject_manifest,
+                    ssh,
+                    ssh_port,
+                    ssh_timeout,
+                    no_shell,
+                    window,
+                    ssh_private_key,
+                    accel,
+                    firmware,
+                    arch,
+                    hw_profile,
+                    pvpanic,
+                    share,
+                    record,
+                    visual,
+                    spawn: crate::tasks::testing::scenarios::SpawnOptions { persist, snapshot },
+                    direct,
+                    cmdline_append,
+                    rebase,
+                },
             ),
             crate::cli::ScenariosCmd::Test {
                 target,
                 distro,
                 inject,
                 inject_file,
+                inject_manifest,
+                firmware,
+                arch,
+                accel,
                 force,
-            } => crate::tasks::testing::scenarios::test(target, distro, inject, inject_file, force),
+                retries,
+            } => crate::tasks::testing::scenarios::test(
+                target,
+                distro,
+                inject,
+                inject_file,
+                inject_manifest,
+                crate::tasks::testing::scenarios::HarnessVm {
+                    firmware,
+                    arch,
+                    accel,
+                },
+                force,
+                retries,
+            ),
             crate::cli::ScenariosCmd::TestUpTo {
                 target,
                 distro,
                 inject,
                 inject_file,
-            } => crate::tasks::testing::scenarios::test_up_to(target, distro, inject, inject_file),
+                inject_manifest,
+                firmware,
+                arch,
+                accel,
+                retries,
+            } => crate::tasks::testing::scenarios::test_up_to(
+                target,
+                distro,
+                inject,
+                inject_file,
+                inject_manifest,
+                crate::tasks::testing::scenarios::HarnessVm {
+                    firmware,
+                    arch,
+                    accel,
+                },
+                retries,
+            ),
+            crate::cli::ScenariosCmd::Matrix {
+                distros,
+                up_to,
+                jobs,
+                mem_budget,
+                job_mem,
+                inject,
+                inject_file,
+            } => crate::tasks::testing::matrix::run(crate::tasks::testing::matrix::MatrixOptions {
+                distros,
+                up_to,
+                jobs,
+                mem_budget_mib: mem_budget,
+                job_mem_mib: job_mem,
+                inject,
+                inject_file,
+            }),
+            crate::cli::ScenariosCmd::SecbootRefusal { distro, accel } => {
+                crate::tasks::testing::scenarios::secboot_refusal(distro, accel)
+            }
+            crate::cli::ScenariosCmd::AbTrial {
+                distro,
+                paths,
+                accel,
+                snapshot,
+                boot_timeout,
+                ssh_private_key,
+            } => crate::tasks::testing::scenarios::ab_trial(
+                distro,
+                &paths,
+                accel,
+                snapshot,
+                boot_timeout,
+                ssh_private_key,
+            ),
             crate::cli::ScenariosCmd::Status { distro } => {
                 crate::tasks::testing::scenarios::status(distro)
             }
+            crate::cli::ScenariosCmd::Flakes { distro } => {
+                crate::tasks::testing::flake::show(distro)
+            }
             crate::cli::ScenariosCmd::Reset { distro } => {
                 crate::tasks::testing::scenarios::reset(distro)
             }
+            crate::cli::ScenariosCmd::Snapshots { cmd } => match cmd {
+                crate::cli::SnapshotsCmd::Ls { distro } => {
+                    crate::tasks::testing::snapshots::ls(distro)
+                }
+                crate::cli::SnapshotsCmd::Rm { name, distro } => {
+                    crate::tasks::testing::snapshots::rm(distro, &name)
+                }
+            },
+            crate::cli::ScenariosCmd::Ps => crate::tasks::testing::vm::ps(),
+            crate::cli::ScenariosCmd::Attach { id, serial } => {
+                crate::tasks::testing::vm::attach(&id, serial)
+            }
+            crate::cli::ScenariosCmd::Kill { id, timeout } => {
+                crate::tasks::testing::vm::kill(&id, timeout)
+            }
+            crate::cli::ScenariosCmd::Exec { id, command } => {
+                crate::tasks::testing::vm::exec(&id, &command)
+            }
+            crate::cli::ScenariosCmd::Cp {
+                src,
+                dst,
+                recursive,
+            } => crate::tasks::testing::vm::cp(&src, &dst, recursive),
+            crate::cli::ScenariosCmd::Vm { id, cmd } => match cmd {
+                crate::cli::VmCmd::Shutdown { timeout } => {
+                    crate::tasks::testing::vm::shutdown(&id, timeout)
+                }
+                crate::cli::VmCmd::Screenshot { path } => {
+                    crate::tasks::testing::vm::screenshot(&id, &path)
+                }
+                crate::cli::VmCmd::Keys {
+                    sequence,
+                    text,
+                    delay_ms,
+                } => crate::tasks::testing::vm::keys(&id, sequence, text, delay_ms),
+                crate::cli::VmCmd::Status => crate::tasks::testing::vm::status(&id),
+            },
         },
         crate::cli::Cmd::Policy { cmd } => match cmd {
             crate::cli::PolicyCmd::AuditLegacyBindings => {
@@ -131,6 +276,7 @@ pub fn run(cli: crate::cli::Cli) -> Result<()> {
                 stdout,
                 ansi,
                 keep_transcript,
+                cast,
             } => crate::tasks::tui::inspect::run(crate::tasks::tui::inspect::Options {
                 app,
                 cwd,
@@ -144,8 +290,14 @@ pub fn run(cli: crate::cli::Cli) -> Result<()> {
                 stdout,
                 ansi,
                 keep_transcript,
+                cast,
             }),
         },
+        crate::cli::Cmd::Replay {
+            cast,
+            speed,
+            max_idle,
+        } => crate::tasks::tooling::replay::run(&cast, speed, max_idle),
     }
 }
 
@@ -162,6 +314,9 @@ fn enforce_policy_guard_placement(cmd: &crate::cli::Cmd) -> Result<()> {
             cmd: ScenariosCmd::Boot { .. }
                 | ScenariosCmd::Test { .. }
                 | ScenariosCmd::TestUpTo { .. }
+                | ScenariosCmd::Matrix { .. }
+                | ScenariosCmd::SecbootRefusal { .. }
+                | ScenariosCmd::AbTrial { .. }
         }
     );
     if !requires_guard {
@@ -1,6 +1,7 @@
 mod types;
 
 pub use types::{
-    BootDistro, Cli, Cmd, Distro, DocsCmd, HarnessDistro, HooksCmd, KernelsCmd, PolicyCmd,
-    ScenariosCmd, Shell, TuiCmd, TuiInspectApp,
+    AbTrialPath, AccelMode, Arch, BootDistro, Cli, Cmd, Distro, DocsCmd, Firmware, HarnessDistro,
+    HooksCmd, KernelsCmd, PolicyCmd, ScenariosCmd, Shell, SnapshotsCmd, TuiCmd, TuiInspectApp,
+    VisualMode, VmCmd,
 };
@@ -43,6 +43,126 @@ pub enum HarnessDistro {
     Ralph,
 }
 
+/// QEMU accelerator selection for scenario boots.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum AccelMode {
+    /// Use KVM when /dev/kvm is accessible, otherwise fall back to TCG.
+    #[value(name = "auto")]
+    Auto,
+
+    #[value(name = "kvm")]
+    Kvm,
+
+    #[value(name = "tcg")]
+    Tcg,
+}
+
+impl AccelMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Kvm => "kvm",
+            Self::Tcg => "tcg",
+        }
+    }
+}
+
+/// Firmware the scenario VM boots with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Firmware {
+    /// OVMF pflash (the default).
+    #[value(name = "uefi")]
+    Uefi,
+
+    /// QEMU's built-in SeaBIOS, exercising the BIOS/MBR boot paths.
+    #[value(name = "bios")]
+    Bios,
+
+    /// Secure Boot OVMF with enrolled test keys, booting the UKI signed with the test db key.
+    #[value(name = "secboot")]
+    Secboot,
+}
+
+impl Firmware {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Uefi => "uefi",
+            Self::Bios => "bios",
+            Self::Secboot => "secboot",
+        }
+    }
+
+    /// Whether the VM boots through OVMF (with or without Secure Boot).
+    pub fn is_uefi(self) -> bool {
+        matches!(self, Self::Uefi | Self::Secboot)
+    }
+}
+
+/// CPU architecture of kernel builds and scenario VMs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Arch {
+    #[value(name = "x86_64")]
+    X86_64,
+
+    /// Arm64, booted as `qemu-system-aarch64 -M virt` with AAVMF (TCG unless the host is arm64).
+    #[value(name = "aarch64")]
+    Aarch64,
+}
+
+impl Arch {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::X86_64 => "x86_64",
+            Self::Aarch64 => "aarch64",
+        }
+    }
+
+    /// Architecture xtask itself runs on; KVM only accelerates guests of this architecture.
+    pub fn host() -> Option<Self> {
+        match std::env::consts::ARCH {
+            "x86_64" => Some(Self::X86_64),
+            "aarch64" => Some(Self::Aarch64),
+            _ => None,
+        }
+    }
+
+    /// Kernel `ARCH=` name.
+    pub fn kernel_arch(self) -> &'static str {
+        match self {
+            Self::X86_64 => "x86",
+            Self::Aarch64 => "arm64",
+        }
+    }
+}
+
+/// What `scenarios boot --visual` does with its screendumps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum VisualMode {
+    /// Compare captures with the stored references.
+    #[value(name = "check")]
+    Check,
+
+    /// Replace the stored references with the captures.
+    #[value(name = "update")]
+    Update,
+}
+
+/// Slot transition exercised by `scenarios ab-trial`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum AbTrialPath {
+    /// Trial boot the composed slot B once, then make it the default.
+    #[value(name = "commit")]
+    Commit,
+
+    /// Trial boot a deliberately broken slot B; the next boot must fall back to slot A.
+    #[value(name = "fallback")]
+    Fallback,
+
+    /// Commit the composed slot B as the default, then explicitly roll the default back to slot A.
+    #[value(name = "rollback")]
+    Rollback,
+}
+
 impl HarnessDistro {
     pub fn id(self) -> &'static str {
         match self {
@@ -112,6 +232,19 @@ pub enum Cmd {
         #[command(subcommand)]
         cmd: TuiCmd,
     },
+
+    /// Play back an asciicast v2 recording (`scenarios boot --record`, `tui inspect --cast`).
+    Replay {
+        /// Path to the `.cast` file.
+        #[arg(value_name = "CAST")]
+        cast: PathBuf,
+        /// Playback speed multiplier.
+        #[arg(long, default_value_t = 1.0)]
+        speed: f64,
+        /// Cap pauses between output events at this many seconds.
+        #[arg(long, value_name = "SECONDS")]
+        max_idle: Option<f64>,
+    },
 }
 
 #[derive(Subcommand)]
@@ -122,6 +255,11 @@ pub enum KernelsCmd {
         #[arg(value_enum)]
         distro: Distro,
 
+        /// Target architecture. `aarch64` merges `build-host/kernel/aarch64/kconfig` over the
+        /// shared kconfig and cross-compiles with `$CROSS_COMPILE` (default `aarch64-linux-gnu-`).
+        #[arg(long, value_enum, default_value_t = Arch::X86_64)]
+        arch: Arch,
+
         #[arg(
             long = "rebuild",
             help = "Force the selected distro to rebuild+reinstall its kernel even if artifacts are already present. Does not bypass the 23:00-10:00 build-hours policy."
@@ -157,6 +295,10 @@ pub enum KernelsCmd {
     /// Build kernels for all distros (policy window enforced).
     #[command(name = "build-all")]
     BuildAll {
+        /// Target architecture for every distro kernel (see `build`).
+        #[arg(long, value_enum, default_value_t = Arch::X86_64)]
+        arch: Arch,
+
         #[arg(
             long = "rebuild",
             help = "Force every distro to rebuild+reinstall its kernel even if artifacts are already present. Does not bypass the 23:00-10:00 build-hours policy."
@@ -206,6 +348,10 @@ pub enum KernelsCmd {
     Check {
         #[arg(value_enum)]
         distro: Option<Distro>,
+
+        /// Architecture of the kernel artifacts to verify.
+        #[arg(long, value_enum, default_value_t = Arch::X86_64)]
+        arch: Arch,
     },
 }
 
@@ -222,16 +368,28 @@ pub enum HooksCmd {
 pub enum ScenariosCmd {
     /// Boot into an interactive scenario.
     ///
-    /// Interactive targets: `live-boot`, `live-tools`, `installed-boot`
+    /// Interactive targets: `live-boot`, `live-tools`, and disk checkpoints
+    /// (`install`, `installed-boot`, `automated-login`, `runtime`). Disk checkpoints boot a
+    /// throwaway qcow2 overlay on the checkpoint's recorded spawn point.
     Boot {
         target: String,
         #[arg(value_enum, default_value_t = BootDistro::Levitate)]
         distro: BootDistro,
-        #[arg(long, value_name = "KEY=VALUE[,KEY=VALUE...]")]
+        /// Boot injection payload, delivered via fw_cfg and injection media (live and disk boots).
+        #[arg(
+            long,
+            value_name = "KEY=VALUE[,KEY=VALUE...]",
+            conflicts_with_all = ["inject_file", "inject_manifest"]
+        )]
         inject: Option<String>,
-        #[arg(long, value_name = "PATH")]
+        #[arg(long, value_name = "PATH", conflicts_with = "inject_manifest")]
         inject_file: Option<PathBuf>,
-        /// Boot the scenario and wait for SSH readiness on the host forwarded port.
+        /// TOML injection manifest: `[env]` rides in the boot payload; hostname, SSH authorized
+        /// keys and files are copied in over SSH (live boots with `--ssh`).
+        #[arg(long, value_name = "PATH")]
+        inject_manifest: Option<PathBuf>,
+        /// Boot the scenario and wait for SSH readiness on the host forwarded port. Disk
+        /// checkpoints keep the serial console and only forward the port for `exec`/`cp`.
         #[arg(long)]
         ssh: bool,
         /// SSH host-forward port when `--ssh` is enabled.
@@ -249,6 +407,57 @@ pub enum ScenariosCmd {
         /// SSH private key used for interactive or probe login when `--ssh` is enabled.
         #[arg(long, value_name = "PATH")]
         ssh_private_key: Option<PathBuf>,
+        /// QEMU accelerator. `auto` uses KVM when /dev/kvm is accessible and falls back to TCG.
+        #[arg(long, value_enum, default_value_t = AccelMode::Auto)]
+        accel: AccelMode,
+        /// Boot firmware; `bios` boots through SeaBIOS, `secboot` boots the test-signed UKI under
+        /// Secure Boot (live-boot and installed-boot).
+        #[arg(long, value_enum, default_value_t = Firmware::Uefi)]
+        firmware: Firmware,
+        /// Guest architecture; `aarch64` boots the arm64 live ISO under `qemu-system-aarch64 -M virt`
+        /// with AAVMF (live-boot and live-tools, serial or SSH).
+        #[arg(long, value_enum, default_value_t = Arch::X86_64)]
+        arch: Arch,
+        /// Named VM hardware profile from `distro-variants/_shared/scenarios/hw-profiles.toml`.
+        #[arg(long, value_name = "NAME")]
+        hw_profile: Option<String>,
+        /// Attach a pvpanic device so a guest kernel panic fails the boot immediately via QMP.
+        #[arg(long)]
+        pvpanic: bool,
+        /// Export a host directory to the guest (virtiofs when virtiofsd is available, else 9p).
+        /// A mount request at `/mnt/<TAG>` rides in the boot-injection payload; the boot also prints
+        /// the command to mount it by hand. Repeatable.
+        #[arg(long, value_name = "HOST_DIR:TAG[:ro]")]
+        share: Vec<String>,
+        /// Record the serial console as an asciicast v2 file; play it back with
+        /// `cargo xtask replay`.
+        #[arg(long, value_name = "PATH")]
+        record: Option<PathBuf>,
+        /// Capture QMP screendumps at the checkpoints in `distro-variants/<distro>/scenarios/visual.toml`
+        /// and compare them with the stored references (`update` rewrites the references).
+        /// Needs `--no-shell`.
+        #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "check", value_name = "MODE")]
+        visual: Option<VisualMode>,
+        /// Save the disk-checkpoint overlay as a new named snapshot after the VM exits.
+        #[arg(long, value_name = "NAME")]
+        persist: Option<String>,
+        /// Boot a named snapshot (see `scenarios snapshots ls`) instead of the checkpoint base.
+        #[arg(long, value_name = "NAME")]
+        snapshot: Option<String>,
+        /// Boot the staged vmlinuz/initramfs/rootfs from `.artifacts/out/<distro>` without an ISO.
+        #[arg(long)]
+        direct: bool,
+        /// Extra kernel arguments appended to the direct-boot command line.
+        #[arg(
+            long,
+            value_name = "ARGS",
+            requires = "direct",
+            allow_hyphen_values = true
+        )]
+        cmdline_append: Option<String>,
+        /// Adopt this boot's artifact input key as the boot-timeline baseline.
+        #[arg(long)]
+        rebase: bool,
     },
 
     /// Run one automated scenario.
@@ -256,13 +465,35 @@ pub enum ScenariosCmd {
         target: String,
         #[arg(value_enum, default_value_t = HarnessDistro::Levitate)]
         distro: HarnessDistro,
-        #[arg(long, value_name = "KEY=VALUE[,KEY=VALUE...]")]
+        #[arg(
+            long,
+            value_name = "KEY=VALUE[,KEY=VALUE...]",
+            conflicts_with_all = ["inject_file", "inject_manifest"]
+        )]
         inject: Option<String>,
-        #[arg(long, value_name = "PATH")]
+        #[arg(long, value_name = "PATH", conflicts_with = "inject_manifest")]
         inject_file: Option<PathBuf>,
+        /// TOML injection manifest; install-tests takes its `[env]` only.
+        #[arg(long, value_name = "PATH")]
+        inject_manifest: Option<PathBuf>,
+        /// Boot firmware for the harness VMs (`uefi` or `bios`); recorded in the report and
+        /// `scenarios status`.
+        #[arg(long, value_enum, default_value_t = Firmware::Uefi)]
+        firmware: Firmware,
+        /// Guest architecture for the harness VMs; non-x86_64 runs do not record spawn points.
+        #[arg(long, value_enum, default_value_t = Arch::X86_64)]
+        arch: Arch,
+        /// QEMU accelerator for the harness VMs. `auto` uses KVM when /dev/kvm is accessible and
+        /// falls back to TCG.
+        #[arg(long, value_enum, default_value_t = AccelMode::Auto)]
+        accel: AccelMode,
         /// Re-run the requested scenario even if it is already cached as passed.
         #[arg(long)]
         force: bool,
+        /// Retry failed runs up to N times when the flake policy allows it (infrastructure
+        /// failures by default; see `distro-variants/_shared/scenarios/flake-policy.toml`).
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        retries: u32,
     },
 
     /// Run all automated scenarios up to the given scenario.
@@ -270,10 +501,66 @@ pub enum ScenariosCmd {
         target: String,
         #[arg(value_enum, default_value_t = HarnessDistro::Levitate)]
         distro: HarnessDistro,
-        #[arg(long, value_name = "KEY=VALUE[,KEY=VALUE...]")]
+        #[arg(
+            long,
+            value_name = "KEY=VALUE[,KEY=VALUE...]",
+            conflicts_with_all = ["inject_file", "inject_manifest"]
+        )]
         inject: Option<String>,
-        #[arg(long, value_name = "PATH")]
+        #[arg(long, value_name = "PATH", conflicts_with = "inject_manifest")]
         inject_file: Option<PathBuf>,
+        /// TOML injection manifest; install-tests takes its `[env]` only.
+        #[arg(long, value_name = "PATH")]
+        inject_manifest: Option<PathBuf>,
+        /// Boot firmware for the harness VMs (`uefi` or `bios`); recorded in the report and
+        /// `scenarios status`.
+        #[arg(long, value_enum, default_value_t = Firmware::Uefi)]
+        firmware: Firmware,
+        /// Guest architecture for the harness VMs; non-x86_64 runs do not record spawn points.
+        #[arg(long, value_enum, default_value_t = Arch::X86_64)]
+        arch: Arch,
+        /// QEMU accelerator for the harness VMs. `auto` uses KVM when /dev/kvm is accessible and
+        /// falls back to TCG.
+        #[arg(long, value_enum, default_value_t = AccelMode::Auto)]
+        accel: AccelMode,
+        /// Retry failed runs up to N times when the flake policy allows it; cached passes make
+        /// a retry resume at the failed scenario.
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        retries: u32,
+    },
+
+    /// Check that Secure Boot refuses an unsigned and a tampered live UKI.
+    SecbootRefusal {
+        #[arg(value_enum, default_value_t = BootDistro::Levitate)]
+        distro: BootDistro,
+        #[arg(long, value_enum, default_value_t = AccelMode::Auto)]
+        accel: AccelMode,
+    },
+
+    /// Drive the 07Update A/B trial boot on the installed disk: compose slot B, trial boot it
+    /// once, then commit, fall back from a broken slot, or roll back.
+    AbTrial {
+        #[arg(value_enum, default_value_t = BootDistro::Levitate)]
+        distro: BootDistro,
+        /// Paths to run (comma-separated); each boots its own throwaway overlay.
+        #[arg(
+            long = "path",
+            value_enum,
+            value_delimiter = ',',
+            default_values_t = [AbTrialPath::Commit, AbTrialPath::Fallback, AbTrialPath::Rollback]
+        )]
+        paths: Vec<AbTrialPath>,
+        #[arg(long, value_enum, default_value_t = AccelMode::Auto)]
+        accel: AccelMode,
+        /// Boot a named snapshot instead of the recorded 06Runtime spawn point.
+        #[arg(long, value_name = "NAME")]
+        snapshot: Option<String>,
+        /// Seconds one boot may take to reach SSH; a trial boot that exceeds it counts as hung.
+        #[arg(long, default_value_t = 180)]
+        boot_timeout: u64,
+        /// SSH private key authorized for root on the installed system.
+        #[arg(long, value_name = "PATH")]
+        ssh_private_key: Option<PathBuf>,
     },
 
     /// Show scenario test status.
@@ -282,11 +569,150 @@ pub enum ScenariosCmd {
         distro: HarnessDistro,
     },
 
+    /// Show recorded scenario outcomes, flakiness scores and quarantined checks.
+    Flakes {
+        #[arg(value_enum, default_value_t = HarnessDistro::Levitate)]
+        distro: HarnessDistro,
+    },
+
     /// Reset cached scenario state for a distro.
     Reset {
         #[arg(value_enum, default_value_t = HarnessDistro::Levitate)]
         distro: HarnessDistro,
     },
+
+    /// Run the checkpoint ladder for several distros in parallel and print a pass matrix.
+    Matrix {
+        /// Distros to run (comma-separated).
+        #[arg(
+            long,
+            value_enum,
+            value_delimiter = ',',
+            default_values_t = [
+                HarnessDistro::Levitate,
+                HarnessDistro::Acorn,
+                HarnessDistro::Iuppiter,
+                HarnessDistro::Ralph,
+            ]
+        )]
+        distros: Vec<HarnessDistro>,
+        /// Last scenario of the ladder to run for every distro.
+        #[arg(long, value_name = "SCENARIO")]
+        up_to: String,
+        /// Maximum concurrent distro jobs (further capped by the memory budget).
+        #[arg(long, default_value_t = 2)]
+        jobs: usize,
+        /// Host memory budget in MiB for concurrent VMs (default: 80% of MemAvailable).
+        #[arg(long, value_name = "MIB")]
+        mem_budget: Option<u64>,
+        /// Memory reserved per job in MiB (guest RAM plus QEMU overhead).
+        #[arg(long, value_name = "MIB", default_value_t = 5120)]
+        job_mem: u64,
+        #[arg(long, value_name = "KEY=VALUE[,KEY=VALUE...]")]
+        inject: Option<String>,
+        #[arg(long, value_name = "PATH")]
+        inject_file: Option<PathBuf>,
+    },
+
+    /// Manage checkpoint spawn points and persisted snapshots.
+    Snapshots {
+        #[command(subcommand)]
+        cmd: SnapshotsCmd,
+    },
+
+    /// Control a running scenario VM over its QMP socket.
+    ///
+    /// The VM id is printed by `scenarios boot` (for example `levitate-live-boot`).
+    Vm {
+        id: String,
+        #[command(subcommand)]
+        cmd: VmCmd,
+    },
+
+    /// List running scenario VMs and their QMP, SSH, VNC and serial endpoints.
+    Ps,
+
+    /// Open an SSH shell on a running scenario VM, or follow its serial console.
+    Attach {
+        id: String,
+        /// Follow the serial log even when the VM has an SSH endpoint.
+        #[arg(long)]
+        serial: bool,
+    },
+
+    /// Stop a running scenario VM's QEMU (SIGTERM, then SIGKILL after the timeout).
+    Kill {
+        id: String,
+        /// Seconds to wait after SIGTERM before sending SIGKILL.
+        #[arg(long, default_value_t = 10)]
+        timeout: u64,
+    },
+
+    /// Run a command as root in a scenario VM booted with `--ssh`, exiting with its status.
+    Exec {
+        id: String,
+        #[arg(last = true, required = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+
+    /// Copy files between the host and a scenario VM booted with `--ssh`.
+    ///
+    /// Exactly one of SRC and DST is a guest path written `<vm-id>:<path>`.
+    Cp {
+        src: String,
+        dst: String,
+        /// Copy directories recursively.
+        #[arg(short, long)]
+        recursive: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SnapshotsCmd {
+    /// List recorded spawn points and persisted snapshots.
+    Ls {
+        #[arg(value_enum, default_value_t = HarnessDistro::Levitate)]
+        distro: HarnessDistro,
+    },
+
+    /// Remove a snapshot by name.
+    Rm {
+        name: String,
+        #[arg(value_enum, default_value_t = HarnessDistro::Levitate)]
+        distro: HarnessDistro,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum VmCmd {
+    /// Request ACPI powerdown and wait for QEMU to exit; quit QEMU after the timeout.
+    Shutdown {
+        /// Seconds to wait for the guest to power off before forcing QEMU to quit.
+        #[arg(long, default_value_t = 30)]
+        timeout: u64,
+    },
+
+    /// Write a screendump of the VM display (`.png` selects PNG, anything else PPM).
+    Screenshot {
+        #[arg(value_name = "PATH")]
+        path: PathBuf,
+    },
+
+    /// Send key chords (QEMU qcode names), for example `ctrl-alt-f2 ret`.
+    Keys {
+        /// Whitespace-separated chords; keys within a chord are joined by '-'.
+        #[arg(value_name = "SEQUENCE", required_unless_present = "text")]
+        sequence: Option<String>,
+        /// Type printable text (US layout) instead of raw chords.
+        #[arg(long, value_name = "TEXT", conflicts_with = "sequence")]
+        text: Option<String>,
+        /// Delay between chords in milliseconds.
+        #[arg(long, default_value_t = 50)]
+        delay_ms: u64,
+    },
+
+    /// Print the QEMU run status of the VM.
+    Status,
 }
 
 #[derive(Subcommand)]
@@ -394,6 +820,11 @@ pub enum TuiCmd {
         /// Keep raw terminal transcript next to plain output for debugging.
         #[arg(long)]
         keep_transcript: bool,
+
+        /// Also record the session as an asciicast v2 file (`.cast`); play it back with
+        /// `cargo xtask replay`.
+        #[arg(long)]
+        cast: bool,
     },
 }
 
@@ -1,12 +1,19 @@
 use anyhow::Result;
 use clap::Parser;
+use std::process::ExitCode;
 
 mod app;
 mod cli;
 mod tasks;
 mod util;
 
-fn main() -> Result<()> {
+fn main() -> Result<ExitCode> {
     let cli = crate::cli::Cli::parse();
-    crate::app::run(cli)
+    match crate::app::run(cli) {
+        Ok(()) => Ok(ExitCode::SUCCESS),
+        Err(err) => match err.downcast_ref::<crate::tasks::testing::vm::GuestExit>() {
+            Some(exit) => Ok(ExitCode::from(exit.code)),
+            None => Err(err),
+        },
+    }
 }
@@ -233,6 +233,7 @@ fn render_slug_transcript(
         rows,
         seconds,
         transcript_path,
+        cast_path: None,
     })
 }
 
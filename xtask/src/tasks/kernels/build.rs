@@ -2,16 +2,20 @@ use anyhow::{Context, Result};
 
 pub fn run(
     distro: crate::cli::Distro,
+    arch: crate::cli::Arch,
     rebuild_even_if_verified: bool,
     autofix: super::common::AutoFixOptions,
 ) -> Result<()> {
     let root = crate::util::repo::repo_root()?;
-    let t = super::common::target_for(distro);
+    let t = super::common::target_for(distro, arch);
 
     eprintln!("[info] Repo: {}", root.display());
     eprintln!(
-        "[info] Target: {} ({}{})",
-        t.distro_id, t.kernel.version, t.kernel.localversion
+        "[info] Target: {} {} ({}{})",
+        t.distro_id,
+        t.arch.as_str(),
+        t.kernel.version,
+        t.kernel.localversion
     );
 
     let need_build = rebuild_even_if_verified || !super::common::kernel_is_built(&root, &t);
@@ -30,10 +34,8 @@ pub fn run(
     if let Err(e) = super::common::build_kernel_via_recipe(
         &recipe_bin,
         &root,
-        t.distro_id,
+        &t,
         rebuild_even_if_verified,
-        t.kernel,
-        t.module_install_path,
         &autofix,
     ) {
         return Err(e).context(format!("Kernel build failed for {}", t.distro_id));
@@ -1,16 +1,20 @@
 use anyhow::{Context, Result};
 use std::path::Path;
 
-pub fn run(rebuild_even_if_verified: bool, autofix: super::common::AutoFixOptions) -> Result<()> {
+pub fn run(
+    arch: crate::cli::Arch,
+    rebuild_even_if_verified: bool,
+    autofix: super::common::AutoFixOptions,
+) -> Result<()> {
     let root = crate::util::repo::repo_root()?;
 
     eprintln!("[info] Repo: {}", root.display());
 
     let targets = [
-        super::common::target_for(crate::cli::Distro::Leviso),
-        super::common::target_for(crate::cli::Distro::AcornOS),
-        super::common::target_for(crate::cli::Distro::IuppiterOS),
-        super::common::target_for(crate::cli::Distro::RalphOS),
+        super::common::target_for(crate::cli::Distro::Leviso, arch),
+        super::common::target_for(crate::cli::Distro::AcornOS, arch),
+        super::common::target_for(crate::cli::Distro::IuppiterOS, arch),
+        super::common::target_for(crate::cli::Distro::RalphOS, arch),
     ];
 
     let need_build = rebuild_even_if_verified
@@ -31,7 +35,10 @@ pub fn run(rebuild_even_if_verified: bool, autofix: super::common::AutoFixOption
         None
     };
 
-    eprintln!("[info] Kernel targets (from distro-spec):");
+    eprintln!(
+        "[info] Kernel targets (from distro-spec, {}):",
+        arch.as_str()
+    );
     for t in targets.iter() {
         eprintln!(
             "  {}: {}{}",
@@ -47,10 +54,8 @@ pub fn run(rebuild_even_if_verified: bool, autofix: super::common::AutoFixOption
             super::common::build_kernel_via_recipe(
                 recipe_bin.as_path(),
                 &root,
-                t.distro_id,
+                t,
                 rebuild_even_if_verified,
-                t.kernel,
-                t.module_install_path,
                 &autofix,
             )
         })?;
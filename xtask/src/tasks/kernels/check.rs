@@ -1,6 +1,6 @@
 use anyhow::Result;
 
-pub fn run(distro: Option<crate::cli::Distro>) -> Result<()> {
+pub fn run(distro: Option<crate::cli::Distro>, arch: crate::cli::Arch) -> Result<()> {
     let root = crate::util::repo::repo_root()?;
     let distros: Vec<crate::cli::Distro> = match distro {
         Some(d) => vec![d],
@@ -14,12 +14,12 @@ pub fn run(distro: Option<crate::cli::Distro>) -> Result<()> {
 
     let mut fail = false;
     for d in distros {
-        let t = super::common::target_for(d);
+        let t = super::common::target_for(d, arch);
         match super::common::verify_one(&root, &t) {
-            Ok(rel) => eprintln!("[ok] {}: {}", t.distro_id, rel),
+            Ok(rel) => eprintln!("[ok] {} ({}): {}", t.distro_id, arch.as_str(), rel),
             Err(e) => {
                 fail = true;
-                eprintln!("[bad] {}: {:#}", t.distro_id, e);
+                eprintln!("[bad] {} ({}): {:#}", t.distro_id, arch.as_str(), e);
             }
         }
     }
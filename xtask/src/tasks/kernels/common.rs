@@ -7,6 +7,7 @@ use std::process::Command;
 #[derive(Clone, Copy)]
 pub(crate) struct KernelTarget {
     pub distro_id: &'static str,
+    pub arch: crate::cli::Arch,
     pub kernel: &'static distro_spec::shared::KernelSource,
     pub module_install_path: &'static str,
 }
@@ -19,31 +20,71 @@ pub(crate) struct AutoFixOptions {
     pub(crate) llm_profile: Option<String>,
 }
 
-pub(crate) fn target_for(d: crate::cli::Distro) -> KernelTarget {
+pub(crate) fn target_for(d: crate::cli::Distro, arch: crate::cli::Arch) -> KernelTarget {
     match d {
         crate::cli::Distro::Leviso => KernelTarget {
             distro_id: "levitate",
+            arch,
             kernel: &distro_spec::levitate::KERNEL_SOURCE,
             module_install_path: distro_spec::levitate::MODULE_INSTALL_PATH,
         },
         crate::cli::Distro::AcornOS => KernelTarget {
             distro_id: "acorn",
+            arch,
             kernel: &distro_spec::acorn::KERNEL_SOURCE,
             module_install_path: distro_spec::acorn::MODULE_INSTALL_PATH,
         },
         crate::cli::Distro::IuppiterOS => KernelTarget {
             distro_id: "iuppiter",
+            arch,
             kernel: &distro_spec::iuppiter::KERNEL_SOURCE,
             module_install_path: distro_spec::iuppiter::MODULE_INSTALL_PATH,
         },
         crate::cli::Distro::RalphOS => KernelTarget {
             distro_id: "ralph",
+            arch,
             kernel: &distro_spec::ralph::KERNEL_SOURCE,
             module_install_path: distro_spec::ralph::MODULE_INSTALL_PATH,
         },
     }
 }
 
+/// `.artifacts/kernel/<distro>/current` for x86_64; other architectures get their own
+/// `<distro>/<arch>/current` so both builds can coexist.
+pub(crate) fn artifact_root(root: &Path, t: &KernelTarget) -> PathBuf {
+    let distro_dir = root.join(".artifacts/kernel").join(t.distro_id);
+    match t.arch {
+        crate::cli::Arch::X86_64 => distro_dir.join("current"),
+        arch => distro_dir.join(arch.as_str()).join("current"),
+    }
+}
+
+/// The declared kconfig (`kernel/kconfig`) is shared by every architecture; non-x86_64 builds
+/// merge the delta fragment in an `<arch>/` directory next to it (`kernel/aarch64/kconfig`) on top.
+fn arch_kconfig_fragment(declared: &Path, arch: crate::cli::Arch) -> PathBuf {
+    match (declared.parent(), declared.file_name()) {
+        (Some(dir), Some(name)) => dir.join(arch.as_str()).join(name),
+        _ => declared.join(arch.as_str()),
+    }
+}
+
+/// Kconfig symbol every kernel built for `arch` has set.
+fn arch_config_symbol(arch: crate::cli::Arch) -> &'static str {
+    match arch {
+        crate::cli::Arch::X86_64 => "CONFIG_X86_64=y",
+        crate::cli::Arch::Aarch64 => "CONFIG_ARM64=y",
+    }
+}
+
+/// Recipe build dir, split per architecture like [`artifact_root`].
+fn work_dir(root: &Path, t: &KernelTarget) -> PathBuf {
+    let distro_dir = root.join(".artifacts/work").join(t.distro_id);
+    match t.arch {
+        crate::cli::Arch::X86_64 => distro_dir.join("downloads"),
+        arch => distro_dir.join(arch.as_str()).join("downloads"),
+    }
+}
+
 pub(crate) fn enforce_build_hours() -> Result<()> {
     let hhmm = run_capture("date", &["+%H%M"])?;
     let hhmm = hhmm.trim();
@@ -74,10 +115,7 @@ pub(crate) fn kernel_is_built(root: &Path, t: &KernelTarget) -> bool {
 }
 
 pub(crate) fn verify_one(root: &Path, t: &KernelTarget) -> Result<String> {
-    let kernel_root = root
-        .join(".artifacts/kernel")
-        .join(t.distro_id)
-        .join("current");
+    let kernel_root = artifact_root(root, t);
     let rel_file = kernel_root.join("kernel-build/include/config/kernel.release");
     let vmlinuz = kernel_root.join("staging/boot/vmlinuz");
 
@@ -88,6 +126,21 @@ pub(crate) fn verify_one(root: &Path, t: &KernelTarget) -> Result<String> {
         bail!("Missing vmlinuz: {}", vmlinuz.display());
     }
 
+    // Older artifact trees may not keep the build `.config`; only check it when present.
+    let config = kernel_root.join("kernel-build/.config");
+    if let Ok(raw) = fs::read_to_string(&config) {
+        let symbol = arch_config_symbol(t.arch);
+        if !raw.lines().any(|line| line.trim() == symbol) {
+            bail!(
+                "{} kernel at {} is not an {} build ({} lacks {symbol})",
+                t.distro_id,
+                kernel_root.display(),
+                t.arch.as_str(),
+                config.display()
+            );
+        }
+    }
+
     let rel =
         fs::read_to_string(&rel_file).with_context(|| format!("Reading {}", rel_file.display()))?;
     let rel = rel.trim_end_matches(['\n', '\r']).to_string();
@@ -140,27 +193,29 @@ pub(crate) fn build_recipe_bin(root: &Path) -> Result<PathBuf> {
 pub(crate) fn build_kernel_via_recipe(
     recipe_bin: &Path,
     root: &Path,
-    distro_id: &str,
+    t: &KernelTarget,
     force_rebuild: bool,
-    kernel: &distro_spec::shared::KernelSource,
-    module_install_path: &str,
     autofix: &AutoFixOptions,
 ) -> Result<()> {
+    let (distro_id, kernel, module_install_path) = (t.distro_id, t.kernel, t.module_install_path);
     let bundle = load_variant_contract_bundle_for_distro_from(root, distro_id)
         .with_context(|| format!("loading canonical variant contract for '{}'", distro_id))?;
     let recipe_rhai = root.join("distro-builder/recipes/linux.rhai");
-    let build_dir = root
-        .join(".artifacts/work")
-        .join(distro_id)
-        .join("downloads");
+    let build_dir = work_dir(root, t);
     let recipes_path = root.join("distro-builder/recipes");
     let kconfig_path = bundle
         .paths
         .build_host_declared_path(&bundle.contract.build.kernel.kconfig_path);
-    let kernel_artifact_root = root
-        .join(".artifacts/kernel")
-        .join(distro_id)
-        .join("current");
+    let kernel_artifact_root = artifact_root(root, t);
+    let fragment = arch_kconfig_fragment(&kconfig_path, t.arch);
+    if t.arch != crate::cli::Arch::X86_64 && !fragment.is_file() {
+        bail!(
+            "missing {} kconfig fragment for {}: {}",
+            t.arch.as_str(),
+            distro_id,
+            fragment.display()
+        );
+    }
 
     let mut cmd = Command::new(recipe_bin);
     cmd.current_dir(root);
@@ -195,6 +250,20 @@ pub(crate) fn build_kernel_via_recipe(
             "--define",
             &format!("MODULE_INSTALL_PATH={module_install_path}"),
         ]);
+    // x86_64 builds keep the recipe's native defaults; other architectures merge their delta
+    // fragment over the shared kconfig and cross-compile unless the host already matches.
+    if t.arch != crate::cli::Arch::X86_64 {
+        cmd.args(["--define", &format!("KERNEL_ARCH={}", t.arch.kernel_arch())])
+            .args([
+                "--define",
+                &format!("KERNEL_KCONFIG_FRAGMENT={}", fragment.display()),
+            ]);
+        if crate::cli::Arch::host() != Some(t.arch) {
+            let cross = std::env::var("CROSS_COMPILE")
+                .unwrap_or_else(|_| format!("{}-linux-gnu-", t.arch.as_str()));
+            cmd.args(["--define", &format!("KERNEL_CROSS_COMPILE={cross}")]);
+        }
+    }
 
     if autofix.enabled {
         cmd.args(["--autofix"]);
@@ -212,21 +281,14 @@ pub(crate) fn build_kernel_via_recipe(
 pub(crate) fn install_prebuilt_kernel_via_recipe(
     recipe_bin: &Path,
     root: &Path,
-    distro_id: &str,
+    t: &KernelTarget,
     force_redownload: bool,
-    kernel: &distro_spec::shared::KernelSource,
-    module_install_path: &str,
 ) -> Result<()> {
+    let (kernel, module_install_path) = (t.kernel, t.module_install_path);
     let recipe_rhai = root.join("distro-builder/recipes/linux-prebuilt.rhai");
-    let build_dir = root
-        .join(".artifacts/work")
-        .join(distro_id)
-        .join("downloads");
+    let build_dir = work_dir(root, t);
     let recipes_path = root.join("distro-builder/recipes");
-    let kernel_artifact_root = root
-        .join(".artifacts/kernel")
-        .join(distro_id)
-        .join("current");
+    let kernel_artifact_root = artifact_root(root, t);
 
     let mut cmd = Command::new(recipe_bin);
     cmd.current_dir(root)
@@ -2,7 +2,8 @@ use anyhow::{Context, Result};
 
 pub fn run(distro: crate::cli::Distro, refresh: bool) -> Result<()> {
     let root = crate::util::repo::repo_root()?;
-    let t = super::common::target_for(distro);
+    // Prebuilt kernels are published for x86_64 only.
+    let t = super::common::target_for(distro, crate::cli::Arch::X86_64);
 
     eprintln!("[info] Repo: {}", root.display());
     eprintln!(
@@ -18,15 +19,8 @@ pub fn run(distro: crate::cli::Distro, refresh: bool) -> Result<()> {
     let recipe_bin = super::common::build_recipe_bin(&root).context("Building recipe binary")?;
 
     eprintln!("[step] Install prebuilt kernel: {}", t.distro_id);
-    super::common::install_prebuilt_kernel_via_recipe(
-        &recipe_bin,
-        &root,
-        t.distro_id,
-        refresh,
-        t.kernel,
-        t.module_install_path,
-    )
-    .with_context(|| format!("Prebuilt kernel install failed for {}", t.distro_id))?;
+    super::common::install_prebuilt_kernel_via_recipe(&recipe_bin, &root, &t, refresh)
+        .with_context(|| format!("Prebuilt kernel install failed for {}", t.distro_id))?;
 
     let rel = super::common::verify_one(&root, &t).with_context(|| {
         format!(
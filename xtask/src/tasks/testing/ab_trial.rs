@@ -0,0 +1,468 @@
+use super::registry::SshEndpoint;
+use anyhow::{Context, Result, bail};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// GPT partition labels of the two system slots (`EFI + ROOT_A + ROOT_B + VAR`, see
+/// `docs/ab-default-plan.md`).
+const SLOT_A_LABEL: &str = "ROOT_A";
+const SLOT_B_LABEL: &str = "ROOT_B";
+/// Written into the composed slot; its presence after a reboot shows the change is live.
+const TRIAL_STAMP_PATH: &str = "/etc/levitate-ab-trial";
+
+/// Prints the boot id and the partition label of `/`. Argument: trial stamp path.
+const PROBE_SCRIPT: &str = r#"set -eu
+src=$(findmnt -no SOURCE /)
+label=$(blkid -s PARTLABEL -o value "$src" 2>/dev/null || true)
+stamp=$(cat "$1" 2>/dev/null || true)
+printf 'boot_id=%s\nroot=%s\nlabel=%s\nstamp=%s\n' "$(cat /proc/sys/kernel/random/boot_id)" "$src" "$label" "$stamp"
+"#;
+
+/// Refuses disks without both slot partitions or without systemd-boot, then prints each slot's
+/// PARTUUID (`slot\t<label>\t<partuuid>`) and each systemd-boot entry with its kernel options
+/// (`entry\t<id>\t<options>`).
+const LAYOUT_SCRIPT: &str = r#"set -eu
+for slot in ROOT_A ROOT_B; do
+  if [ ! -e "/dev/disk/by-partlabel/$slot" ]; then
+    echo "no $slot partition: the installed disk has no A/B slots" >&2
+    exit 3
+  fi
+done
+if ! command -v bootctl >/dev/null 2>&1 || ! bootctl is-installed >/dev/null 2>&1; then
+  echo "systemd-boot is not installed: A/B trial boots drive systemd-boot entries" >&2
+  exit 3
+fi
+for slot in ROOT_A ROOT_B; do
+  printf 'slot\t%s\t%s\n' "$slot" "$(blkid -s PARTUUID -o value "/dev/disk/by-partlabel/$slot")"
+done
+esp=$(bootctl --print-esp-path)
+for entry in "$esp"/loader/entries/*.conf; do
+  [ -e "$entry" ] || continue
+  printf 'entry\t%s\t%s\n' "${entry##*/}" "$(sed -n 's/^options[[:space:]]*//p' "$entry" | tr '\n' ' ')"
+done
+"#;
+
+/// Copies the running slot into the inactive one and stamps it. Arguments: target label, source
+/// label, stamp, trial stamp path.
+const COMPOSE_SCRIPT: &str = r#"set -eu
+target=$1 source=$2 stamp=$3 stamp_path=$4
+dev=/dev/disk/by-partlabel/$target
+mnt=$(mktemp -d)
+mount "$dev" "$mnt"
+trap 'umount "$mnt"; rmdir "$mnt"' EXIT
+find "$mnt" -mindepth 1 -maxdepth 1 -exec rm -rf {} +
+cp -ax /. "$mnt"/
+sed -i "s/PARTLABEL=$source\([[:space:]]\)/PARTLABEL=$target\1/" "$mnt/etc/fstab"
+printf '%s\n' "$stamp" > "$mnt$stamp_path"
+sync
+"#;
+
+/// Wipes the filesystem signature of a slot so its root mount fails. Argument: slot label.
+const BREAK_SCRIPT: &str = r#"set -eu
+dev=/dev/disk/by-partlabel/$1
+wipefs -a "$dev"
+sync
+"#;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Slot {
+    A,
+    B,
+}
+
+impl Slot {
+    fn from_label(label: &str) -> Result<Self> {
+        match label.trim() {
+            SLOT_A_LABEL => Ok(Self::A),
+            SLOT_B_LABEL => Ok(Self::B),
+            other => bail!(
+                "root partition label '{other}' is not an A/B slot (expected {SLOT_A_LABEL} or {SLOT_B_LABEL})"
+            ),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::A => SLOT_A_LABEL,
+            Self::B => SLOT_B_LABEL,
+        }
+    }
+
+    fn other(self) -> Self {
+        match self {
+            Self::A => Self::B,
+            Self::B => Self::A,
+        }
+    }
+}
+
+/// What the guest reported about the boot it is running.
+#[derive(Debug, PartialEq, Eq)]
+struct Probe {
+    boot_id: String,
+    slot: Slot,
+    stamp: Option<String>,
+}
+
+fn parse_probe(raw: &str) -> Result<Probe> {
+    let field = |key: &str| {
+        raw.lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+            .map(str::trim)
+            .unwrap_or_default()
+    };
+    let boot_id = field("boot_id");
+    if boot_id.is_empty() {
+        bail!("slot probe printed no boot id:\n{raw}");
+    }
+    let label = field("label");
+    let slot = Slot::from_label(label)
+        .with_context(|| format!("identifying the slot mounted from '{}'", field("root")))?;
+    let stamp = Some(field("stamp"))
+        .filter(|stamp| !stamp.is_empty())
+        .map(str::to_string);
+    Ok(Probe {
+        boot_id: boot_id.to_string(),
+        slot,
+        stamp,
+    })
+}
+
+/// Picks the systemd-boot entry that boots each slot from the `LAYOUT_SCRIPT` listing: the one
+/// whose `root=` names the slot by `PARTLABEL` or `PARTUUID`.
+fn slot_entries(listing: &str) -> Result<(String, String)> {
+    let mut partuuids = Vec::new();
+    let mut entries = Vec::new();
+    for line in listing.lines() {
+        let mut fields = line.splitn(3, '\t');
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some("slot"), Some(label), Some(partuuid)) => {
+                partuuids.push((label.trim(), partuuid.trim()))
+            }
+            (Some("entry"), Some(id), options) => {
+                entries.push((id.trim(), options.unwrap_or_default()))
+            }
+            _ => {}
+        }
+    }
+    let find = |slot: Slot| -> Result<String> {
+        let label = slot.label();
+        let partuuid = partuuids
+            .iter()
+            .find(|(name, _)| *name == label)
+            .map(|(_, partuuid)| *partuuid)
+            .filter(|partuuid| !partuuid.is_empty());
+        let boots_slot = |options: &str| {
+            options.split_whitespace().any(|arg| {
+                let Some(root) = arg.strip_prefix("root=") else {
+                    return false;
+                };
+                root == format!("PARTLABEL={label}")
+                    || root
+                        .strip_prefix("PARTUUID=")
+                        .zip(partuuid)
+                        .is_some_and(|(found, want)| found.eq_ignore_ascii_case(want))
+            })
+        };
+        let found: Vec<&str> = entries
+            .iter()
+            .filter(|(_, options)| boots_slot(options))
+            .map(|(id, _)| *id)
+            .collect();
+        match found.as_slice() {
+            [entry] if valid_entry_id(entry) => Ok(entry.to_string()),
+            [entry] => bail!("unexpected characters in boot entry id '{entry}'"),
+            [] => bail!(
+                "no systemd-boot entry boots {label} (root=PARTLABEL={label} or its PARTUUID); entries: {}",
+                entries
+                    .iter()
+                    .map(|(id, _)| *id)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            many => bail!(
+                "several systemd-boot entries boot {label}: {}",
+                many.join(", ")
+            ),
+        }
+    };
+    Ok((find(Slot::A)?, find(Slot::B)?))
+}
+
+fn valid_entry_id(entry: &str) -> bool {
+    entry
+        .chars()
+        .all(|ch| ch.is_ascii_alphanumeric() || "-_.+".contains(ch))
+}
+
+/// One headless overlay VM of the installed system, reachable over SSH.
+pub(super) struct TrialVm<'a> {
+    pub(super) ssh: SshEndpoint,
+    pub(super) qmp_socket: &'a Path,
+    pub(super) child: &'a mut Child,
+    /// Budget for one boot from reset to SSH.
+    pub(super) boot_timeout: Duration,
+}
+
+impl TrialVm<'_> {
+    /// Runs `script` through `sh -s` in the guest and returns its stdout.
+    fn run(&mut self, script: &str, args: &[&str]) -> Result<String> {
+        let mut child = Command::new("ssh")
+            .args(self.ssh.args())
+            .arg("root@127.0.0.1")
+            .arg("--")
+            .arg(format!("sh -s -- {}", args.join(" ")))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("launching ssh")?;
+        child
+            .stdin
+            .take()
+            .context("ssh stdin is piped")?
+            .write_all(script.as_bytes())
+            .context("sending guest script")?;
+        let output = child.wait_with_output().context("waiting for ssh")?;
+        if !output.status.success() {
+            bail!(
+                "guest script failed with status {}:\n{}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn probe(&mut self) -> Result<Probe> {
+        let raw = self.run(PROBE_SCRIPT, &[TRIAL_STAMP_PATH])?;
+        parse_probe(&raw)
+    }
+
+    /// Waits until SSH answers from a boot other than `previous`. Returns `None` on timeout.
+    fn wait_for_boot(&mut self, previous: Option<&str>) -> Result<Option<Probe>> {
+        let deadline = Instant::now() + self.boot_timeout;
+        while Instant::now() < deadline {
+            if let Some(status) = self.child.try_wait()? {
+                bail!("QEMU exited ({status}) while waiting for the guest to boot");
+            }
+            match self.probe() {
+                Ok(probe) if previous != Some(probe.boot_id.as_str()) => return Ok(Some(probe)),
+                _ => {}
+            }
+            sleep(Duration::from_secs(2));
+        }
+        Ok(None)
+    }
+
+    fn reboot(&mut self, previous: &Probe) -> Result<Option<Probe>> {
+        // The connection drops as the guest goes down, so the exit status is not meaningful.
+        let _ = self.run("(sleep 1; reboot) >/dev/null 2>&1 &\n", &[]);
+        self.wait_for_boot(Some(&previous.boot_id))
+    }
+
+    /// Resets the VM from the host, standing in for the watchdog that catches a hung trial boot.
+    fn reset(&mut self) -> Result<()> {
+        let mut client =
+            crate::util::qmp::QmpClient::connect(self.qmp_socket, Duration::from_secs(5))?;
+        client.execute("system_reset", None)?;
+        Ok(())
+    }
+}
+
+/// One of the 07Update slot transitions driven by `scenarios ab-trial`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum TrialPath {
+    /// Trial boot the composed slot once, then make it the default.
+    Commit,
+    /// Trial boot a broken slot; the one-shot entry is spent and the next boot is back on A.
+    Fallback,
+    /// Commit the composed slot as the default, then explicitly switch the default back.
+    Rollback,
+}
+
+impl TrialPath {
+    pub(super) fn from_cli(path: crate::cli::AbTrialPath) -> Self {
+        match path {
+            crate::cli::AbTrialPath::Commit => Self::Commit,
+            crate::cli::AbTrialPath::Fallback => Self::Fallback,
+            crate::cli::AbTrialPath::Rollback => Self::Rollback,
+        }
+    }
+
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            Self::Commit => "commit",
+            Self::Fallback => "fallback",
+            Self::Rollback => "rollback",
+        }
+    }
+
+    /// Evidence marker printed when the path passes (`<SCENARIO WORDS> PASSED`).
+    pub(super) fn pass_marker(self) -> &'static str {
+        match self {
+            Self::Commit => "AB COMMIT PASSED",
+            Self::Fallback => "AB FALLBACK PASSED",
+            Self::Rollback => "AB ROLLBACK PASSED",
+        }
+    }
+}
+
+/// Drives `path` on a freshly booted VM that has not been touched yet.
+pub(super) fn run_path(vm: &mut TrialVm<'_>, path: TrialPath) -> Result<()> {
+    let Some(start) = vm.wait_for_boot(None)? else {
+        bail!(
+            "installed system did not answer over SSH within {}s",
+            vm.boot_timeout.as_secs()
+        );
+    };
+    let layout = vm
+        .run(LAYOUT_SCRIPT, &[])
+        .context("checking the installed disk for A/B slots")?;
+    let (entry_a, entry_b) = slot_entries(&layout)?;
+    if start.slot != Slot::A {
+        bail!(
+            "trial must start from {SLOT_A_LABEL}, but the installed system booted {}",
+            start.slot.label()
+        );
+    }
+    let stamp = format!("{}-{}", path.as_str(), start.boot_id);
+    let inactive = start.slot.other();
+    eprintln!(
+        "[ab-trial] {}: booted {} (entry {entry_a}); composing {}",
+        path.as_str(),
+        start.slot.label(),
+        inactive.label()
+    );
+    vm.run(
+        COMPOSE_SCRIPT,
+        &[
+            inactive.label(),
+            start.slot.label(),
+            &stamp,
+            TRIAL_STAMP_PATH,
+        ],
+    )?;
+    if path == TrialPath::Fallback {
+        eprintln!("[ab-trial] fallback: breaking {}", inactive.label());
+        vm.run(BREAK_SCRIPT, &[inactive.label()])?;
+    }
+    vm.run(&format!("bootctl set-oneshot {entry_b}\n"), &[])?;
+    eprintln!("[ab-trial] {}: trial boot of {entry_b}", path.as_str());
+    let trial = vm.reboot(&start)?;
+
+    match path {
+        TrialPath::Commit => {
+            let trial = expect_slot(trial, inactive, Some(&stamp), "trial boot", vm)?;
+            vm.run(&format!("bootctl set-default {entry_b}\n"), &[])?;
+            let committed = vm.reboot(&trial)?;
+            expect_slot(committed, inactive, Some(&stamp), "boot after commit", vm)?;
+        }
+        TrialPath::Fallback => {
+            let fallback = match trial {
+                Some(probe) => Some(probe),
+                None => {
+                    eprintln!(
+                        "[ab-trial] fallback: broken slot did not come up within {}s; resetting",
+                        vm.boot_timeout.as_secs()
+                    );
+                    vm.reset()?;
+                    vm.wait_for_boot(Some(&start.boot_id))?
+                }
+            };
+            expect_slot(fallback, start.slot, None, "boot after failed trial", vm)?;
+        }
+        TrialPath::Rollback => {
+            // Commit first so the default entry really points at the new slot; rolling back
+            // from a one-shot trial would only show the default was never changed.
+            let trial = expect_slot(trial, inactive, Some(&stamp), "trial boot", vm)?;
+            vm.run(&format!("bootctl set-default {entry_b}\n"), &[])?;
+            let committed = vm.reboot(&trial)?;
+            let committed =
+                expect_slot(committed, inactive, Some(&stamp), "boot after commit", vm)?;
+            vm.run(&format!("bootctl set-default {entry_a}\n"), &[])?;
+            let rolled_back = vm.reboot(&committed)?;
+            expect_slot(rolled_back, start.slot, None, "boot after rollback", vm)?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `probe` came from `slot` and carries `stamp` (or no stamp for the original slot).
+fn expect_slot(
+    probe: Option<Probe>,
+    slot: Slot,
+    stamp: Option<&str>,
+    what: &str,
+    vm: &TrialVm<'_>,
+) -> Result<Probe> {
+    let Some(probe) = probe else {
+        bail!(
+            "{what}: guest did not answer over SSH within {}s",
+            vm.boot_timeout.as_secs()
+        );
+    };
+    if probe.slot != slot {
+        bail!(
+            "{what}: expected {} but the guest booted {}",
+            slot.label(),
+            probe.slot.label()
+        );
+    }
+    if probe.stamp.as_deref() != stamp {
+        bail!(
+            "{what}: {} has trial stamp {:?} in {TRIAL_STAMP_PATH}, expected {:?}",
+            slot.label(),
+            probe.stamp,
+            stamp
+        );
+    }
+    eprintln!("[ab-trial] {what}: {} verified", slot.label());
+    Ok(probe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_and_entries_identify_slots() {
+        let probe =
+            parse_probe("boot_id=2f1c\nroot=/dev/vda3\nlabel=ROOT_B\nstamp=commit-1a2b\n").unwrap();
+        assert_eq!(
+            probe,
+            Probe {
+                boot_id: "2f1c".to_string(),
+                slot: Slot::B,
+                stamp: Some("commit-1a2b".to_string()),
+            }
+        );
+        let unstamped = parse_probe("boot_id=9e\nroot=/dev/vda2\nlabel=ROOT_A\nstamp=\n").unwrap();
+        assert_eq!((unstamped.slot, unstamped.stamp), (Slot::A, None));
+        assert!(parse_probe("boot_id=9e\nroot=/dev/vda2\nlabel=rootfs\nstamp=\n").is_err());
+
+        let listing = "slot\tROOT_A\t6c1e-aa\n\
+                       slot\tROOT_B\t7d2f-bb\n\
+                       entry\tlevitate.conf\troot=PARTLABEL=ROOT_A rw quiet \n\
+                       entry\tlevitate-trial.conf\troot=PARTUUID=7D2F-BB rw \n\
+                       entry\tmemtest.conf\t\n";
+        let (a, b) = slot_entries(listing).unwrap();
+        assert_eq!(
+            (a.as_str(), b.as_str()),
+            ("levitate.conf", "levitate-trial.conf")
+        );
+        assert!(
+            slot_entries("slot\tROOT_A\t6c1e-aa\nentry\tx.conf\troot=PARTLABEL=ROOT_A\n").is_err()
+        );
+        assert!(
+            slot_entries(
+                "entry\tx.conf\troot=PARTLABEL=ROOT_A\nentry\ty.conf\troot=PARTLABEL=ROOT_A\n\
+                 entry\tz.conf\troot=PARTLABEL=ROOT_B\n"
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,363 @@
+use super::crash::{CrashPatterns, GuestCrash};
+use crate::cli::Firmware;
+use anyhow::{Context, Result, bail};
+use distro_contract::load_variant_contract_bundle_for_distro_from;
+use regex::Regex;
+use std::path::Path;
+
+/// Harness default used when a variant does not declare live-boot success patterns.
+const DEFAULT_LIVE_BOOT_SUCCESS: &str = "switching root to live system";
+
+/// Prefix marking a scenarios.toml pattern as a regular expression instead of a literal.
+const REGEX_PREFIX: &str = "re:";
+
+/// Kernel log line carrying the booted command line (only visible at loglevel >= 5).
+const KERNEL_CMDLINE_MARKER: &str = "Kernel command line:";
+
+/// Prefixes scoping a scenarios.toml pattern to one boot firmware, e.g. `uefi:re:BdsDxe: ...`.
+const FIRMWARE_SCOPES: &[(&str, Firmware)] =
+    &[("uefi:", Firmware::Uefi), ("bios:", Firmware::Bios)];
+
+/// SeaBIOS gives up with this line when no boot sector (MBR or El Torito) is found.
+const SEABIOS_NO_BOOT_DEVICE: &str = "No bootable device";
+
+/// OVMF BdsDxe reports images rejected by Secure Boot image verification with one of these.
+pub(crate) const SECURE_BOOT_REFUSAL: &str =
+    "re:BdsDxe: failed to load .*(Access Denied|Security Violation)";
+
+#[derive(Clone, Debug)]
+pub(crate) struct BootPattern {
+    source: String,
+    kind: &'static str,
+    matcher: PatternMatcher,
+}
+
+#[derive(Clone, Debug)]
+enum PatternMatcher {
+    Literal(String),
+    Regex(Regex),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct PatternMatch {
+    pub(crate) kind: &'static str,
+    pub(crate) pattern: String,
+    pub(crate) line: String,
+}
+
+impl std::fmt::Display for PatternMatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} pattern '{}' matched: {}",
+            self.kind,
+            self.pattern,
+            self.line.trim()
+        )
+    }
+}
+
+impl BootPattern {
+    /// Parses a scenarios.toml pattern. Plain strings match case-insensitively as substrings;
+    /// `re:<expr>` strings are compiled as regular expressions.
+    pub(crate) fn parse(raw: &str, kind: &'static str) -> Result<Self> {
+        let matcher = match raw.strip_prefix(REGEX_PREFIX) {
+            Some(expr) => PatternMatcher::Regex(
+                Regex::new(expr)
+                    .with_context(|| format!("compiling {kind} regex pattern '{raw}'"))?,
+            ),
+            None => {
+                if raw.trim().is_empty() {
+                    bail!("{kind} pattern must not be empty");
+                }
+                PatternMatcher::Literal(raw.to_lowercase())
+            }
+        };
+        Ok(Self {
+            source: raw.to_string(),
+            kind,
+            matcher,
+        })
+    }
+
+    fn literal(raw: &str, kind: &'static str) -> Self {
+        Self {
+            source: raw.to_string(),
+            kind,
+            matcher: PatternMatcher::Literal(raw.to_lowercase()),
+        }
+    }
+
+    pub(crate) fn source(&self) -> &str {
+        &self.source
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        match &self.matcher {
+            PatternMatcher::Literal(needle) => line.to_lowercase().contains(needle),
+            PatternMatcher::Regex(re) => re.is_match(line),
+        }
+    }
+
+    pub(crate) fn find(&self, content: &str) -> Option<PatternMatch> {
+        content
+            .lines()
+            .find(|line| self.is_match(line))
+            .map(|line| PatternMatch {
+                kind: self.kind,
+                pattern: self.source.clone(),
+                line: line.to_string(),
+            })
+    }
+}
+
+/// Live-boot expectations for one distro variant, loaded from `scenarios.toml`.
+#[derive(Clone, Debug)]
+pub(crate) struct LiveBootPatterns {
+    pub(crate) distro_id: String,
+    success: Vec<BootPattern>,
+    fatal: Vec<BootPattern>,
+    crash: CrashPatterns,
+    pub(crate) required_kernel_cmdline: Vec<String>,
+}
+
+impl LiveBootPatterns {
+    /// Loads the live-boot patterns that apply to `firmware`; see [`scope_to_firmware`].
+    pub(crate) fn load(root: &Path, distro_id: &str, firmware: Firmware) -> Result<Self> {
+        let bundle = load_variant_contract_bundle_for_distro_from(root, distro_id)
+            .with_context(|| format!("loading canonical variant contract for '{}'", distro_id))?;
+        let live_boot = &bundle.contract.scenarios.live_boot;
+        let mut patterns = Self::from_declared(
+            distro_id,
+            &scope_to_firmware(&live_boot.success_patterns, firmware),
+            &scope_to_firmware(&live_boot.fatal_patterns, firmware),
+            &live_boot.required_kernel_cmdline,
+            &live_boot.required_live_services,
+        )
+        .with_context(|| format!("parsing scenarios.live_boot patterns for '{}'", distro_id))?;
+        match firmware {
+            Firmware::Bios => patterns.fatal.push(BootPattern::literal(
+                SEABIOS_NO_BOOT_DEVICE,
+                "firmware boot failure",
+            )),
+            Firmware::Secboot => patterns.fatal.push(BootPattern::parse(
+                SECURE_BOOT_REFUSAL,
+                "secure boot refusal",
+            )?),
+            Firmware::Uefi => {}
+        }
+        Ok(patterns)
+    }
+
+    fn from_declared(
+        distro_id: &str,
+        success_patterns: &[String],
+        fatal_patterns: &[String],
+        required_kernel_cmdline: &[String],
+        required_live_services: &[String],
+    ) -> Result<Self> {
+        let mut success = success_patterns
+            .iter()
+            .map(|raw| BootPattern::parse(raw, "success"))
+            .collect::<Result<Vec<_>>>()?;
+        if success.is_empty() {
+            success.push(BootPattern::literal(DEFAULT_LIVE_BOOT_SUCCESS, "success"));
+        }
+
+        // Host-side QEMU failures apply to every variant regardless of its init system, as does
+        // the Debian-style `ssh` unit name that `required_live_services` does not cover.
+        let mut fatal = vec![
+            BootPattern::literal(
+                "could not set up host forwarding rule",
+                "hostfwd setup failed",
+            ),
+            BootPattern::literal("failed to start ssh.service", "sshd failure"),
+        ];
+        for raw in fatal_patterns {
+            fatal.push(BootPattern::parse(raw, "fatal")?);
+        }
+        for service in required_live_services {
+            fatal.extend(service_failure_patterns(service));
+        }
+
+        Ok(Self {
+            distro_id: distro_id.to_string(),
+            success,
+            fatal,
+            crash: CrashPatterns::new(),
+            required_kernel_cmdline: required_kernel_cmdline.to_vec(),
+        })
+    }
+
+    pub(crate) fn detect_success(&self, content: &str) -> Option<PatternMatch> {
+        self.success
+            .iter()
+            .find_map(|pattern| pattern.find(content))
+    }
+
+    pub(crate) fn detect_fatal(&self, content: &str) -> Option<PatternMatch> {
+        self.fatal.iter().find_map(|pattern| pattern.find(content))
+    }
+
+    /// Kernel panics, oopses, `BUG:`s and hung tasks apply to every variant.
+    pub(crate) fn detect_crash(&self, content: &str) -> Option<GuestCrash> {
+        self.crash.detect(content)
+    }
+
+    /// Returns the required kernel arguments missing from `cmdline`.
+    pub(crate) fn missing_kernel_cmdline(&self, cmdline: &str) -> Vec<String> {
+        let present: Vec<&str> = cmdline.split_whitespace().collect();
+        self.required_kernel_cmdline
+            .iter()
+            .filter(|arg| !present.contains(&arg.as_str()))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Service failure signatures for both systemd and OpenRC, scoped to a declared live service.
+fn service_failure_patterns(service: &str) -> Vec<BootPattern> {
+    let service = service.trim();
+    if service.is_empty() {
+        return Vec::new();
+    }
+    vec![
+        BootPattern::literal(
+            &format!("failed to start {service}.service"),
+            "service failure",
+        ),
+        BootPattern::literal(
+            &format!("{service}.service: failed with result"),
+            "service failure",
+        ),
+        BootPattern::literal(
+            &format!("{service}.service: start request repeated too quickly"),
+            "service failure",
+        ),
+        BootPattern::literal(
+            &format!("ERROR: {service} failed to start"),
+            "service failure",
+        ),
+    ]
+}
+
+/// Keeps unscoped patterns plus those prefixed for `firmware`, with the prefix removed.
+/// `uefi:` patterns also apply to Secure Boot runs, which boot the same OVMF.
+pub(crate) fn scope_to_firmware(patterns: &[String], firmware: Firmware) -> Vec<String> {
+    patterns
+        .iter()
+        .filter_map(|raw| {
+            match FIRMWARE_SCOPES
+                .iter()
+                .find(|(prefix, _)| raw.starts_with(prefix))
+            {
+                Some((prefix, scope)) => {
+                    let applies =
+                        *scope == firmware || (*scope == Firmware::Uefi && firmware.is_uefi());
+                    applies.then(|| raw[prefix.len()..].to_string())
+                }
+                None => Some(raw.clone()),
+            }
+        })
+        .collect()
+}
+
+/// Extracts the booted kernel command line from a serial log when the kernel printed it.
+pub(crate) fn kernel_cmdline_from_log(content: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        line.find(KERNEL_CMDLINE_MARKER)
+            .map(|idx| line[idx + KERNEL_CMDLINE_MARKER.len()..].trim().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(raw: &[&str]) -> Vec<String> {
+        raw.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn empty_success_patterns_fall_back_to_root_switch_marker() {
+        let patterns =
+            LiveBootPatterns::from_declared("levitate", &[], &[], &[], &[]).expect("patterns");
+        let matched = patterns
+            .detect_success("[  3.1] Switching root to live system\n")
+            .expect("success match");
+        assert_eq!(matched.pattern, DEFAULT_LIVE_BOOT_SUCCESS);
+    }
+
+    #[test]
+    fn regex_patterns_report_matching_line() {
+        let patterns = LiveBootPatterns::from_declared(
+            "acorn",
+            &strings(&["re:^acorn login:\\s*$"]),
+            &strings(&["re:(?i)kernel panic"]),
+            &[],
+            &[],
+        )
+        .expect("patterns");
+        assert!(patterns.detect_success("xacorn login:").is_none());
+        assert!(patterns.detect_success("acorn login: ").is_some());
+        let fatal = patterns
+            .detect_fatal("boot\nKernel panic - not syncing\n")
+            .expect("fatal match");
+        assert_eq!(fatal.line, "Kernel panic - not syncing");
+    }
+
+    #[test]
+    fn firmware_scoped_patterns_keep_matching_scope_only() {
+        let declared = strings(&["uefi:re:BdsDxe: loading", "bios:Booting from DVD", "login:"]);
+        assert_eq!(
+            scope_to_firmware(&declared, Firmware::Bios),
+            strings(&["Booting from DVD", "login:"])
+        );
+        assert_eq!(
+            scope_to_firmware(&declared, Firmware::Uefi),
+            strings(&["re:BdsDxe: loading", "login:"])
+        );
+    }
+
+    #[test]
+    fn service_failures_follow_declared_services_only() {
+        let openrc = LiveBootPatterns::from_declared(
+            "iuppiter",
+            &[],
+            &[],
+            &[],
+            &strings(&["networking", "sshd"]),
+        )
+        .expect("patterns");
+        assert!(
+            openrc
+                .detect_fatal(" * ERROR: sshd failed to start")
+                .is_some()
+        );
+        assert!(
+            openrc
+                .detect_fatal("Failed to start auditd.service")
+                .is_none()
+        );
+        assert!(
+            openrc
+                .detect_fatal("[FAILED] Failed to start ssh.service - OpenBSD Secure Shell server.")
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn missing_kernel_cmdline_compares_whole_arguments() {
+        let patterns = LiveBootPatterns::from_declared(
+            "levitate",
+            &[],
+            &[],
+            &strings(&["audit=1", "loglevel=3"]),
+            &[],
+        )
+        .expect("patterns");
+        let cmdline = kernel_cmdline_from_log("[0.0] Kernel command line: audit=10 loglevel=3\n")
+            .expect("cmdline");
+        assert_eq!(patterns.missing_kernel_cmdline(&cmdline), vec!["audit=1"]);
+    }
+}
@@ -0,0 +1,531 @@
+use super::boot_patterns::{BootPattern, scope_to_firmware};
+use crate::cli::Firmware;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const SPEC_PATH: &str = "distro-variants/_shared/scenarios/boot-timeline.toml";
+const SPEC_SCHEMA_VERSION: u32 = 1;
+
+/// Allowed slowdown per phase before a boot counts as a regression, unless boot-timeline.toml
+/// declares `regression_threshold_percent`.
+const DEFAULT_REGRESSION_PERCENT: f64 = 25.0;
+
+/// Absolute slack per phase so serial-log polling jitter does not trip the gate on short phases.
+const DEFAULT_REGRESSION_MIN_SECS: f64 = 2.0;
+
+/// Name of the implicit last phase, ending when the harness sees the guest as ready.
+const READY_PHASE: &str = "service-ready";
+
+/// Input-hash files written next to staged artifacts by the build pipeline.
+const INPUT_HASH_FILES: &[&str] = &[
+    ".kernel-inputs.hash",
+    ".rootfs-inputs.hash",
+    ".initramfs-inputs.hash",
+    ".install-initramfs-inputs.hash",
+];
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpecFile {
+    schema_version: u32,
+    #[serde(default)]
+    regression_threshold_percent: Option<f64>,
+    phases: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+struct TimelineMarker {
+    phase: String,
+    pattern: BootPattern,
+}
+
+/// Ordered phase-end markers from `distro-variants/_shared/scenarios/boot-timeline.toml`.
+///
+/// Each `phases` entry is `<phase>=<pattern>`; the phase spans from the previous marker (or
+/// QEMU spawn) to the first serial line matching `<pattern>`.
+#[derive(Clone, Debug)]
+pub(crate) struct TimelineSpec {
+    markers: Vec<TimelineMarker>,
+    threshold_percent: f64,
+}
+
+impl TimelineSpec {
+    /// Loads the phases that apply to `firmware`; phase patterns may carry a `uefi:`/`bios:` scope.
+    pub(crate) fn load(root: &Path, firmware: Firmware) -> Result<Self> {
+        let path = root.join(SPEC_PATH);
+        let raw = fs::read_to_string(&path)
+            .with_context(|| format!("reading boot timeline spec '{}'", path.display()))?;
+        let file: SpecFile = toml::from_str(&raw)
+            .with_context(|| format!("parsing boot timeline spec '{}'", path.display()))?;
+        if file.schema_version != SPEC_SCHEMA_VERSION {
+            bail!(
+                "boot timeline spec '{}' has schema_version {}, expected {}",
+                path.display(),
+                file.schema_version,
+                SPEC_SCHEMA_VERSION
+            );
+        }
+        let phases: Vec<String> = file
+            .phases
+            .iter()
+            .filter_map(|raw| match raw.split_once('=') {
+                Some((phase, pattern)) => {
+                    scope_to_firmware(&[pattern.trim().to_string()], firmware)
+                        .pop()
+                        .map(|pattern| format!("{phase}={pattern}"))
+                }
+                // Left for `from_declared` to reject with the usual message.
+                None => Some(raw.clone()),
+            })
+            .collect();
+        Self::from_declared(&phases, file.regression_threshold_percent)
+            .with_context(|| format!("parsing boot timeline spec '{}'", path.display()))
+    }
+
+    fn from_declared(phases: &[String], threshold_percent: Option<f64>) -> Result<Self> {
+        let mut markers = Vec::new();
+        for raw in phases {
+            let Some((phase, pattern)) = raw.split_once('=') else {
+                bail!("timeline phase '{raw}' must be written as '<phase>=<pattern>'");
+            };
+            let phase = phase.trim();
+            if phase.is_empty() || phase == READY_PHASE {
+                bail!("timeline phase '{raw}' needs a name other than '{READY_PHASE}'");
+            }
+            markers.push(TimelineMarker {
+                phase: phase.to_string(),
+                pattern: BootPattern::parse(pattern.trim(), "timeline")?,
+            });
+        }
+        let threshold_percent = std::env::var("LEVITATE_BOOT_REGRESSION_PCT")
+            .ok()
+            .and_then(|raw| raw.parse::<f64>().ok())
+            .or(threshold_percent)
+            .unwrap_or(DEFAULT_REGRESSION_PERCENT);
+        Ok(Self {
+            markers,
+            threshold_percent,
+        })
+    }
+}
+
+/// Records when each timeline marker first shows up in the serial log.
+pub(crate) struct TimelineTracker<'a> {
+    spec: TimelineSpec,
+    store: TimelineStore<'a>,
+    started: Instant,
+    seen: Vec<Option<Duration>>,
+    ready: Option<Duration>,
+}
+
+impl<'a> TimelineTracker<'a> {
+    /// Starts the clock; call right before spawning QEMU.
+    pub(crate) fn start(spec: TimelineSpec, store: TimelineStore<'a>) -> Self {
+        let seen = vec![None; spec.markers.len()];
+        Self {
+            spec,
+            store,
+            started: Instant::now(),
+            seen,
+            ready: None,
+        }
+    }
+
+    pub(crate) fn observe(&mut self, content: &str) {
+        let now = self.started.elapsed();
+        for (idx, marker) in self.spec.markers.iter().enumerate() {
+            if self.seen[idx].is_none() && marker.pattern.find(content).is_some() {
+                self.seen[idx] = Some(now);
+            }
+        }
+    }
+
+    /// Fixes the ready time now, for callers that keep the VM running before [`Self::gate`].
+    pub(crate) fn mark_ready(&mut self) {
+        self.ready.get_or_insert(self.started.elapsed());
+    }
+
+    /// Closes the timeline at the moment the guest was seen as ready.
+    fn finish(&self) -> BootTimeline {
+        let ready = self.ready.unwrap_or_else(|| self.started.elapsed());
+        let mut phases = Vec::new();
+        let mut cursor = Duration::ZERO;
+        for (marker, seen) in self.spec.markers.iter().zip(&self.seen) {
+            let duration = seen.map(|at| at.saturating_sub(cursor).as_secs_f64());
+            if let Some(at) = seen {
+                cursor = *at;
+            }
+            phases.push(PhaseTiming {
+                phase: marker.phase.clone(),
+                duration_secs: duration,
+            });
+        }
+        phases.push(PhaseTiming {
+            phase: READY_PHASE.to_string(),
+            duration_secs: Some(ready.saturating_sub(cursor).as_secs_f64()),
+        });
+        BootTimeline {
+            phases,
+            total_secs: ready.as_secs_f64(),
+        }
+    }
+
+    /// Finishes the timeline, stores it and checks it against the baseline.
+    ///
+    /// Regressions fail the boot when `enforce` is set (automated `--no-shell` runs) and are
+    /// only reported for interactive sessions.
+    pub(crate) fn gate(&self, enforce: bool) -> Result<()> {
+        let timeline = self.finish();
+        print_timeline(&timeline);
+        let regressions = self.store.record(&timeline, self.spec.threshold_percent)?;
+        if regressions.is_empty() {
+            return Ok(());
+        }
+        let summary = regressions.join("\n  ");
+        if enforce {
+            bail!(
+                "Boot-time regression for {}:\n  {summary}",
+                self.store.distro_id
+            );
+        }
+        eprintln!(
+            "[timeline] warning: boot-time regression for {}:\n  {summary}",
+            self.store.distro_id
+        );
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct PhaseTiming {
+    pub(crate) phase: String,
+    /// `None` when the phase-end marker never appeared in the serial log.
+    pub(crate) duration_secs: Option<f64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct BootTimeline {
+    pub(crate) phases: Vec<PhaseTiming>,
+    pub(crate) total_secs: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct TimelineRecord {
+    distro: String,
+    input_key: String,
+    media: String,
+    accel: String,
+    recorded_unix: u64,
+    timeline: BootTimeline,
+}
+
+/// The build whose timelines later boots are compared against.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct BaselinePointer {
+    input_key: String,
+    rebased_unix: u64,
+}
+
+/// Where and under which keys a boot timeline is stored.
+pub(crate) struct TimelineStore<'a> {
+    pub(crate) root: &'a Path,
+    pub(crate) distro_id: &'a str,
+    pub(crate) input_key: String,
+    /// `iso` or `direct`, suffixed `-bios`/`-secboot` for non-default firmware; firmware/kernel
+    /// phases are not comparable across boot media.
+    pub(crate) media: String,
+    pub(crate) accel: &'static str,
+    /// `--rebase`: make this input key the baseline and store this boot as its timeline.
+    pub(crate) rebase: bool,
+}
+
+impl TimelineStore<'_> {
+    fn dir(&self) -> PathBuf {
+        self.root
+            .join(".artifacts/scenarios")
+            .join(self.distro_id)
+            .join("timelines")
+    }
+
+    fn pointer_path(&self) -> PathBuf {
+        self.dir().join("baseline.json")
+    }
+
+    fn record_name(&self, input_key: &str) -> String {
+        format!("{}-{}-{}.json", input_key, self.media, self.accel)
+    }
+
+    /// Stores `timeline` under the artifact input key and compares it with the baseline build.
+    ///
+    /// The baseline is the input key last adopted with `--rebase`; boots are compared with the
+    /// timeline it recorded for the same media and accelerator. Without one nothing is compared.
+    /// Returns human-readable regressions (empty when within threshold).
+    fn record(&self, timeline: &BootTimeline, threshold_percent: f64) -> Result<Vec<String>> {
+        let dir = self.dir();
+        fs::create_dir_all(dir.join("runs"))
+            .with_context(|| format!("creating timeline store '{}'", dir.display()))?;
+        let now_unix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|dur| dur.as_secs())
+            .unwrap_or(0);
+        let record = TimelineRecord {
+            distro: self.distro_id.to_string(),
+            input_key: self.input_key.clone(),
+            media: self.media.clone(),
+            accel: self.accel.to_string(),
+            recorded_unix: now_unix,
+            timeline: timeline.clone(),
+        };
+        let encoded = serde_json::to_string_pretty(&record).context("encoding boot timeline")?;
+        let run_path = dir.join("runs").join(self.record_name(&self.input_key));
+        fs::write(&run_path, &encoded)
+            .with_context(|| format!("writing boot timeline '{}'", run_path.display()))?;
+
+        if self.rebase {
+            let baselines = dir.join("baselines");
+            fs::create_dir_all(&baselines)
+                .with_context(|| format!("creating '{}'", baselines.display()))?;
+            let baseline_path = baselines.join(self.record_name(&self.input_key));
+            fs::write(&baseline_path, &encoded).with_context(|| {
+                format!("writing timeline baseline '{}'", baseline_path.display())
+            })?;
+            let pointer = BaselinePointer {
+                input_key: self.input_key.clone(),
+                rebased_unix: now_unix,
+            };
+            let pointer_path = self.pointer_path();
+            fs::write(
+                &pointer_path,
+                serde_json::to_string_pretty(&pointer).context("encoding timeline baseline")?,
+            )
+            .with_context(|| format!("writing '{}'", pointer_path.display()))?;
+            eprintln!(
+                "[timeline] input key {} is now the baseline ({} on {})",
+                self.input_key, self.media, self.accel
+            );
+            return Ok(Vec::new());
+        }
+
+        let pointer_path = self.pointer_path();
+        let pointer = match fs::read_to_string(&pointer_path) {
+            Ok(raw) => serde_json::from_str::<BaselinePointer>(&raw)
+                .with_context(|| format!("parsing '{}'", pointer_path.display()))?,
+            Err(_) => {
+                eprintln!(
+                    "[timeline] no baseline for {}; rerun with `--rebase` to adopt input key {}",
+                    self.distro_id, self.input_key
+                );
+                return Ok(Vec::new());
+            }
+        };
+        let baseline_path = dir
+            .join("baselines")
+            .join(self.record_name(&pointer.input_key));
+        let Ok(raw) = fs::read_to_string(&baseline_path) else {
+            eprintln!(
+                "[timeline] baseline input key {} has no {} timeline on {}; rerun with `--rebase` to record one",
+                pointer.input_key, self.media, self.accel
+            );
+            return Ok(Vec::new());
+        };
+        let baseline = serde_json::from_str::<TimelineRecord>(&raw)
+            .with_context(|| format!("parsing timeline baseline '{}'", baseline_path.display()))?;
+        let min_secs = std::env::var("LEVITATE_BOOT_REGRESSION_MIN_SECS")
+            .ok()
+            .and_then(|raw| raw.parse::<f64>().ok())
+            .unwrap_or(DEFAULT_REGRESSION_MIN_SECS);
+        Ok(
+            phase_regressions(timeline, &baseline.timeline, threshold_percent, min_secs)
+                .into_iter()
+                .map(|regression| {
+                    format!("{regression} (baseline input key {})", baseline.input_key)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Compares phases by name; a phase regresses when it is both `threshold_percent` slower and
+/// at least `min_secs` slower than the baseline.
+fn phase_regressions(
+    current: &BootTimeline,
+    baseline: &BootTimeline,
+    threshold_percent: f64,
+    min_secs: f64,
+) -> Vec<String> {
+    let mut regressions = Vec::new();
+    for phase in &current.phases {
+        let Some(now) = phase.duration_secs else {
+            continue;
+        };
+        let Some(before) = baseline
+            .phases
+            .iter()
+            .find(|base| base.phase == phase.phase)
+            .and_then(|base| base.duration_secs)
+        else {
+            continue;
+        };
+        let limit = before * (1.0 + threshold_percent / 100.0);
+        if now > limit && now - before >= min_secs {
+            regressions.push(format!(
+                "phase '{}' took {now:.1}s vs baseline {before:.1}s (limit +{threshold_percent}%)",
+                phase.phase
+            ));
+        }
+    }
+    regressions
+}
+
+fn print_timeline(timeline: &BootTimeline) {
+    eprintln!("Boot timeline:");
+    for phase in &timeline.phases {
+        match phase.duration_secs {
+            Some(secs) => eprintln!("  {:<16} {secs:>7.2}s", phase.phase),
+            None => eprintln!("  {:<16} {:>8}", phase.phase, "(no marker)"),
+        }
+    }
+    eprintln!("  {:<16} {:>7.2}s", "total", timeline.total_secs);
+}
+
+/// Identifies the boot inputs: build-pipeline input hashes when present, otherwise the ISO
+/// checksum file, otherwise the primary artifact's path, size and mtime.
+pub(crate) fn artifact_input_key(root: &Path, distro_id: &str, primary: &Path) -> String {
+    let mut material = Vec::new();
+    let out_dir = root.join(".artifacts/out").join(distro_id);
+    let dirs: Vec<&Path> = match primary.parent() {
+        Some(parent) => vec![parent, out_dir.as_path()],
+        None => vec![out_dir.as_path()],
+    };
+    for dir in dirs {
+        for name in INPUT_HASH_FILES {
+            if let Ok(raw) = fs::read(dir.join(name)) {
+                material.extend_from_slice(name.as_bytes());
+                material.extend_from_slice(&raw);
+            }
+        }
+        if !material.is_empty() {
+            break;
+        }
+    }
+    if material.is_empty() {
+        material = fs::read(primary.with_extension("sha512")).unwrap_or_default();
+    }
+    if material.is_empty() {
+        material.extend_from_slice(primary.display().to_string().as_bytes());
+        if let Ok(meta) = fs::metadata(primary) {
+            material.extend_from_slice(&meta.len().to_le_bytes());
+            let mtime = meta
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|dur| dur.as_secs())
+                .unwrap_or(0);
+            material.extend_from_slice(&mtime.to_le_bytes());
+        }
+    }
+    format!("{:016x}", fnv1a64(&material))
+}
+
+fn fnv1a64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timeline(phases: &[(&str, Option<f64>)]) -> BootTimeline {
+        BootTimeline {
+            phases: phases
+                .iter()
+                .map(|(phase, secs)| PhaseTiming {
+                    phase: phase.to_string(),
+                    duration_secs: *secs,
+                })
+                .collect(),
+            total_secs: 0.0,
+        }
+    }
+
+    #[test]
+    fn regressions_need_both_percent_and_absolute_slack() {
+        let baseline = timeline(&[
+            ("firmware", Some(4.0)),
+            ("kernel", Some(1.0)),
+            ("initramfs", Some(10.0)),
+        ]);
+        let current = timeline(&[
+            ("firmware", Some(4.5)),
+            ("kernel", Some(2.5)),
+            ("initramfs", None),
+        ]);
+        assert!(phase_regressions(&current, &baseline, 25.0, 2.0).is_empty());
+
+        let slower = timeline(&[("firmware", Some(7.0)), ("kernel", Some(1.0))]);
+        let regressions = phase_regressions(&slower, &baseline, 25.0, 2.0);
+        assert_eq!(regressions.len(), 1);
+        assert!(regressions[0].starts_with("phase 'firmware'"));
+    }
+
+    #[test]
+    fn timeline_spec_requires_named_phases() {
+        assert!(TimelineSpec::from_declared(&["kernel=initramfs starting".into()], None).is_ok());
+        assert!(TimelineSpec::from_declared(&["initramfs starting".into()], None).is_err());
+        assert!(TimelineSpec::from_declared(&["service-ready=login:".into()], None).is_err());
+
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let spec = TimelineSpec::load(&root, Firmware::Bios).expect("load shipped spec");
+        let phases: Vec<&str> = spec.markers.iter().map(|m| m.phase.as_str()).collect();
+        assert_eq!(phases, ["firmware", "kernel", "initramfs"]);
+    }
+
+    #[test]
+    fn baselines_are_adopted_per_input_key_with_rebase() {
+        let root = std::env::temp_dir().join(format!(
+            "levitate-xtask-timeline-{}-{}",
+            std::process::id(),
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("clock")
+                .as_nanos()
+        ));
+        let store = |input_key: &str, rebase: bool| TimelineStore {
+            root: &root,
+            distro_id: "levitate",
+            input_key: input_key.to_string(),
+            media: "iso".to_string(),
+            accel: "kvm",
+            rebase,
+        };
+        let fast = timeline(&[("kernel", Some(2.0))]);
+        let slow = timeline(&[("kernel", Some(9.0))]);
+
+        let first = store("aaaa", false)
+            .record(&slow, 25.0)
+            .expect("record first");
+        let pointer_before_rebase = store("aaaa", false).pointer_path().exists();
+        store("aaaa", true).record(&fast, 25.0).expect("rebase");
+        let same_build = store("aaaa", false)
+            .record(&slow, 25.0)
+            .expect("record again");
+        let next_build = store("bbbb", false)
+            .record(&slow, 25.0)
+            .expect("record next");
+        let _ = fs::remove_dir_all(&root);
+
+        assert!(first.is_empty());
+        assert!(!pointer_before_rebase);
+        assert_eq!(same_build.len(), 1);
+        assert_eq!(next_build.len(), 1);
+        assert!(next_build[0].ends_with("(baseline input key aaaa)"));
+    }
+}
@@ -0,0 +1,292 @@
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Kernel crash report headers, checked in order against each serial line. More specific
+/// markers come first so a soft lockup is not reported as a generic `BUG:`.
+const CRASH_MARKERS: &[(&str, &str)] = &[
+    ("soft-lockup", r"watchdog: BUG: soft lockup"),
+    (
+        "hung-task",
+        r"INFO: task \S+ blocked for more than \d+ seconds",
+    ),
+    ("rcu-stall", r"rcu: INFO: rcu_\w+ detected stalls"),
+    ("bug", r"kernel BUG at |\bBUG: "),
+    // Only the kernel's own header forms, after the optional printk timestamp: userspace
+    // `traps: ... general protection fault ip:...` lines are not kernel crashes.
+    (
+        "oops",
+        r"^(?:\[\s*\d+\.\d+\]\s*)?(?:Oops(?:: | - )|general protection fault(?:, |: [0-9a-f]{4} ))",
+    ),
+    ("panic", r"Kernel panic - not syncing"),
+];
+
+/// Closes an oops/BUG report; a panic report closes with `---[ end Kernel panic ...`.
+const END_TRACE_MARKER: &str = "---[ end trace";
+const END_PANIC_MARKER: &str = "---[ end Kernel panic";
+
+/// Upper bound on captured trace lines; hung-task reports have no end marker.
+const MAX_TRACE_LINES: usize = 200;
+
+/// `LINUX_EFI_CRASH_GUID`: vendor GUID of the EFI variables written by the efi-pstore backend.
+const EFI_PSTORE_GUID: &str = "cfc8fc79-be2e-4ddc-97f0-9f98bfe298a0";
+
+#[derive(Clone, Debug)]
+pub(crate) struct CrashPatterns {
+    markers: Vec<(&'static str, Regex)>,
+}
+
+/// A kernel crash report found on the serial console.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct GuestCrash {
+    /// `panic`, `oops`, `bug`, `hung-task`, `soft-lockup` or `rcu-stall`.
+    pub(crate) kind: &'static str,
+    pub(crate) headline: String,
+    /// The report from its header line to its end marker (or [`MAX_TRACE_LINES`]).
+    pub(crate) trace: String,
+    /// Whether the report reached its `---[ end ... ]---` marker.
+    pub(crate) complete: bool,
+}
+
+impl std::fmt::Display for GuestCrash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "guest kernel {}: {}", self.kind, self.headline)
+    }
+}
+
+impl CrashPatterns {
+    pub(crate) fn new() -> Self {
+        Self {
+            markers: CRASH_MARKERS
+                .iter()
+                .map(|(kind, raw)| (*kind, Regex::new(raw).expect("valid crash marker regex")))
+                .collect(),
+        }
+    }
+
+    /// Returns the first crash report in `content`. An oops followed by the panic it causes is
+    /// captured as one report through the panic's end marker.
+    pub(crate) fn detect(&self, content: &str) -> Option<GuestCrash> {
+        let lines: Vec<&str> = content.lines().map(|line| line.trim_end()).collect();
+        let (start, kind) = lines.iter().enumerate().find_map(|(idx, line)| {
+            self.markers
+                .iter()
+                .find(|(_, re)| re.is_match(line))
+                .map(|(kind, _)| (idx, *kind))
+        })?;
+
+        let window = &lines[start..lines.len().min(start + MAX_TRACE_LINES)];
+        let mut end = None;
+        for (offset, line) in window.iter().enumerate() {
+            if line.contains(END_PANIC_MARKER) {
+                end = Some(offset);
+                break;
+            }
+            if line.contains(END_TRACE_MARKER) {
+                end = Some(offset);
+                // Keep reading: a panic report may follow the oops it was triggered by.
+                if !window[offset + 1..]
+                    .iter()
+                    .take(8)
+                    .any(|next| next.contains("Kernel panic - not syncing"))
+                {
+                    break;
+                }
+            }
+        }
+        let taken = end.map_or(window.len(), |offset| offset + 1);
+        Some(GuestCrash {
+            kind,
+            headline: strip_printk_time(lines[start]).to_string(),
+            trace: window[..taken].join("\n"),
+            complete: end.is_some(),
+        })
+    }
+}
+
+/// Drops the `[    1.234567] ` printk timestamp from a kernel log line.
+fn strip_printk_time(line: &str) -> &str {
+    let trimmed = line.trim_start();
+    match (trimmed.strip_prefix('['), trimmed.find(']')) {
+        (Some(_), Some(close)) => trimmed[close + 1..].trim_start(),
+        _ => trimmed,
+    }
+}
+
+/// QEMU `-device` argument for the ISA pvpanic device; with it a guest panic stops the VM in
+/// the `guest-panicked` QMP run state instead of hanging until the scenario timeout.
+pub(crate) fn pvpanic_args(arch: crate::cli::Arch) -> [&'static str; 2] {
+    match arch {
+        crate::cli::Arch::X86_64 => ["-device", "pvpanic"],
+        // The ISA device does not exist on the arm64 virt machine.
+        crate::cli::Arch::Aarch64 => ["-device", "pvpanic-pci"],
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct VarsJson {
+    variables: Vec<VarJson>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VarJson {
+    name: String,
+    guid: String,
+    /// Hex-encoded variable payload.
+    data: String,
+}
+
+/// Copies efi-pstore crash records out of an OVMF vars file into
+/// `.artifacts/scenarios/<distro>/crash/<stamp>`, skipping record names in `seen`.
+///
+/// The guest kernel writes its panic/oops dmesg into EFI variables, which survive in the
+/// checkpoint's vars file until the next boot; ramoops is not usable because guest RAM does not
+/// outlive the `-no-reboot` QEMU process. Returns the files written.
+pub(crate) fn collect_efi_pstore(
+    root: &Path,
+    distro_id: &str,
+    vars: &Path,
+    seen: &mut Vec<String>,
+) -> Result<Vec<PathBuf>> {
+    let json_path = vars.with_extension("pstore.json");
+    let mut cmd = Command::new("virt-fw-vars");
+    cmd.arg("--input")
+        .arg(vars)
+        .arg("--output-json")
+        .arg(&json_path)
+        .stdout(std::process::Stdio::null());
+    crate::util::tools_env::apply_to_command(&mut cmd, root)?;
+    let status = cmd
+        .status()
+        .context("running virt-fw-vars (python3-virt-firmware); is it installed?")?;
+    if !status.success() {
+        bail!(
+            "virt-fw-vars could not read '{}' (status {status})",
+            vars.display()
+        );
+    }
+    let raw = fs::read_to_string(&json_path)
+        .with_context(|| format!("reading '{}'", json_path.display()))?;
+    let _ = fs::remove_file(&json_path);
+    let parsed: VarsJson = serde_json::from_str(&raw)
+        .with_context(|| format!("parsing virt-fw-vars output for '{}'", vars.display()))?;
+
+    // `dump-type<type>-<part>-<count>-<timestamp>-<C|D>`; parts count down from the tail of
+    // the log, so the highest part comes first.
+    let mut records: BTreeMap<String, Vec<(u32, bool, Vec<u8>)>> = BTreeMap::new();
+    for var in parsed.variables {
+        if !var.guid.eq_ignore_ascii_case(EFI_PSTORE_GUID) || seen.contains(&var.name) {
+            continue;
+        }
+        let fields: Vec<&str> = var.name.split('-').collect();
+        let [_, kind, part, count, timestamp, flag] = fields.as_slice() else {
+            continue;
+        };
+        let Ok(part) = part.parse::<u32>() else {
+            continue;
+        };
+        records
+            .entry(format!("{kind}-{timestamp}-{count}"))
+            .or_default()
+            .push((part, *flag == "C", decode_hex(&var.data)?));
+        seen.push(var.name);
+    }
+    if records.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|dur| dur.as_secs())
+        .unwrap_or(0);
+    let dir = root
+        .join(".artifacts/scenarios")
+        .join(distro_id)
+        .join("crash")
+        .join(stamp.to_string());
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("creating crash capture dir '{}'", dir.display()))?;
+    let mut written = Vec::new();
+    for (record, mut parts) in records {
+        parts.sort_by_key(|(part, _, _)| std::cmp::Reverse(*part));
+        let compressed = parts.iter().any(|(_, compressed, _)| *compressed);
+        let path = dir.join(format!(
+            "{record}.{}",
+            if compressed { "deflate" } else { "txt" }
+        ));
+        let body: Vec<u8> = parts.into_iter().flat_map(|(_, _, data)| data).collect();
+        fs::write(&path, body).with_context(|| format!("writing '{}'", path.display()))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+fn decode_hex(raw: &str) -> Result<Vec<u8>> {
+    if !raw.len().is_multiple_of(2) {
+        bail!("odd-length hex payload in virt-fw-vars output");
+    }
+    (0..raw.len())
+        .step_by(2)
+        .map(|idx| {
+            u8::from_str_radix(&raw[idx..idx + 2], 16)
+                .with_context(|| format!("invalid hex byte '{}'", &raw[idx..idx + 2]))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oops_and_following_panic_are_one_report() {
+        let log = "\
+[    5.100000] systemd[1]: Started foo.service.
+[    5.200000] BUG: kernel NULL pointer dereference, address: 0000000000000000
+[    5.200100] Oops: 0000 [#1] PREEMPT SMP NOPTI
+[    5.200200] Call Trace:
+[    5.200300] ---[ end trace 0000000000000000 ]---
+[    5.200400] Kernel panic - not syncing: Fatal exception
+[    5.200500] ---[ end Kernel panic - not syncing: Fatal exception ]---
+[    5.300000] unrelated trailing line";
+        let crash = CrashPatterns::new().detect(log).unwrap();
+        assert_eq!(crash.kind, "bug");
+        assert_eq!(
+            crash.headline,
+            "BUG: kernel NULL pointer dereference, address: 0000000000000000"
+        );
+        assert!(crash.complete);
+        assert_eq!(crash.trace.lines().count(), 6);
+        assert!(crash.trace.ends_with("Fatal exception ]---"));
+
+        let hung = "[  240.0] INFO: task kworker/0:1:42 blocked for more than 120 seconds.\n\
+                    [  240.1] Call Trace:";
+        let crash = CrashPatterns::new().detect(hung).unwrap();
+        assert_eq!(crash.kind, "hung-task");
+        assert!(!crash.complete);
+        assert!(CrashPatterns::new().detect("clean boot\nlogin:").is_none());
+    }
+
+    #[test]
+    fn general_protection_fault_matches_kernel_headers_only() {
+        let patterns = CrashPatterns::new();
+        let headers = [
+            "[    7.1] general protection fault, probably for non-canonical address 0xdead: 0000 [#1] SMP",
+            "[    7.1] general protection fault: 0000 [#1] SMP NOPTI",
+            "[    7.1] Oops: general protection fault, probably for non-canonical address 0xdead: 0000 [#1]",
+        ];
+        for header in headers {
+            assert_eq!(
+                patterns.detect(header).map(|crash| crash.kind),
+                Some("oops")
+            );
+        }
+        let userspace = "[   12.3] traps: foo[321] general protection fault ip:7f00 sp:7ffc error:0 in libc.so.6";
+        assert!(patterns.detect(userspace).is_none());
+    }
+}
@@ -0,0 +1,152 @@
+use anyhow::{Context, Result, bail};
+use distro_contract::load_variant_contract_bundle_for_distro_from;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const INITRAMFS_LIVE_FILENAME: &str = "initramfs-live.cpio.gz";
+const ROOTFS_FILENAME: &str = "filesystem.erofs";
+const OVERLAY_FILENAME: &str = "overlayfs.erofs";
+const VMLINUZ_FILENAME: &str = "vmlinuz";
+
+/// Console arguments the harness needs for serial capture; UKIs set these inside reciso.
+const HARNESS_CONSOLE_CMDLINE: &[&str] = &["console=tty0", "console=ttyS0,115200n8"];
+
+/// Staged live-boot artifacts used to boot with `-kernel/-initrd/-append` instead of an ISO.
+#[derive(Clone, Debug)]
+pub(crate) struct DirectBootArtifacts {
+    pub(crate) kernel: PathBuf,
+    pub(crate) initrd: PathBuf,
+    pub(crate) rootfs: PathBuf,
+    pub(crate) overlay: Option<PathBuf>,
+    pub(crate) cmdline: String,
+}
+
+impl DirectBootArtifacts {
+    /// Resolves the newest staged live payload under `.artifacts/out/<distro>`.
+    ///
+    /// Release directories matching `product` (for example `live-tools`) win over other
+    /// products when both exist. The kernel is taken from the release directory when staged
+    /// there and otherwise from `.artifacts/kernel/<distro>/current/staging/boot`.
+    pub(crate) fn resolve(
+        root: &Path,
+        distro_id: &str,
+        product: &str,
+        cmdline_append: Option<&str>,
+    ) -> Result<Self> {
+        let out_dir = root.join(".artifacts/out").join(distro_id);
+        let mut candidates = Vec::new();
+        collect_release_dirs(&out_dir, &mut candidates)?;
+        if candidates.is_empty() {
+            bail!(
+                "no staged {INITRAMFS_LIVE_FILENAME} + {ROOTFS_FILENAME} under '{}'.\n\
+                 Build the release products once (`just release-build {distro_id}`); afterwards only the initramfs needs rebuilding.",
+                out_dir.display()
+            );
+        }
+        candidates.sort_by_key(|(dir, mtime)| {
+            let matches_product = dir
+                .components()
+                .any(|component| component.as_os_str() == product);
+            (matches_product, *mtime)
+        });
+        let (release_dir, _) = candidates.pop().expect("non-empty candidates");
+
+        let staged_kernel = release_dir.join(VMLINUZ_FILENAME);
+        let kernel = if staged_kernel.is_file() {
+            staged_kernel
+        } else {
+            root.join(".artifacts/kernel")
+                .join(distro_id)
+                .join("current/staging/boot")
+                .join(VMLINUZ_FILENAME)
+        };
+        if !kernel.is_file() {
+            bail!(
+                "missing staged kernel for direct boot: {} (build it with `cargo xtask kernels build {distro_id}`)",
+                kernel.display()
+            );
+        }
+
+        let overlay = release_dir.join(OVERLAY_FILENAME);
+        Ok(Self {
+            kernel,
+            initrd: release_dir.join(INITRAMFS_LIVE_FILENAME),
+            rootfs: release_dir.join(ROOTFS_FILENAME),
+            overlay: overlay.is_file().then_some(overlay),
+            cmdline: live_kernel_cmdline(root, distro_id, cmdline_append)?,
+        })
+    }
+}
+
+/// Builds the live kernel command line the same way ring0 merges UKI command lines:
+/// ring1 `live_uki.extra_cmdline` first, then `scenarios.live_boot.required_kernel_cmdline`,
+/// then harness console arguments and the caller's `--cmdline-append`.
+fn live_kernel_cmdline(root: &Path, distro_id: &str, append: Option<&str>) -> Result<String> {
+    let bundle = load_variant_contract_bundle_for_distro_from(root, distro_id)
+        .with_context(|| format!("loading canonical variant contract for '{}'", distro_id))?;
+    let extra = bundle
+        .contract
+        .ring1_transforms
+        .live_uki
+        .extra_cmdline
+        .as_deref()
+        .unwrap_or("");
+    Ok(merge_cmdline(
+        extra,
+        &bundle.contract.scenarios.live_boot.required_kernel_cmdline,
+        append,
+    ))
+}
+
+fn merge_cmdline(extra: &str, required: &[String], append: Option<&str>) -> String {
+    let mut args: Vec<&str> = extra.split_whitespace().collect();
+    args.extend(required.iter().map(String::as_str));
+    args.extend(HARNESS_CONSOLE_CMDLINE);
+    if let Some(append) = append {
+        args.extend(append.split_whitespace());
+    }
+    args.join(" ")
+}
+
+fn collect_release_dirs(dir: &Path, out: &mut Vec<(PathBuf, SystemTime)>) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("reading '{}'", dir.display()));
+        }
+    };
+    let initrd = dir.join(INITRAMFS_LIVE_FILENAME);
+    if initrd.is_file() && dir.join(ROOTFS_FILENAME).is_file() {
+        let mtime = fs::metadata(&initrd)
+            .and_then(|meta| meta.modified())
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+        out.push((dir.to_path_buf(), mtime));
+    }
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() && !path.is_symlink() {
+            collect_release_dirs(&path, out)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge_cmdline;
+
+    #[test]
+    fn merge_cmdline_orders_extra_required_console_then_append() {
+        let required = vec!["audit=1".to_string(), "loglevel=3".to_string()];
+        assert_eq!(
+            merge_cmdline("video=1920x1080", &required, Some("rd.break  debug")),
+            "video=1920x1080 audit=1 loglevel=3 console=tty0 console=ttyS0,115200n8 rd.break debug"
+        );
+        assert_eq!(
+            merge_cmdline("", &[], None),
+            "console=tty0 console=ttyS0,115200n8"
+        );
+    }
+}
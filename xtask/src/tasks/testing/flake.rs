@@ -0,0 +1,408 @@
+use super::boot_patterns::BootPattern;
+use super::report::{ScenarioReport, rfc3339_utc};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const POLICY_PATH: &str = "distro-variants/_shared/scenarios/flake-policy.toml";
+const POLICY_SCHEMA_VERSION: u32 = 1;
+/// Outcomes kept per distro; the oldest are dropped once the history grows past this.
+const HISTORY_LIMIT: usize = 2000;
+/// Most recent product outcomes per scenario that the flakiness score looks at.
+const SCORE_WINDOW: usize = 30;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    schema_version: u32,
+    #[serde(default)]
+    infra_patterns: Vec<String>,
+    #[serde(default)]
+    retry: RetryClasses,
+    #[serde(default)]
+    quarantine: Vec<QuarantineEntry>,
+}
+
+/// Failure classes that `--retries` may retry.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RetryClasses {
+    #[serde(default = "default_true")]
+    infra: bool,
+    #[serde(default)]
+    product: bool,
+}
+
+impl Default for RetryClasses {
+    fn default() -> Self {
+        Self {
+            infra: true,
+            product: false,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A known-flaky check whose failures are reported without failing the run.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct QuarantineEntry {
+    /// Distro id, or `*` for every distro.
+    distro: String,
+    scenario: String,
+    pub(super) reason: String,
+    /// Last day (`YYYY-MM-DD`, UTC) the entry applies; quarantines should not outlive the fix.
+    #[serde(default)]
+    until: Option<String>,
+}
+
+/// Retry and quarantine rules from `distro-variants/_shared/scenarios/flake-policy.toml`.
+pub(super) struct FlakePolicy {
+    infra: Vec<BootPattern>,
+    retry: RetryClasses,
+    quarantine: Vec<QuarantineEntry>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(super) enum Outcome {
+    Pass,
+    /// The product under test failed.
+    ProductFailure,
+    /// The host, QEMU or harness failed (port clash, image lock, missing KVM, ...).
+    InfraFailure,
+}
+
+impl Outcome {
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            Self::Pass => "pass",
+            Self::ProductFailure => "product failure",
+            Self::InfraFailure => "infrastructure failure",
+        }
+    }
+}
+
+impl FlakePolicy {
+    pub(super) fn load(root: &Path) -> Result<Self> {
+        let path = root.join(POLICY_PATH);
+        let raw = fs::read_to_string(&path)
+            .with_context(|| format!("reading flake policy '{}'", path.display()))?;
+        Self::parse(&raw).with_context(|| format!("parsing flake policy '{}'", path.display()))
+    }
+
+    fn parse(raw: &str) -> Result<Self> {
+        let file: PolicyFile = toml::from_str(raw)?;
+        if file.schema_version != POLICY_SCHEMA_VERSION {
+            bail!(
+                "unsupported schema_version {} (expected {POLICY_SCHEMA_VERSION})",
+                file.schema_version
+            );
+        }
+        let infra = file
+            .infra_patterns
+            .iter()
+            .map(|raw| BootPattern::parse(raw, "infrastructure failure"))
+            .collect::<Result<Vec<_>>>()
+            .context("parsing infra_patterns")?;
+        Ok(Self {
+            infra,
+            retry: file.retry,
+            quarantine: file.quarantine,
+        })
+    }
+
+    /// Classifies a failed run from the harness's stderr. Guest console output is left out:
+    /// a guest that logs "No space left on device" is a product failure, not a host one.
+    pub(super) fn classify_failure(&self, harness_stderr: &str) -> Outcome {
+        if self
+            .infra
+            .iter()
+            .any(|pattern| pattern.find(harness_stderr).is_some())
+        {
+            Outcome::InfraFailure
+        } else {
+            Outcome::ProductFailure
+        }
+    }
+
+    pub(super) fn retries(&self, outcome: Outcome) -> bool {
+        match outcome {
+            Outcome::Pass => false,
+            Outcome::InfraFailure => self.retry.infra,
+            Outcome::ProductFailure => self.retry.product,
+        }
+    }
+
+    /// The unexpired quarantine entry covering `scenario` on `distro_id`, if any.
+    pub(super) fn quarantined(&self, distro_id: &str, scenario: &str) -> Option<&QuarantineEntry> {
+        let today = &rfc3339_utc(SystemTime::now())[..10];
+        self.quarantine.iter().find(|entry| {
+            (entry.distro == "*" || entry.distro == distro_id)
+                && entry.scenario == scenario
+                && entry.until.as_deref().is_none_or(|until| until >= today)
+        })
+    }
+}
+
+/// One scenario attempt in `.artifacts/scenarios/<distro>/history.jsonl`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(super) struct HistoryEntry {
+    pub(super) recorded_at: String,
+    pub(super) scenario: String,
+    /// Boot inputs the attempt ran against; flips only count within one key, so a product
+    /// change that fixes or breaks a scenario is not mistaken for flakiness.
+    pub(super) artifact_key: String,
+    pub(super) mode: String,
+    pub(super) firmware: String,
+    pub(super) attempt: u32,
+    pub(super) outcome: Outcome,
+    #[serde(default)]
+    pub(super) reason: Option<String>,
+}
+
+pub(super) fn history_path(root: &Path, distro_id: &str) -> PathBuf {
+    root.join(".artifacts/scenarios")
+        .join(distro_id)
+        .join("history.jsonl")
+}
+
+/// History entries for the scenarios a harness run actually executed: each phase closed by a
+/// pass marker, plus the failing phase. Cached passes print no marker and are left out.
+pub(super) fn entries_for_run(
+    report: &ScenarioReport,
+    artifact_key: &str,
+    attempt: u32,
+    failure: Outcome,
+) -> Vec<HistoryEntry> {
+    report
+        .phases
+        .iter()
+        .filter(|phase| !phase.passed || phase.pass_marker.is_some())
+        .map(|phase| HistoryEntry {
+            recorded_at: report.finished_at.clone(),
+            scenario: phase.scenario.clone(),
+            artifact_key: artifact_key.to_string(),
+            mode: report.mode.clone(),
+            firmware: report.firmware.clone(),
+            attempt,
+            outcome: if phase.passed { Outcome::Pass } else { failure },
+            reason: (!phase.passed)
+                .then(|| report.failure_reason.clone())
+                .flatten(),
+        })
+        .collect()
+}
+
+pub(super) fn append(root: &Path, distro_id: &str, entries: &[HistoryEntry]) -> Result<()> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let path = history_path(root, distro_id);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("creating history dir '{}'", parent.display()))?;
+    }
+    let mut history = load(root, distro_id);
+    if history.len() + entries.len() > HISTORY_LIMIT {
+        history.extend_from_slice(entries);
+        let keep = history.split_off(history.len() - HISTORY_LIMIT);
+        let mut raw = String::new();
+        for entry in &keep {
+            raw.push_str(&serde_json::to_string(entry).context("encoding history entry")?);
+            raw.push('\n');
+        }
+        return fs::write(&path, raw)
+            .with_context(|| format!("rewriting scenario history '{}'", path.display()));
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("opening scenario history '{}'", path.display()))?;
+    for entry in entries {
+        let mut line = serde_json::to_string(entry).context("encoding history entry")?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("appending to scenario history '{}'", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Entries in recording order; unreadable lines are skipped.
+pub(super) fn load(root: &Path, distro_id: &str) -> Vec<HistoryEntry> {
+    fs::read_to_string(history_path(root, distro_id))
+        .map(|raw| {
+            raw.lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[derive(Debug, PartialEq)]
+pub(super) struct FlakeStats {
+    pub(super) scenario: String,
+    pub(super) passes: usize,
+    pub(super) product_failures: usize,
+    pub(super) infra_failures: usize,
+    /// Share of consecutive same-artifact product outcomes that flip between pass and fail
+    /// (0 = stable, 1 = alternates every run), over the last [`SCORE_WINDOW`] outcomes.
+    pub(super) score: f64,
+}
+
+pub(super) fn stats(history: &[HistoryEntry]) -> Vec<FlakeStats> {
+    let mut by_scenario: BTreeMap<&str, Vec<&HistoryEntry>> = BTreeMap::new();
+    for entry in history {
+        by_scenario.entry(&entry.scenario).or_default().push(entry);
+    }
+    by_scenario
+        .into_iter()
+        .map(|(scenario, entries)| {
+            let count = |outcome| entries.iter().filter(|e| e.outcome == outcome).count();
+            let product: Vec<&&HistoryEntry> = entries
+                .iter()
+                .filter(|entry| entry.outcome != Outcome::InfraFailure)
+                .collect();
+            let recent = &product[product.len().saturating_sub(SCORE_WINDOW)..];
+            let (mut pairs, mut flips) = (0, 0);
+            for pair in recent.windows(2) {
+                if pair[0].artifact_key != pair[1].artifact_key {
+                    continue;
+                }
+                pairs += 1;
+                if pair[0].outcome != pair[1].outcome {
+                    flips += 1;
+                }
+            }
+            FlakeStats {
+                scenario: scenario.to_string(),
+                passes: count(Outcome::Pass),
+                product_failures: count(Outcome::ProductFailure),
+                infra_failures: count(Outcome::InfraFailure),
+                score: if pairs == 0 {
+                    0.0
+                } else {
+                    flips as f64 / pairs as f64
+                },
+            }
+        })
+        .collect()
+}
+
+/// `scenarios flakes`: outcome counts, flakiness scores and quarantine state per scenario.
+pub fn show(distro: crate::cli::HarnessDistro) -> Result<()> {
+    let root = crate::util::repo::repo_root()?;
+    let policy = FlakePolicy::load(&root)?;
+    let history = load(&root, distro.id());
+    if history.is_empty() {
+        eprintln!(
+            "No scenario history for {} yet ({}).",
+            distro.id(),
+            history_path(&root, distro.id()).display()
+        );
+        return Ok(());
+    }
+    println!(
+        "{:<20} {:>6} {:>8} {:>6} {:>6}  quarantine",
+        "scenario", "pass", "product", "infra", "flaky"
+    );
+    for row in stats(&history) {
+        let quarantine = policy
+            .quarantined(distro.id(), &row.scenario)
+            .map(|entry| entry.reason.as_str())
+            .unwrap_or("-");
+        println!(
+            "{:<20} {:>6} {:>8} {:>6} {:>5.0}%  {quarantine}",
+            row.scenario,
+            row.passes,
+            row.product_failures,
+            row.infra_failures,
+            row.score * 100.0
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flips_count_within_one_artifact_key_only() {
+        let entry = |key: &str, outcome| HistoryEntry {
+            recorded_at: String::new(),
+            scenario: "live-boot".to_string(),
+            artifact_key: key.to_string(),
+            mode: "test".to_string(),
+            firmware: "uefi".to_string(),
+            attempt: 1,
+            outcome,
+            reason: None,
+        };
+        let history = vec![
+            entry("a", Outcome::Pass),
+            entry("a", Outcome::ProductFailure),
+            entry("a", Outcome::InfraFailure),
+            entry("a", Outcome::Pass),
+            // A new build that fails consistently is a regression, not a flake.
+            entry("b", Outcome::ProductFailure),
+            entry("b", Outcome::ProductFailure),
+        ];
+        let stats = stats(&history);
+        assert_eq!(stats.len(), 1);
+        assert_eq!(
+            (
+                stats[0].passes,
+                stats[0].product_failures,
+                stats[0].infra_failures
+            ),
+            (2, 3, 1)
+        );
+        // Same-key pairs: a pass->fail, a fail->pass, b fail->fail.
+        assert!((stats[0].score - 2.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn policy_classifies_and_quarantines_failures() {
+        let policy = FlakePolicy::parse(
+            r#"
+schema_version = 1
+infra_patterns = ["could not set up host forwarding rule"]
+
+[[quarantine]]
+distro = "acorn"
+scenario = "live-tools"
+reason = "tracked upstream"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            policy.classify_failure("qemu: could not set up host forwarding rule 'tcp::2222-:22'"),
+            Outcome::InfraFailure
+        );
+        assert_eq!(
+            policy.classify_failure("LIVE BOOT FAILED: sshd inactive"),
+            Outcome::ProductFailure
+        );
+        assert!(policy.retries(Outcome::InfraFailure));
+        assert!(!policy.retries(Outcome::ProductFailure));
+        assert!(policy.quarantined("acorn", "live-tools").is_some());
+        assert!(policy.quarantined("acorn", "install").is_none());
+        assert!(policy.quarantined("levitate", "live-tools").is_none());
+        assert!(FlakePolicy::parse("schema_version = 2").is_err());
+    }
+
+    #[test]
+    fn shipped_policy_loads() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        FlakePolicy::load(&root).unwrap();
+    }
+}
@@ -0,0 +1,378 @@
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const PROFILES_PATH: &str = "distro-variants/_shared/scenarios/hw-profiles.toml";
+const PROFILES_SCHEMA_VERSION: u32 = 1;
+const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProfilesFile {
+    schema_version: u32,
+    #[serde(default)]
+    scenarios: BTreeMap<String, String>,
+    profiles: BTreeMap<String, HardwareProfile>,
+}
+
+/// Named VM hardware for scenario boots, loaded from `distro-variants/_shared/scenarios/hw-profiles.toml`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct HardwareProfile {
+    #[serde(skip)]
+    pub(super) name: String,
+    cpus: u32,
+    memory_mib: u64,
+    machine: Machine,
+    nic: NicModel,
+    #[serde(default)]
+    pub(super) disks: Vec<DiskSpec>,
+    /// Attach `disks` as blank install targets on live boots; live boots get no disks otherwise.
+    #[serde(default)]
+    live_disks: bool,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum Machine {
+    Q35,
+    Pc,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+enum NicModel {
+    #[serde(rename = "virtio-net-pci")]
+    VirtioNet,
+    #[serde(rename = "e1000")]
+    E1000,
+    #[serde(rename = "e1000e")]
+    E1000e,
+    #[serde(rename = "rtl8139")]
+    Rtl8139,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum DiskBus {
+    Virtio,
+    Nvme,
+    Sata,
+    Scsi,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct DiskSpec {
+    bus: DiskBus,
+    /// `qemu-img` size such as `20G`; ignored for the installed disk of checkpoint boots.
+    size: String,
+    #[serde(default = "default_sector_size")]
+    sector_size: u32,
+}
+
+fn default_sector_size() -> u32 {
+    512
+}
+
+impl ProfilesFile {
+    fn load(root: &Path) -> Result<Self> {
+        let path = root.join(PROFILES_PATH);
+        let raw = fs::read_to_string(&path)
+            .with_context(|| format!("reading hardware profiles '{}'", path.display()))?;
+        let file: ProfilesFile = toml::from_str(&raw)
+            .with_context(|| format!("parsing hardware profiles '{}'", path.display()))?;
+        if file.schema_version != PROFILES_SCHEMA_VERSION {
+            bail!(
+                "unsupported hardware profiles schema_version {} in '{}' (expected {PROFILES_SCHEMA_VERSION})",
+                file.schema_version,
+                path.display()
+            );
+        }
+        Ok(file)
+    }
+}
+
+/// Refuses install-tests runs covering `scenario_keys` when `[scenarios]` maps one of them to a
+/// profile: install-tests builds its VMs with its own fixed hardware and would ignore it.
+pub(super) fn ensure_unmapped_for_install_tests(root: &Path, scenario_keys: &[&str]) -> Result<()> {
+    let file = ProfilesFile::load(root)?;
+    for key in scenario_keys {
+        if let Some(profile) = file.scenarios.get(*key) {
+            bail!(
+                "{PROFILES_PATH} maps '{key}' to hardware profile '{profile}', but install-tests \
+                 (`test`, `test-up-to`, `matrix`) runs its own VM hardware; profiles apply to \
+                 `scenarios boot` only. Drop the mapping and pass `--hw-profile {profile}` to \
+                 `scenarios boot` instead."
+            );
+        }
+    }
+    Ok(())
+}
+
+impl HardwareProfile {
+    /// Resolves `requested` (`--hw-profile`), else the `[scenarios]` entry for `scenario_key`,
+    /// else `default`.
+    pub(super) fn resolve(
+        root: &Path,
+        scenario_key: &str,
+        requested: Option<&str>,
+    ) -> Result<Self> {
+        let file = ProfilesFile::load(root)?;
+        let name = requested
+            .or_else(|| file.scenarios.get(scenario_key).map(String::as_str))
+            .unwrap_or(DEFAULT_PROFILE);
+        let Some(profile) = file.profiles.get(name) else {
+            bail!(
+                "unknown hardware profile '{name}'; available: {}",
+                file.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+            );
+        };
+        let mut profile = profile.clone();
+        profile.name = name.to_string();
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.cpus == 0 {
+            bail!("hardware profile '{}': cpus must be at least 1", self.name);
+        }
+        if self.memory_mib < 512 {
+            bail!(
+                "hardware profile '{}': memory_mib {} is below the 512 MiB firmware minimum",
+                self.name,
+                self.memory_mib
+            );
+        }
+        for disk in &self.disks {
+            if disk.sector_size != 512 && disk.sector_size != 4096 {
+                bail!(
+                    "hardware profile '{}': sector_size must be 512 or 4096, got {}",
+                    self.name,
+                    disk.sector_size
+                );
+            }
+            // QEMU's ide-hd only emulates 512-byte logical sectors.
+            if disk.bus == DiskBus::Sata && disk.sector_size != 512 {
+                bail!(
+                    "hardware profile '{}': sata disks cannot use 4Kn sectors; use nvme, virtio or scsi",
+                    self.name
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub(super) fn summary(&self) -> String {
+        let disks: Vec<String> = self
+            .disks
+            .iter()
+            .map(|disk| {
+                let sectors = if disk.sector_size == 512 { "" } else { " 4Kn" };
+                format!("{} {}{sectors}", disk.bus.as_str(), disk.size)
+            })
+            .collect();
+        format!(
+            "{} ({}, {} vCPU, {} MiB, nic {}, disks: {})",
+            self.name,
+            self.machine.as_str(),
+            self.cpus,
+            self.memory_mib,
+            self.nic.device(),
+            if disks.is_empty() {
+                "none".to_string()
+            } else if self.live_disks {
+                disks.join(", ")
+            } else {
+                format!("{}; disk checkpoints only", disks.join(", "))
+            }
+        )
+    }
+
+    /// Disks to create blank for a live boot.
+    pub(super) fn live_disks(&self) -> &[DiskSpec] {
+        if self.live_disks { &self.disks } else { &[] }
+    }
+
+    pub(super) fn memory_mib(&self) -> u64 {
+        self.memory_mib
+    }
+
+    /// `-machine`, `-smp` and `-m` arguments. `smm` forces `q35,smm=on`, which Secure Boot OVMF
+    /// requires regardless of the profile's machine type; aarch64 always uses `virt`.
+    pub(super) fn machine_args(&self, smm: bool, arch: crate::cli::Arch) -> Vec<String> {
+        let machine = if arch == crate::cli::Arch::Aarch64 {
+            "virt,gic-version=max"
+        } else if smm {
+            "q35,smm=on"
+        } else {
+            self.machine.as_str()
+        };
+        vec![
+            "-machine".to_string(),
+            machine.to_string(),
+            "-smp".to_string(),
+            self.cpus.to_string(),
+            "-m".to_string(),
+            format!("{}M", self.memory_mib),
+        ]
+    }
+
+    pub(super) fn nic_args(&self, netdev: &str) -> Vec<String> {
+        vec![
+            "-device".to_string(),
+            format!("{},netdev={netdev}", self.nic.device()),
+        ]
+    }
+
+    /// Attaches `images` (`(path, format)`) in order on the bus and sector size of the matching
+    /// profile disk.
+    pub(super) fn disk_args(&self, images: &[(PathBuf, &str)]) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut ahci_ports = 0;
+        let mut scsi = false;
+        for (idx, ((path, format), disk)) in images.iter().zip(&self.disks).enumerate() {
+            let drive = format!("hwdisk{idx}");
+            let blocks = format!(
+                "logical_block_size={0},physical_block_size={0}",
+                disk.sector_size
+            );
+            let device = match disk.bus {
+                DiskBus::Virtio => {
+                    format!("virtio-blk-pci,drive={drive},serial=levitate-disk{idx},{blocks}")
+                }
+                DiskBus::Nvme => format!("nvme,drive={drive},serial=levitate-disk{idx},{blocks}"),
+                DiskBus::Sata => {
+                    if ahci_ports == 0 {
+                        args.extend(["-device".to_string(), "ahci,id=hwahci".to_string()]);
+                    }
+                    ahci_ports += 1;
+                    format!(
+                        "ide-hd,drive={drive},bus=hwahci.{},serial=levitate-disk{idx}",
+                        ahci_ports - 1
+                    )
+                }
+                DiskBus::Scsi => {
+                    if !scsi {
+                        args.extend([
+                            "-device".to_string(),
+                            "virtio-scsi-pci,id=hwscsi".to_string(),
+                        ]);
+                        scsi = true;
+                    }
+                    format!("scsi-hd,drive={drive},bus=hwscsi.0,serial=levitate-disk{idx},{blocks}")
+                }
+            };
+            args.extend([
+                "-drive".to_string(),
+                format!("id={drive},if=none,format={format},file={}", path.display()),
+                "-device".to_string(),
+                device,
+            ]);
+        }
+        args
+    }
+}
+
+impl Machine {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Q35 => "q35",
+            Self::Pc => "pc",
+        }
+    }
+}
+
+impl DiskBus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Virtio => "virtio",
+            Self::Nvme => "nvme",
+            Self::Sata => "sata",
+            Self::Scsi => "scsi",
+        }
+    }
+}
+
+impl NicModel {
+    fn device(self) -> &'static str {
+        match self {
+            Self::VirtioNet => "virtio-net-pci",
+            Self::E1000 => "e1000",
+            Self::E1000e => "e1000e",
+            Self::Rtl8139 => "rtl8139",
+        }
+    }
+}
+
+/// Blank qcow2 disks created for a profile and removed when dropped.
+pub(super) struct ScratchDisks {
+    dir: PathBuf,
+    pub(super) images: Vec<PathBuf>,
+}
+
+impl ScratchDisks {
+    pub(super) fn create(root: &Path, disks: &[DiskSpec], dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("creating scratch disk dir '{}'", dir.display()))?;
+        let mut scratch = Self {
+            dir,
+            images: Vec::new(),
+        };
+        for (idx, disk) in disks.iter().enumerate() {
+            let image = scratch.dir.join(format!("disk{idx}.qcow2"));
+            let mut cmd = Command::new("qemu-img");
+            cmd.args(["create", "-q", "-f", "qcow2"])
+                .arg(&image)
+                .arg(&disk.size);
+            crate::util::tools_env::apply_to_command(&mut cmd, root)?;
+            let status = cmd
+                .status()
+                .context("running qemu-img create for scratch disk")?;
+            if !status.success() {
+                bail!(
+                    "qemu-img create '{}' ({}) failed with status {status}",
+                    image.display(),
+                    disk.size
+                );
+            }
+            scratch.images.push(image);
+        }
+        Ok(scratch)
+    }
+}
+
+impl Drop for ScratchDisks {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_profiles_parse_and_validate() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let raw = fs::read_to_string(root.join(PROFILES_PATH)).unwrap();
+        let file: ProfilesFile = toml::from_str(&raw).unwrap();
+        assert!(file.profiles.contains_key(DEFAULT_PROFILE));
+        assert!(file.profiles[DEFAULT_PROFILE].live_disks().is_empty());
+        for (name, profile) in &file.profiles {
+            let mut profile = profile.clone();
+            profile.name = name.clone();
+            profile.validate().unwrap();
+        }
+
+        let profile = HardwareProfile::resolve(&root, "install", Some("4kn")).unwrap();
+        let args = profile.disk_args(&[(PathBuf::from("/tmp/d.qcow2"), "qcow2")]);
+        assert_eq!(
+            args[3],
+            "nvme,drive=hwdisk0,serial=levitate-disk0,logical_block_size=4096,physical_block_size=4096"
+        );
+    }
+}
@@ -0,0 +1,435 @@
+use super::vm::shell_quote;
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// fw_cfg blobs are read into guest memory during early boot; keep the env payload small.
+const MAX_FW_CFG_PAYLOAD_BYTES: u64 = 64 * 1024;
+/// Upper bound for the files one manifest copies into the guest.
+const MAX_PROVISION_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Declarative boot injection manifest (`--inject-manifest`).
+///
+/// `[env]` travels in the fw_cfg boot payload like `--inject`. Nothing in the guest reads
+/// hostnames, keys or files from the payload, so those are copied in over SSH as root once the
+/// guest answers, which needs a `--ssh` live boot.
+///
+/// ```toml
+/// hostname = "scenario-vm"
+/// ssh_authorized_keys = ["ssh-ed25519 AAAA... dev@host"]
+/// ssh_authorized_key_files = ["~/.ssh/id_ed25519.pub"]
+///
+/// [env]
+/// LEVITATE_DEBUG = "1"
+///
+/// [[files]]
+/// source = "payload/motd"   # relative to the manifest
+/// target = "/etc/motd"
+/// mode = "0644"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(super) struct InjectionManifest {
+    hostname: Option<String>,
+    #[serde(default)]
+    ssh_authorized_keys: Vec<String>,
+    #[serde(default)]
+    ssh_authorized_key_files: Vec<PathBuf>,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    #[serde(default)]
+    files: Vec<FileEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileEntry {
+    /// File or directory on the host; directories are copied recursively under `target`.
+    source: PathBuf,
+    /// Absolute path in the guest.
+    target: String,
+    /// Octal mode such as `"0600"`; defaults to the source file's permission bits.
+    mode: Option<String>,
+}
+
+/// The manifest entries applied over SSH once the guest is reachable.
+#[derive(Debug, Default)]
+pub(super) struct Provisioning {
+    hostname: Option<String>,
+    authorized_keys: Vec<String>,
+    /// `(host file, guest path, mode)` triples.
+    files: Vec<(PathBuf, String, u32)>,
+}
+
+impl InjectionManifest {
+    pub(super) fn load(path: &Path) -> Result<Self> {
+        let raw = fs::read_to_string(path)
+            .with_context(|| format!("reading injection manifest '{}'", path.display()))?;
+        let mut manifest: Self = toml::from_str(&raw)
+            .with_context(|| format!("parsing injection manifest '{}'", path.display()))?;
+        if manifest.env.is_empty() && !manifest.provisions() {
+            bail!(
+                "injection manifest '{}' is empty; set env, hostname, ssh keys or files",
+                path.display()
+            );
+        }
+        let base = path.parent().unwrap_or(Path::new("."));
+        for file in &mut manifest.files {
+            file.source = resolve_source(base, &file.source);
+        }
+        for key_file in &mut manifest.ssh_authorized_key_files {
+            *key_file = resolve_source(base, key_file);
+        }
+        Ok(manifest)
+    }
+
+    /// Whether the manifest sets anything beyond `[env]`.
+    pub(super) fn provisions(&self) -> bool {
+        self.hostname.is_some()
+            || !self.ssh_authorized_keys.is_empty()
+            || !self.ssh_authorized_key_files.is_empty()
+            || !self.files.is_empty()
+    }
+
+    /// Writes the `[env]` payload, in the `--inject` KEY=VALUE format, into `staging`; `None`
+    /// when the manifest sets no env.
+    pub(super) fn pack_env(&self, staging: &Path) -> Result<Option<PathBuf>> {
+        if self.env.is_empty() {
+            return Ok(None);
+        }
+        let mut lines = Vec::new();
+        for (key, value) in &self.env {
+            validate_env_key(key)?;
+            if value.contains('\n') {
+                bail!("injection manifest env '{key}' contains a newline");
+            }
+            lines.push(format!("{key}={value}"));
+        }
+        let payload_text = format!("{}\n", lines.join("\n"));
+        if payload_text.len() as u64 > MAX_FW_CFG_PAYLOAD_BYTES {
+            bail!(
+                "boot injection payload is {} bytes; fw_cfg payloads are limited to {MAX_FW_CFG_PAYLOAD_BYTES} bytes",
+                payload_text.len()
+            );
+        }
+        let payload = staging.join("boot-injection.env");
+        fs::write(&payload, payload_text)
+            .with_context(|| format!("writing boot injection payload '{}'", payload.display()))?;
+        Ok(Some(payload))
+    }
+
+    /// Validates and expands the hostname, keys and files for [`Provisioning::apply`].
+    pub(super) fn provisioning(&self) -> Result<Provisioning> {
+        if let Some(hostname) = &self.hostname {
+            validate_hostname(hostname)?;
+        }
+        let mut files = Vec::new();
+        for file in &self.files {
+            expand_entry(file, &mut files)?;
+        }
+        let mut bytes = 0;
+        for (source, _, _) in &files {
+            bytes += fs::metadata(source)
+                .with_context(|| format!("reading injection source '{}'", source.display()))?
+                .len();
+        }
+        if bytes > MAX_PROVISION_BYTES {
+            bail!(
+                "injection manifest files add up to {} MiB; the limit is {} MiB",
+                bytes / (1024 * 1024),
+                MAX_PROVISION_BYTES / (1024 * 1024)
+            );
+        }
+        Ok(Provisioning {
+            hostname: self.hostname.clone(),
+            authorized_keys: self.authorized_keys()?,
+            files,
+        })
+    }
+
+    fn authorized_keys(&self) -> Result<Vec<String>> {
+        let mut keys = Vec::new();
+        for key in &self.ssh_authorized_keys {
+            keys.push(key.trim().to_string());
+        }
+        for path in &self.ssh_authorized_key_files {
+            let raw = fs::read_to_string(path)
+                .with_context(|| format!("reading SSH public key file '{}'", path.display()))?;
+            keys.extend(
+                raw.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_string),
+            );
+        }
+        for key in &keys {
+            let kind = key.split_whitespace().next().unwrap_or("");
+            let known = kind.starts_with("ssh-")
+                || kind.starts_with("ecdsa-")
+                || kind.starts_with("sk-ssh-")
+                || kind.starts_with("sk-ecdsa-");
+            if !known || key.split_whitespace().count() < 2 {
+                bail!("'{key}' is not an OpenSSH public key");
+            }
+        }
+        Ok(keys)
+    }
+}
+
+impl Provisioning {
+    pub(super) fn is_empty(&self) -> bool {
+        self.hostname.is_none() && self.authorized_keys.is_empty() && self.files.is_empty()
+    }
+
+    /// Applies the hostname, keys and files as root; `ssh_args` are the connection options
+    /// for the guest's forwarded SSH port.
+    pub(super) fn apply(&self, ssh_args: &[String]) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        if let Some(hostname) = &self.hostname {
+            run_guest(
+                ssh_args,
+                &format!("hostname {hostname} && echo {hostname} > /etc/hostname"),
+                Stdio::null(),
+                None,
+            )?;
+        }
+        if !self.authorized_keys.is_empty() {
+            let keys = format!("{}\n", self.authorized_keys.join("\n"));
+            run_guest(
+                ssh_args,
+                "umask 077 && mkdir -p /root/.ssh && cat >> /root/.ssh/authorized_keys",
+                Stdio::piped(),
+                Some(&keys),
+            )?;
+        }
+        for (source, target, mode) in &self.files {
+            let file = fs::File::open(source)
+                .with_context(|| format!("opening injection source '{}'", source.display()))?;
+            let dir = match target.rsplit_once('/') {
+                Some(("", _)) | None => "/",
+                Some((dir, _)) => dir,
+            };
+            let target = shell_quote(target);
+            run_guest(
+                ssh_args,
+                &format!(
+                    "mkdir -p {} && cat > {target} && chmod {mode:04o} {target}",
+                    shell_quote(dir)
+                ),
+                Stdio::from(file),
+                None,
+            )?;
+        }
+        eprintln!(
+            "Injection manifest applied over SSH ({} file(s), {} key(s){}).",
+            self.files.len(),
+            self.authorized_keys.len(),
+            self.hostname
+                .as_deref()
+                .map(|name| format!(", hostname {name}"))
+                .unwrap_or_default()
+        );
+        Ok(())
+    }
+}
+
+fn run_guest(ssh_args: &[String], script: &str, stdin: Stdio, input: Option<&str>) -> Result<()> {
+    let mut child = Command::new("ssh")
+        .args(ssh_args)
+        .args(["-o", "BatchMode=yes", "root@127.0.0.1", script])
+        .stdin(stdin)
+        .spawn()
+        .context("running ssh for the injection manifest")?;
+    if let Some(input) = input {
+        let mut stdin = child.stdin.take().expect("piped ssh stdin");
+        stdin
+            .write_all(input.as_bytes())
+            .context("writing injection manifest input to ssh")?;
+    }
+    let status = child.wait().context("waiting for ssh")?;
+    if !status.success() {
+        bail!("guest command `{script}` failed over SSH with status {status}");
+    }
+    Ok(())
+}
+
+fn resolve_source(base: &Path, source: &Path) -> PathBuf {
+    if let (Ok(rest), Some(home)) = (source.strip_prefix("~"), std::env::var_os("HOME")) {
+        return PathBuf::from(home).join(rest);
+    }
+    if source.is_absolute() {
+        source.to_path_buf()
+    } else {
+        base.join(source)
+    }
+}
+
+/// Flattens one `[[files]]` entry into `(host file, guest path, mode)` triples.
+fn expand_entry(entry: &FileEntry, out: &mut Vec<(PathBuf, String, u32)>) -> Result<()> {
+    if !entry.target.starts_with('/') {
+        bail!(
+            "injection target '{}' must be an absolute guest path",
+            entry.target
+        );
+    }
+    let mode = entry.mode.as_deref().map(parse_mode).transpose()?;
+    let meta = fs::metadata(&entry.source)
+        .with_context(|| format!("reading injection source '{}'", entry.source.display()))?;
+    if meta.is_file() {
+        let mode = mode.unwrap_or(meta.permissions().mode() & 0o7777);
+        out.push((entry.source.clone(), entry.target.clone(), mode));
+        return Ok(());
+    }
+    if !meta.is_dir() {
+        bail!(
+            "injection source '{}' is neither a file nor a directory",
+            entry.source.display()
+        );
+    }
+
+    let mut pending = vec![entry.source.clone()];
+    while let Some(dir) = pending.pop() {
+        let mut children: Vec<_> = fs::read_dir(&dir)
+            .with_context(|| format!("reading injection directory '{}'", dir.display()))?
+            .collect::<std::io::Result<_>>()?;
+        children.sort_by_key(|child| child.path());
+        for child in children {
+            let path = child.path();
+            let file_type = child.file_type()?;
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_file() {
+                let rel = path
+                    .strip_prefix(&entry.source)
+                    .expect("child of injection source");
+                let target = format!(
+                    "{}/{}",
+                    entry.target.trim_end_matches('/'),
+                    rel.to_string_lossy()
+                );
+                let file_mode = mode.unwrap_or(child.metadata()?.permissions().mode() & 0o7777);
+                out.push((path, target, file_mode));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_mode(raw: &str) -> Result<u32> {
+    let digits = raw.trim_start_matches("0o");
+    match u32::from_str_radix(digits, 8) {
+        Ok(mode) if mode <= 0o7777 => Ok(mode),
+        _ => bail!("invalid file mode '{raw}'; expected octal such as \"0644\""),
+    }
+}
+
+fn validate_env_key(key: &str) -> Result<()> {
+    let valid = key
+        .chars()
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+        && key
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_');
+    if !valid {
+        bail!("invalid injection env key '{key}'; use letters, digits and '_'");
+    }
+    Ok(())
+}
+
+fn validate_hostname(hostname: &str) -> Result<()> {
+    let valid = !hostname.is_empty()
+        && hostname.len() <= 253
+        && hostname.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || ch == '-')
+        });
+    if !valid {
+        bail!("invalid injection hostname '{hostname}'");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InjectionManifest;
+    use crate::tasks::testing::test_temp_dir;
+    use std::fs;
+
+    #[test]
+    fn manifest_splits_env_payload_from_ssh_provisioning() {
+        let dir = test_temp_dir("injection-manifest");
+        fs::create_dir_all(dir.join("tree/sub")).expect("create source tree");
+        fs::write(dir.join("tree/sub/b.conf"), "b").expect("write tree file");
+        fs::write(dir.join("motd"), "hi").expect("write motd");
+        let manifest_path = dir.join("inject.toml");
+        fs::write(
+            &manifest_path,
+            r#"
+hostname = "scenario-vm"
+ssh_authorized_keys = ["ssh-ed25519 AAAAC3Nza dev@host"]
+
+[env]
+LEVITATE_DEBUG = "1"
+
+[[files]]
+source = "motd"
+target = "/etc/motd"
+mode = "0644"
+
+[[files]]
+source = "tree"
+target = "/etc/extra/"
+mode = "0600"
+"#,
+        )
+        .expect("write manifest");
+
+        let manifest = InjectionManifest::load(&manifest_path).expect("load manifest");
+        let payload = manifest
+            .pack_env(&dir)
+            .expect("pack env")
+            .expect("env payload");
+        let payload = fs::read_to_string(payload).expect("read payload");
+        let provisioning = manifest.provisioning().expect("expand provisioning");
+
+        fs::write(&manifest_path, "hostname = \"bad_name\"\n").expect("rewrite manifest");
+        let bad_hostname = InjectionManifest::load(&manifest_path)
+            .expect("load manifest")
+            .provisioning();
+        fs::write(&manifest_path, "").expect("empty manifest");
+        let empty = InjectionManifest::load(&manifest_path);
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(payload, "LEVITATE_DEBUG=1\n");
+        assert!(manifest.provisions());
+        assert_eq!(provisioning.hostname.as_deref(), Some("scenario-vm"));
+        assert_eq!(
+            provisioning.authorized_keys,
+            ["ssh-ed25519 AAAAC3Nza dev@host"]
+        );
+        let files: Vec<_> = provisioning
+            .files
+            .iter()
+            .map(|(_, target, mode)| (target.as_str(), *mode))
+            .collect();
+        assert_eq!(
+            files,
+            [("/etc/motd", 0o644), ("/etc/extra/sub/b.conf", 0o600)]
+        );
+        assert!(bad_hostname.is_err());
+        assert!(empty.is_err());
+    }
+}
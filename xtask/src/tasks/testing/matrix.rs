@@ -0,0 +1,405 @@
+use super::hw_profile;
+use super::report::{self, ScenarioReport};
+use super::scenarios::{allocate_local_port, install_tests_command};
+use super::snapshots;
+use anyhow::{Context, Result, bail};
+use install_tests::scenarios::parse_scenario_name;
+use std::collections::VecDeque;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::SystemTime;
+
+/// Automated checkpoint ladder in execution order (`00Build` through `06Runtime`).
+const CHECKPOINT_LADDER: &[(&str, &str)] = &[
+    ("build-preflight", "00Build"),
+    ("live-boot", "01Boot"),
+    ("live-tools", "02LiveTools"),
+    ("install", "03Install"),
+    ("installed-boot", "04LoginGate"),
+    ("automated-login", "05Harness"),
+    ("runtime", "06Runtime"),
+];
+
+/// Harness stops for `test-up-to target`: every disk checkpoint on the ladder before `target`,
+/// then `target` itself.
+pub(super) fn disk_checkpoint_stops(target: &str) -> Vec<&str> {
+    let Some(end) = CHECKPOINT_LADDER.iter().position(|(key, _)| *key == target) else {
+        return vec![target];
+    };
+    let mut stops: Vec<&str> = CHECKPOINT_LADDER[..end]
+        .iter()
+        .map(|(key, _)| *key)
+        .filter(|key| snapshots::checkpoint_label(key).is_some())
+        .collect();
+    stops.push(target);
+    stops
+}
+
+/// Ladder scenario keys up to and including `target`; just `target` when it is off the ladder.
+pub(super) fn ladder_through(target: &str) -> Vec<&str> {
+    match CHECKPOINT_LADDER.iter().position(|(key, _)| *key == target) {
+        Some(end) => CHECKPOINT_LADDER[..=end]
+            .iter()
+            .map(|(key, _)| *key)
+            .collect(),
+        None => vec![target],
+    }
+}
+
+/// Ladder checkpoints after `failed` up to and including `target`; 0 when either is off the
+/// ladder or `failed` is the target itself.
+pub(super) fn checkpoints_after(failed: &str, target: &str) -> usize {
+    let position = |key: &str| CHECKPOINT_LADDER.iter().position(|(k, _)| *k == key);
+    match (position(failed), position(target)) {
+        (Some(failed), Some(target)) => target.saturating_sub(failed),
+        _ => 0,
+    }
+}
+
+/// Per-job overrides of install-tests' fixed SSH (2222) and VNC host ports.
+const SSH_PORT_ENV: &str = "LEVITATE_SCENARIO_SSH_PORT";
+const VNC_PORT_ENV: &str = "LEVITATE_SCENARIO_VNC_PORT";
+
+pub struct MatrixOptions {
+    pub distros: Vec<crate::cli::HarnessDistro>,
+    pub up_to: String,
+    pub jobs: usize,
+    pub mem_budget_mib: Option<u64>,
+    pub job_mem_mib: u64,
+    pub inject: Option<String>,
+    pub inject_file: Option<PathBuf>,
+}
+
+struct MatrixJob {
+    distro_id: &'static str,
+    ssh_port: u16,
+    vnc_port: u16,
+    tmp_dir: PathBuf,
+}
+
+struct JobOutcome {
+    distro_id: &'static str,
+    passed: bool,
+    /// Scenario keys whose pass marker was observed (cached passes print none).
+    passed_keys: Vec<String>,
+    report_path: Option<PathBuf>,
+    error: Option<String>,
+}
+
+pub fn run(opts: MatrixOptions) -> Result<()> {
+    let root = crate::util::repo::repo_root()?;
+    let target = parse_scenario_name(&opts.up_to)
+        .with_context(|| format!("parsing scenario '{}'", opts.up_to))?;
+    let Some(ladder_len) = CHECKPOINT_LADDER
+        .iter()
+        .position(|(key, _)| *key == target.key())
+        .map(|idx| idx + 1)
+    else {
+        bail!(
+            "scenario '{}' is not on the automated checkpoint ladder",
+            target.key()
+        );
+    };
+    let ladder = &CHECKPOINT_LADDER[..ladder_len];
+    let ladder_keys: Vec<&str> = ladder.iter().map(|(key, _)| *key).collect();
+    hw_profile::ensure_unmapped_for_install_tests(&root, &ladder_keys)?;
+
+    let mut distros: Vec<&'static str> = Vec::new();
+    for distro in &opts.distros {
+        if !distros.contains(&distro.id()) {
+            distros.push(distro.id());
+        }
+    }
+    if distros.is_empty() {
+        bail!("no distros selected");
+    }
+
+    let jobs = effective_jobs(&opts, distros.len())?;
+    let run_dir = root
+        .join(".artifacts/scenarios/matrix")
+        .join(report::rfc3339_utc(SystemTime::now()).replace(['-', ':'], ""));
+
+    let mut taken_ports = Vec::new();
+    let mut queue = VecDeque::new();
+    for distro_id in &distros {
+        let ssh_port = allocate_local_port(Ipv4Addr::LOCALHOST, 2222, 2999, &taken_ports)?;
+        taken_ports.push(ssh_port);
+        let vnc_port = allocate_local_port(Ipv4Addr::LOCALHOST, 5900, 5999, &taken_ports)?;
+        taken_ports.push(vnc_port);
+        let tmp_dir = run_dir.join(distro_id).join("tmp");
+        fs::create_dir_all(&tmp_dir)
+            .with_context(|| format!("creating job temp dir '{}'", tmp_dir.display()))?;
+        queue.push_back(MatrixJob {
+            distro_id,
+            ssh_port,
+            vnc_port,
+            tmp_dir,
+        });
+    }
+
+    eprintln!(
+        "[matrix] {} distro(s) up to {} with {jobs} concurrent job(s); logs under {}",
+        distros.len(),
+        target.key(),
+        run_dir.display()
+    );
+
+    let queue = Arc::new(Mutex::new(queue));
+    let outcomes = Arc::new(Mutex::new(Vec::new()));
+    let mut workers = Vec::new();
+    for _ in 0..jobs {
+        let queue = Arc::clone(&queue);
+        let outcomes = Arc::clone(&outcomes);
+        let root = root.clone();
+        let opts_inject = opts.inject.clone();
+        let opts_inject_file = opts.inject_file.clone();
+        let target_key = target.key().to_string();
+        workers.push(thread::spawn(move || {
+            // The guard moves into `and_then`, so the queue is unlocked while the job runs.
+            while let Some(job) = queue.lock().ok().and_then(|mut queue| queue.pop_front()) {
+                let outcome = run_job(
+                    &root,
+                    &job,
+                    &target_key,
+                    opts_inject.clone(),
+                    opts_inject_file.clone(),
+                );
+                if let Ok(mut outcomes) = outcomes.lock() {
+                    outcomes.push(outcome);
+                }
+            }
+        }));
+    }
+    for worker in workers {
+        let _ = worker.join();
+    }
+
+    let outcomes = std::mem::take(&mut *outcomes.lock().expect("matrix outcomes"));
+    print_matrix(&distros, ladder, &outcomes);
+
+    let failed: Vec<&str> = outcomes
+        .iter()
+        .filter(|outcome| !outcome.passed)
+        .map(|outcome| outcome.distro_id)
+        .collect();
+    if !failed.is_empty() {
+        bail!("scenario matrix failed for: {}", failed.join(", "));
+    }
+    Ok(())
+}
+
+fn run_job(
+    root: &Path,
+    job: &MatrixJob,
+    target_key: &str,
+    inject: Option<String>,
+    inject_file: Option<PathBuf>,
+) -> JobOutcome {
+    eprintln!(
+        "[matrix] {}: started (ssh port {}, vnc port {})",
+        job.distro_id, job.ssh_port, job.vnc_port
+    );
+    match run_job_reported(root, job, target_key, inject, inject_file) {
+        Ok((passed, passed_keys, report_path)) => {
+            eprintln!(
+                "[matrix] {}: {} ({})",
+                job.distro_id,
+                if passed { "passed" } else { "FAILED" },
+                report_path.display()
+            );
+            JobOutcome {
+                distro_id: job.distro_id,
+                passed,
+                passed_keys,
+                report_path: Some(report_path),
+                error: None,
+            }
+        }
+        Err(err) => {
+            eprintln!("[matrix] {}: error: {err:#}", job.distro_id);
+            JobOutcome {
+                distro_id: job.distro_id,
+                passed: false,
+                passed_keys: Vec::new(),
+                report_path: None,
+                error: Some(format!("{err:#}")),
+            }
+        }
+    }
+}
+
+/// Runs one distro ladder with isolated ports and temp dir, one disk checkpoint at a time like
+/// `test-up-to`, returning `(passed, observed pass keys, last JSON report path)`.
+fn run_job_reported(
+    root: &Path,
+    job: &MatrixJob,
+    target_key: &str,
+    inject: Option<String>,
+    inject_file: Option<PathBuf>,
+) -> Result<(bool, Vec<String>, PathBuf)> {
+    let mut passed_keys = Vec::new();
+    let mut json_path = PathBuf::new();
+    for stop in disk_checkpoint_stops(target_key) {
+        let mut cmd = install_tests_command(
+            root,
+            &["--distro", job.distro_id, "--up-to-scenario", stop],
+            inject.clone(),
+            inject_file.clone(),
+        )?;
+        cmd.env("TMPDIR", &job.tmp_dir)
+            .env(SSH_PORT_ENV, job.ssh_port.to_string())
+            .env(VNC_PORT_ENV, job.vnc_port.to_string());
+
+        let before = snapshots::runtime_fingerprint(job.distro_id);
+        let stem = report::report_stem(root, job.distro_id, stop);
+        let log_path = stem.with_extension("log");
+        let run = report::run_captured(&mut cmd, &log_path, false)?;
+        let failure =
+            (!run.status.success()).then(|| format!("Command failed with status {}", run.status));
+        let passed = failure.is_none();
+        let mut scenario_report = ScenarioReport::from_run(
+            "matrix",
+            stop,
+            job.distro_id,
+            crate::cli::Firmware::Uefi.as_str(),
+            &run,
+            failure,
+            &log_path,
+        );
+        json_path = scenario_report.write(&stem)?.0;
+        passed_keys.extend(
+            run.markers
+                .iter()
+                .map(|(_, marker)| report::marker_scenario_key(marker)),
+        );
+        if !passed {
+            return Ok((false, passed_keys, json_path));
+        }
+        snapshots::record_checkpoint(root, job.distro_id, stop, before.as_ref())?;
+    }
+    Ok((true, passed_keys, json_path))
+}
+
+/// Caps `--jobs` by the host memory budget so concurrent VMs do not push the host into swap.
+fn effective_jobs(opts: &MatrixOptions, job_count: usize) -> Result<usize> {
+    if opts.jobs == 0 {
+        bail!("--jobs must be at least 1");
+    }
+    let budget = match opts.mem_budget_mib {
+        Some(budget) => budget,
+        None => available_memory_mib()
+            .map(|available| available * 4 / 5)
+            .unwrap_or(opts.job_mem_mib),
+    };
+    let by_memory = (budget / opts.job_mem_mib.max(1)).max(1) as usize;
+    let jobs = opts.jobs.min(by_memory).min(job_count);
+    if jobs < opts.jobs.min(job_count) {
+        eprintln!(
+            "[matrix] memory budget {budget} MiB fits {by_memory} job(s) of {} MiB; running {jobs} concurrently",
+            opts.job_mem_mib
+        );
+    }
+    Ok(jobs)
+}
+
+fn available_memory_mib() -> Option<u64> {
+    let meminfo = fs::read_to_string("/proc/meminfo").ok()?;
+    meminfo.lines().find_map(|line| {
+        let rest = line.strip_prefix("MemAvailable:")?;
+        let kib: u64 = rest.trim().trim_end_matches("kB").trim().parse().ok()?;
+        Some(kib / 1024)
+    })
+}
+
+fn cell_status(outcome: Option<&JobOutcome>, ladder: &[(&str, &str)], idx: usize) -> &'static str {
+    let Some(outcome) = outcome else {
+        return "-";
+    };
+    let key = ladder[idx].0;
+    if outcome.passed || outcome.passed_keys.iter().any(|passed| passed == key) {
+        return "pass";
+    }
+    // The first rung without a pass marker is where a failed run stopped.
+    let first_unpassed = ladder
+        .iter()
+        .position(|(key, _)| !outcome.passed_keys.iter().any(|passed| passed == key));
+    if first_unpassed == Some(idx) {
+        "FAIL"
+    } else {
+        "-"
+    }
+}
+
+fn print_matrix(distros: &[&str], ladder: &[(&str, &str)], outcomes: &[JobOutcome]) {
+    let width = ladder
+        .iter()
+        .map(|(_, label)| label.len())
+        .max()
+        .unwrap_or(4)
+        .max(4);
+    let mut header = format!("{:<10}", "distro");
+    for (_, label) in ladder {
+        header.push_str(&format!(" {label:<width$}"));
+    }
+    println!();
+    println!("{header}");
+    for distro_id in distros {
+        let outcome = outcomes
+            .iter()
+            .find(|outcome| outcome.distro_id == *distro_id);
+        let mut row = format!("{distro_id:<10}");
+        for idx in 0..ladder.len() {
+            row.push_str(&format!(" {:<width$}", cell_status(outcome, ladder, idx)));
+        }
+        println!("{row}");
+    }
+    for outcome in outcomes {
+        match (&outcome.error, &outcome.report_path) {
+            (Some(error), _) => println!("{}: {error}", outcome.distro_id),
+            (None, Some(report_path)) if !outcome.passed => {
+                println!("{}: see {}", outcome.distro_id, report_path.display())
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_job_marks_first_unpassed_rung() {
+        let ladder = &CHECKPOINT_LADDER[..4];
+        let outcome = JobOutcome {
+            distro_id: "acorn",
+            passed: false,
+            passed_keys: vec!["build-preflight".to_string(), "live-boot".to_string()],
+            report_path: None,
+            error: None,
+        };
+        let cells: Vec<_> = (0..ladder.len())
+            .map(|idx| cell_status(Some(&outcome), ladder, idx))
+            .collect();
+        assert_eq!(cells, vec!["pass", "pass", "FAIL", "-"]);
+    }
+
+    #[test]
+    fn checkpoints_after_counts_skipped_rungs() {
+        assert_eq!(checkpoints_after("live-tools", "installed-boot"), 2);
+        assert_eq!(checkpoints_after("installed-boot", "installed-boot"), 0);
+        assert_eq!(checkpoints_after("live-tools", "secboot-refusal"), 0);
+    }
+
+    #[test]
+    fn disk_checkpoint_stops_end_at_the_target() {
+        assert_eq!(
+            disk_checkpoint_stops("runtime"),
+            ["install", "installed-boot", "automated-login", "runtime"]
+        );
+        assert_eq!(disk_checkpoint_stops("live-tools"), ["live-tools"]);
+    }
+}
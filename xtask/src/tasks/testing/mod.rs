@@ -1 +1,32 @@
+pub mod ab_trial;
+pub mod boot_patterns;
+pub mod boot_timeline;
+pub mod crash;
+pub mod direct_boot;
+pub mod flake;
+pub mod hw_profile;
+pub mod injection;
+pub mod matrix;
+pub mod registry;
+pub mod report;
 pub mod scenarios;
+pub mod secure_boot;
+pub mod share;
+pub mod snapshots;
+pub mod visual;
+pub mod vm;
+
+/// Unique scratch directory for a test; callers create and remove it.
+#[cfg(test)]
+pub(crate) fn test_temp_dir(tag: &str) -> std::path::PathBuf {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    static TEST_COUNTER: AtomicU64 = AtomicU64::new(0);
+    let id = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("clock")
+        .as_nanos();
+    std::env::temp_dir().join(format!("levitate-xtask-{tag}-{nanos}-{id}"))
+}
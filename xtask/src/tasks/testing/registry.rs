@@ -0,0 +1,308 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Registry entry for one running scenario VM: `<id>.json` in the per-user runtime dir, next to
+/// the QEMU-written `<id>.pid` and the `<id>.qmp` socket.
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct VmRecord {
+    pub(super) id: String,
+    pub(super) distro: String,
+    pub(super) scenario: String,
+    /// The `cargo xtask scenarios boot` process that owns the VM.
+    pub(super) owner_pid: u32,
+    pub(super) started_unix: u64,
+    pub(super) qmp_socket: PathBuf,
+    /// Serial console log, when the console is not attached to the owner's terminal.
+    #[serde(default)]
+    pub(super) serial_log: Option<PathBuf>,
+    #[serde(default)]
+    pub(super) vnc: Option<String>,
+    #[serde(default)]
+    pub(super) ssh: Option<SshEndpoint>,
+}
+
+/// SSH endpoint of a VM booted with `--ssh`, used by `scenarios exec`, `cp` and `attach`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(super) struct SshEndpoint {
+    pub(super) port: u16,
+    pub(super) private_key: PathBuf,
+    /// The booting session's scratch known_hosts, so later connections see the same host key.
+    pub(super) known_hosts: PathBuf,
+}
+
+impl SshEndpoint {
+    /// `ssh`/`scp` options for non-interactive connections to the guest.
+    pub(super) fn args(&self) -> Vec<String> {
+        let mut args =
+            super::scenarios::common_ssh_args(&self.private_key, self.port, &self.known_hosts);
+        args.extend(["-o".to_string(), "BatchMode=yes".to_string()]);
+        args
+    }
+}
+
+pub(super) fn record_path(id: &str) -> PathBuf {
+    crate::util::qmp::runtime_dir().join(format!("{id}.json"))
+}
+
+/// QEMU writes its pid here via `-pidfile`.
+pub(super) fn pid_path(id: &str) -> PathBuf {
+    pid_path_in(&crate::util::qmp::runtime_dir(), id)
+}
+
+fn pid_path_in(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{id}.pid"))
+}
+
+impl VmRecord {
+    pub(super) fn write(&self) -> Result<()> {
+        let path = record_path(&self.id);
+        let raw = serde_json::to_string_pretty(self).context("encoding VM registry entry")?;
+        fs::write(&path, raw)
+            .with_context(|| format!("writing VM registry entry '{}'", path.display()))
+    }
+
+    /// Loads the entry for `id`, pruning it (and failing) when its VM is gone.
+    pub(super) fn lookup(id: &str) -> Result<Self> {
+        Self::lookup_in(&crate::util::qmp::runtime_dir(), id)
+    }
+
+    fn lookup_in(dir: &Path, id: &str) -> Result<Self> {
+        let path = dir.join(format!("{id}.json"));
+        let record = match read_record(&path) {
+            Some(record) => record,
+            None => bail!(
+                "no running scenario VM '{id}' (no registry entry {}).\n\
+                 `cargo xtask scenarios ps` lists running VMs.",
+                path.display()
+            ),
+        };
+        if !record.is_alive_in(dir) {
+            remove_in(dir, id);
+            bail!("scenario VM '{id}' is no longer running; removed its stale registry entry");
+        }
+        Ok(record)
+    }
+
+    /// QEMU's pid once it has written its pidfile.
+    pub(super) fn qemu_pid(&self) -> Option<u32> {
+        read_pid(&pid_path(&self.id))
+    }
+
+    /// A VM is alive while its QEMU runs, or while its owner is still starting QEMU.
+    pub(super) fn is_alive(&self) -> bool {
+        self.is_alive_in(&crate::util::qmp::runtime_dir())
+    }
+
+    /// Pids get reused, so a live pid only counts when it is still the same process: QEMU's
+    /// command line names this VM's pidfile, and the owner started before it wrote the entry.
+    fn is_alive_in(&self, dir: &Path) -> bool {
+        let pidfile = pid_path_in(dir, &self.id);
+        match read_pid(&pidfile) {
+            Some(pid) => {
+                process_alive(pid)
+                    && process_cmdline(pid)
+                        .is_none_or(|cmdline| cmdline.contains(&pidfile.display().to_string()))
+            }
+            None => {
+                process_alive(self.owner_pid)
+                    // One second of slack for the tick and boot-time rounding.
+                    && process_started_unix(self.owner_pid)
+                        .is_none_or(|started| started <= self.started_unix + 1)
+            }
+        }
+    }
+}
+
+/// Running VMs, oldest first. Entries whose processes are gone are removed along the way.
+pub(super) fn list() -> Result<Vec<VmRecord>> {
+    list_in(&crate::util::qmp::runtime_dir())
+}
+
+fn list_in(dir: &Path) -> Result<Vec<VmRecord>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(Vec::new()),
+    };
+    let mut records = Vec::new();
+    for entry in entries {
+        let path = entry
+            .with_context(|| format!("reading VM registry '{}'", dir.display()))?
+            .path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let Some(record) = read_record(&path) else {
+            continue;
+        };
+        if record.is_alive_in(dir) {
+            records.push(record);
+        } else {
+            remove_in(dir, &record.id);
+        }
+    }
+    records.sort_by_key(|record| record.started_unix);
+    Ok(records)
+}
+
+/// Removes the registry entry, pidfile and QMP socket of `id`.
+pub(super) fn remove(id: &str) {
+    remove_in(&crate::util::qmp::runtime_dir(), id);
+}
+
+fn remove_in(dir: &Path, id: &str) {
+    for ext in ["json", "pid", "qmp"] {
+        let _ = fs::remove_file(dir.join(format!("{id}.{ext}")));
+    }
+}
+
+fn read_record(path: &Path) -> Option<VmRecord> {
+    let raw = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&raw).ok()
+}
+
+/// Parses a QEMU `-pidfile` (the pid and a newline); missing or partly written files read as
+/// no pid yet.
+fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|raw| raw.trim().parse().ok())
+        .filter(|&pid| pid > 0)
+}
+
+/// NUL-separated arguments joined with spaces, or `None` where `/proc` is unavailable.
+fn process_cmdline(pid: u32) -> Option<String> {
+    let raw = fs::read(format!("/proc/{pid}/cmdline")).ok()?;
+    Some(String::from_utf8_lossy(&raw).replace('\0', " "))
+}
+
+/// Start time of `pid` as a Unix timestamp, from its `/proc` start ticks and the boot time.
+fn process_started_unix(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    let ticks = parse_start_ticks(&stat)?;
+    let boot = fs::read_to_string("/proc/stat").ok()?;
+    let btime = boot
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    // SAFETY: sysconf has no preconditions.
+    let hz = u64::try_from(unsafe { libc::sysconf(libc::_SC_CLK_TCK) }).ok()?;
+    (hz > 0).then(|| btime + ticks / hz)
+}
+
+/// Field 22 (`starttime`) of `/proc/<pid>/stat`; `comm` may contain spaces and parentheses, so
+/// fields are counted from its closing parenthesis.
+fn parse_start_ticks(stat: &str) -> Option<u64> {
+    let (_, rest) = stat.rsplit_once(')')?;
+    rest.split_whitespace().nth(19)?.parse().ok()
+}
+
+pub(super) fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: signal 0 performs only the existence and permission check.
+    let rc = unsafe { libc::kill(pid, 0) };
+    rc == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, owner_pid: u32, started_unix: u64) -> VmRecord {
+        VmRecord {
+            id: id.to_string(),
+            distro: "levitate".to_string(),
+            scenario: "live-boot".to_string(),
+            owner_pid,
+            started_unix,
+            qmp_socket: PathBuf::from(format!("{id}.qmp")),
+            serial_log: None,
+            vnc: None,
+            ssh: None,
+        }
+    }
+
+    fn write_in(dir: &Path, record: &VmRecord) {
+        let raw = serde_json::to_string(record).expect("encode");
+        fs::write(dir.join(format!("{}.json", record.id)), raw).expect("write entry");
+    }
+
+    fn now_unix() -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .expect("clock")
+            .as_secs()
+    }
+
+    #[test]
+    fn list_and_lookup_prune_stale_entries() {
+        let dir = crate::tasks::testing::test_temp_dir("vm-registry");
+        fs::create_dir_all(&dir).expect("registry dir");
+        // This test process stands in for an owner still starting QEMU.
+        write_in(&dir, &record("live", std::process::id(), now_unix()));
+        // No such pid.
+        write_in(&dir, &record("gone", u32::MAX, 0));
+        fs::write(dir.join("gone.qmp"), "").expect("socket placeholder");
+        // A live pid that started after the entry was written is a reused pid.
+        write_in(&dir, &record("reused-owner", std::process::id(), 0));
+        // A live pid whose command line does not name the pidfile is not this VM's QEMU.
+        write_in(&dir, &record("reused-qemu", std::process::id(), now_unix()));
+        fs::write(
+            dir.join("reused-qemu.pid"),
+            format!("{}\n", std::process::id()),
+        )
+        .expect("pidfile");
+
+        let ids: Vec<String> = list_in(&dir)
+            .expect("list")
+            .into_iter()
+            .map(|record| record.id)
+            .collect();
+        let gone_left = dir.join("gone.json").exists() || dir.join("gone.qmp").exists();
+        let reused_left = dir.join("reused-qemu.pid").exists();
+        let live = VmRecord::lookup_in(&dir, "live").map(|record| record.id);
+        write_in(&dir, &record("gone", u32::MAX, 0));
+        let stale = VmRecord::lookup_in(&dir, "gone").map(|record| record.id);
+        let stale_left = dir.join("gone.json").exists();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(ids, ["live"]);
+        assert!(!gone_left && !reused_left);
+        assert_eq!(live.expect("live lookup"), "live");
+        assert!(format!("{:#}", stale.unwrap_err()).contains("no longer running"));
+        assert!(!stale_left);
+    }
+
+    #[test]
+    fn pidfiles_parse_only_a_positive_pid() {
+        let dir = crate::tasks::testing::test_temp_dir("vm-pidfile");
+        fs::create_dir_all(&dir).expect("pidfile dir");
+        let pid = |raw: &str| {
+            let path = dir.join("vm.pid");
+            fs::write(&path, raw).expect("pidfile");
+            read_pid(&path)
+        };
+        let parsed = [pid("4242\n"), pid(""), pid("42x"), pid("0\n")];
+        let missing = read_pid(&dir.join("missing.pid"));
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(parsed, [Some(4242), None, None, None]);
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn process_checks_see_this_process() {
+        let me = std::process::id();
+        assert!(process_alive(me));
+        assert!(!process_alive(u32::MAX));
+        assert!(process_started_unix(me).is_some_and(|started| started <= now_unix() + 1));
+        assert!(process_cmdline(me).is_some_and(|cmdline| !cmdline.contains('\0')));
+        let stat = "42 (qemu (x) y) S 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 98765 rest";
+        assert_eq!(parse_start_ticks(stat), Some(98765));
+    }
+}
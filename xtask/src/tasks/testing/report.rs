@@ -0,0 +1,671 @@
+use crate::util::asciicast;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::os::fd::{FromRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const REPORT_SCHEMA_VERSION: u32 = 2;
+const LOG_TAIL_LINES: usize = 120;
+
+/// Evidence pass markers in scenarios.toml follow `<SCENARIO WORDS> PASSED`
+/// (for example `LIVE BOOT PASSED` for `live-boot`).
+const PASS_MARKER_PATTERN: &str = r"\b([A-Z][A-Z0-9]*(?: [A-Z0-9]+)*) PASSED\b";
+/// Accelerator line the harness prints for each VM it starts, like `scenarios boot` does
+/// (`Accelerator: tcg (/dev/kvm is not accessible)`).
+const ACCELERATOR_PATTERN: &str = r"^Accelerator: ([a-z0-9]+)\b";
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ScenarioReport {
+    pub(crate) schema_version: u32,
+    /// `test`, `test-up-to` or `matrix`.
+    pub(crate) mode: String,
+    pub(crate) scenario: String,
+    pub(crate) distro: String,
+    /// Accelerator the harness reported for its VMs (`unknown` when it printed none).
+    pub(crate) accelerator: String,
+    /// `uefi` or `bios` (schema 1 reports predate the field and were all UEFI).
+    pub(crate) firmware: String,
+    pub(crate) started_at: String,
+    pub(crate) finished_at: String,
+    pub(crate) duration_secs: f64,
+    pub(crate) passed: bool,
+    pub(crate) pass_marker: Option<String>,
+    pub(crate) failure_reason: Option<String>,
+    pub(crate) phases: Vec<PhaseReport>,
+    pub(crate) log_path: PathBuf,
+    pub(crate) log_tail_path: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct PhaseReport {
+    pub(crate) scenario: String,
+    pub(crate) passed: bool,
+    pub(crate) started_offset_secs: f64,
+    pub(crate) duration_secs: f64,
+    pub(crate) pass_marker: Option<String>,
+}
+
+/// Output of a harness subprocess that was streamed to the terminal and captured to a log.
+pub(crate) struct CapturedRun {
+    pub(crate) status: ExitStatus,
+    pub(crate) started: SystemTime,
+    pub(crate) elapsed: Duration,
+    /// Pass markers in the order they were printed, with their offset from process start.
+    pub(crate) markers: Vec<(Duration, String)>,
+    /// The harness's own stderr (QEMU and harness diagnostics), kept apart from guest output.
+    pub(crate) stderr: String,
+    /// Last accelerator the harness reported, if any.
+    pub(crate) accelerator: Option<String>,
+}
+
+/// Runs `cmd`, copying stdout/stderr into `log_path` and echoing them as they arrive when
+/// `echo` is set. When echoing to a terminal, the child's stdout is a PTY so the harness keeps
+/// its interactive output; stderr stays a separate pipe so it can still be classified.
+/// Opens a PTY sized like the terminal for a child's stdout, returning `(master, slave)`, or
+/// `None` when xtask's own stdout is not a terminal.
+fn stdout_pty() -> Result<Option<(File, OwnedFd)>> {
+    // SAFETY: isatty only inspects the descriptor.
+    if unsafe { libc::isatty(libc::STDOUT_FILENO) } != 1 {
+        return Ok(None);
+    }
+    let (cols, rows) = asciicast::terminal_size();
+    let size = libc::winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    let (mut master, mut slave) = (-1, -1);
+    // SAFETY: openpty writes the two descriptors it opens; the name and termios are optional.
+    let rc = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            &size,
+        )
+    };
+    if rc != 0 {
+        return Err(std::io::Error::last_os_error()).context("opening PTY for harness output");
+    }
+    for fd in [master, slave] {
+        // SAFETY: both descriptors were just opened by openpty; the child gets the slave
+        // through dup2, which clears close-on-exec on its stdout.
+        unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+    }
+    // SAFETY: openpty succeeded, so both descriptors are open and owned by nobody else.
+    Ok(Some(unsafe {
+        (File::from_raw_fd(master), OwnedFd::from_raw_fd(slave))
+    }))
+}
+
+pub(crate) fn run_captured(cmd: &mut Command, log_path: &Path, echo: bool) -> Result<CapturedRun> {
+    if let Some(parent) = log_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("creating report directory '{}'", parent.display()))?;
+    }
+    let log = Arc::new(Mutex::new(File::create(log_path).with_context(|| {
+        format!("creating scenario log '{}'", log_path.display())
+    })?));
+    let markers = Arc::new(Mutex::new(Vec::new()));
+    let stderr_text = Arc::new(Mutex::new(String::new()));
+    let accelerator = Arc::new(Mutex::new(None));
+    let marker_re = Regex::new(PASS_MARKER_PATTERN).expect("valid pass marker regex");
+    let accel_re = Regex::new(ACCELERATOR_PATTERN).expect("valid accelerator regex");
+
+    let started = SystemTime::now();
+    let start = Instant::now();
+    let pty = if echo { stdout_pty()? } else { None };
+    let (pty_master, stdout_target) = match pty {
+        Some((master, slave)) => (Some(master), Stdio::from(slave)),
+        None => (None, Stdio::piped()),
+    };
+    let spawned = cmd
+        .stdout(stdout_target)
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Spawning command");
+    // `cmd` keeps its configured stdio; drop our copy of the PTY slave so the master sees EOF.
+    cmd.stdout(Stdio::inherit());
+    let mut child = spawned?;
+
+    let mut readers = Vec::new();
+    let stdout: Box<dyn std::io::Read + Send> = match pty_master {
+        Some(master) => Box::new(master),
+        None => Box::new(child.stdout.take().expect("piped stdout")),
+    };
+    let stderr = child.stderr.take().expect("piped stderr");
+    for (stream, to_stderr) in [(stdout, false), (Box::new(stderr) as Box<_>, true)] {
+        let log = Arc::clone(&log);
+        let markers = Arc::clone(&markers);
+        let stderr_text = Arc::clone(&stderr_text);
+        let accelerator = Arc::clone(&accelerator);
+        let marker_re = marker_re.clone();
+        let accel_re = accel_re.clone();
+        readers.push(thread::spawn(move || {
+            let mut stream = stream;
+            let handle_line = |line: &mut Vec<u8>| {
+                // The PTY line discipline turns `\n` into `\r\n`; keep the log plain.
+                if line.ends_with(b"\r\n") {
+                    line.remove(line.len() - 2);
+                }
+                if let Ok(mut log) = log.lock() {
+                    let _ = log.write_all(line);
+                }
+                let text = String::from_utf8_lossy(line);
+                if to_stderr && let Ok(mut stderr_text) = stderr_text.lock() {
+                    stderr_text.push_str(&text);
+                }
+                if let Some(found) = accel_re.captures(text.trim_start())
+                    && let Ok(mut accelerator) = accelerator.lock()
+                {
+                    *accelerator = Some(found[1].to_string());
+                }
+                if let Some(found) = marker_re.captures(&text)
+                    && let Ok(mut markers) = markers.lock()
+                {
+                    markers.push((start.elapsed(), found[0].to_string()));
+                }
+            };
+            let mut buf = [0_u8; 8192];
+            let mut pending = Vec::new();
+            loop {
+                // A PTY master reports EIO once the child side has closed.
+                let read = match stream.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => read,
+                };
+                // Echo chunks as they arrive so progress lines without a newline still show.
+                if echo && to_stderr {
+                    let mut out = std::io::stderr().lock();
+                    let _ = out.write_all(&buf[..read]);
+                    let _ = out.flush();
+                } else if echo {
+                    let mut out = std::io::stdout().lock();
+                    let _ = out.write_all(&buf[..read]);
+                    let _ = out.flush();
+                }
+                pending.extend_from_slice(&buf[..read]);
+                while let Some(end) = pending.iter().position(|&byte| byte == b'\n') {
+                    let mut line: Vec<u8> = pending.drain(..=end).collect();
+                    handle_line(&mut line);
+                }
+            }
+            if !pending.is_empty() {
+                handle_line(&mut pending);
+            }
+        }));
+    }
+
+    let status = child.wait().context("waiting for scenario harness")?;
+    for reader in readers {
+        let _ = reader.join();
+    }
+    let elapsed = start.elapsed();
+    let markers = markers.lock().map(|m| m.clone()).unwrap_or_default();
+    let stderr = stderr_text.lock().map(|s| s.clone()).unwrap_or_default();
+    let accelerator = accelerator.lock().map(|a| a.clone()).unwrap_or_default();
+    Ok(CapturedRun {
+        status,
+        started,
+        elapsed,
+        markers,
+        stderr,
+        accelerator,
+    })
+}
+
+/// Maps a pass marker such as `INSTALLED BOOT PASSED` back to its scenario key.
+pub(crate) fn marker_scenario_key(marker: &str) -> String {
+    marker
+        .trim_end_matches(" PASSED")
+        .to_ascii_lowercase()
+        .replace(' ', "-")
+}
+
+impl ScenarioReport {
+    /// Builds a report from a captured harness run. Each observed pass marker closes one
+    /// phase; a failed run gets a trailing phase for the rung that did not pass.
+    pub(crate) fn from_run(
+        mode: &str,
+        scenario: &str,
+        distro: &str,
+        firmware: &str,
+        run: &CapturedRun,
+        failure_reason: Option<String>,
+        log_path: &Path,
+    ) -> Self {
+        let mut phases = Vec::new();
+        let mut cursor = Duration::ZERO;
+        for (at, marker) in &run.markers {
+            phases.push(PhaseReport {
+                scenario: marker_scenario_key(marker),
+                passed: true,
+                started_offset_secs: cursor.as_secs_f64(),
+                duration_secs: at.saturating_sub(cursor).as_secs_f64(),
+                pass_marker: Some(marker.clone()),
+            });
+            cursor = *at;
+        }
+        let target_marker = run
+            .markers
+            .iter()
+            .map(|(_, marker)| marker)
+            .find(|marker| marker_scenario_key(marker) == scenario)
+            .cloned();
+        let passed = failure_reason.is_none();
+        if !passed || phases.is_empty() {
+            // Cached passes print no marker; failed runs end inside the rung that broke.
+            phases.push(PhaseReport {
+                scenario: if passed {
+                    scenario.to_string()
+                } else {
+                    failed_rung(scenario, &run.markers)
+                },
+                passed,
+                started_offset_secs: cursor.as_secs_f64(),
+                duration_secs: run.elapsed.saturating_sub(cursor).as_secs_f64(),
+                pass_marker: None,
+            });
+        }
+
+        Self {
+            schema_version: REPORT_SCHEMA_VERSION,
+            mode: mode.to_string(),
+            scenario: scenario.to_string(),
+            distro: distro.to_string(),
+            accelerator: run
+                .accelerator
+                .clone()
+                .unwrap_or_else(|| "unknown".to_string()),
+            firmware: firmware.to_string(),
+            started_at: rfc3339_utc(run.started),
+            finished_at: rfc3339_utc(run.started + run.elapsed),
+            duration_secs: run.elapsed.as_secs_f64(),
+            passed,
+            pass_marker: target_marker,
+            failure_reason,
+            phases,
+            log_path: log_path.to_path_buf(),
+            log_tail_path: None,
+        }
+    }
+
+    /// The ladder rung a failed run broke in (see [`failed_rung`]).
+    pub(crate) fn failed_scenario(&self) -> Option<&str> {
+        self.phases
+            .iter()
+            .find(|phase| !phase.passed)
+            .map(|phase| phase.scenario.as_str())
+    }
+
+    /// Writes `<stem>.json` and `<stem>.xml` (JUnit) next to the captured log, plus
+    /// `<stem>.tail.log` with the last log lines when the run failed.
+    pub(crate) fn write(&mut self, stem: &Path) -> Result<(PathBuf, PathBuf)> {
+        if !self.passed {
+            let tail_path = stem.with_extension("tail.log");
+            fs::write(&tail_path, log_tail(&self.log_path, LOG_TAIL_LINES))
+                .with_context(|| format!("writing log tail '{}'", tail_path.display()))?;
+            self.log_tail_path = Some(tail_path);
+        }
+        let json_path = stem.with_extension("json");
+        fs::write(
+            &json_path,
+            serde_json::to_string_pretty(self).context("encoding scenario report")?,
+        )
+        .with_context(|| format!("writing scenario report '{}'", json_path.display()))?;
+        let junit_path = stem.with_extension("xml");
+        fs::write(&junit_path, self.to_junit())
+            .with_context(|| format!("writing JUnit report '{}'", junit_path.display()))?;
+        Ok((json_path, junit_path))
+    }
+
+    fn to_junit(&self) -> String {
+        let failures = self.phases.iter().filter(|phase| !phase.passed).count();
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuites tests=\"{}\" failures=\"{failures}\" time=\"{:.3}\">\n",
+            self.phases.len(),
+            self.duration_secs
+        ));
+        xml.push_str(&format!(
+            "  <testsuite name=\"scenarios.{}\" tests=\"{}\" failures=\"{failures}\" time=\"{:.3}\" timestamp=\"{}\">\n",
+            xml_escape(&self.distro),
+            self.phases.len(),
+            self.duration_secs,
+            xml_escape(&self.started_at)
+        ));
+        xml.push_str("    <properties>\n");
+        for (name, value) in [
+            ("distro", self.distro.as_str()),
+            ("accelerator", self.accelerator.as_str()),
+            ("firmware", self.firmware.as_str()),
+            ("mode", self.mode.as_str()),
+            ("target", self.scenario.as_str()),
+        ] {
+            xml.push_str(&format!(
+                "      <property name=\"{name}\" value=\"{}\"/>\n",
+                xml_escape(value)
+            ));
+        }
+        xml.push_str("    </properties>\n");
+        for phase in &self.phases {
+            xml.push_str(&format!(
+                "    <testcase classname=\"scenarios.{}\" name=\"{}\" time=\"{:.3}\"",
+                xml_escape(&self.distro),
+                xml_escape(&phase.scenario),
+                phase.duration_secs
+            ));
+            if phase.passed {
+                xml.push_str("/>\n");
+                continue;
+            }
+            xml.push_str(">\n");
+            let reason = self.failure_reason.as_deref().unwrap_or("scenario failed");
+            xml.push_str(&format!(
+                "      <failure message=\"{}\"/>\n",
+                xml_escape(reason)
+            ));
+            let log = self.log_tail_path.as_ref().unwrap_or(&self.log_path);
+            xml.push_str(&format!(
+                "      <system-out>log: {}</system-out>\n",
+                xml_escape(&log.display().to_string())
+            ));
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+        xml
+    }
+}
+
+/// The checkpoint ladder rung a failed run toward `target` broke in: the one after the last pass
+/// marker. Without markers (or off the ladder) there is no evidence of earlier rungs, so the
+/// target itself is blamed.
+pub(crate) fn failed_rung(target: &str, markers: &[(Duration, String)]) -> String {
+    let ladder = super::matrix::ladder_through(target);
+    let last = markers
+        .last()
+        .map(|(_, marker)| marker_scenario_key(marker));
+    match last.and_then(|key| ladder.iter().position(|rung| *rung == key)) {
+        Some(idx) if idx + 1 < ladder.len() => ladder[idx + 1].to_string(),
+        _ => target.to_string(),
+    }
+}
+
+/// Report directory for one distro: `.artifacts/scenarios/<distro>/reports`.
+pub(crate) fn reports_dir(root: &Path, distro_id: &str) -> PathBuf {
+    root.join(".artifacts/scenarios")
+        .join(distro_id)
+        .join("reports")
+}
+
+/// File stem for a new report, e.g. `20260301T120000Z-live-boot`.
+pub(crate) fn report_stem(root: &Path, distro_id: &str, scenario: &str) -> PathBuf {
+    let stamp = rfc3339_utc(SystemTime::now()).replace(['-', ':'], "");
+    reports_dir(root, distro_id).join(format!("{stamp}-{scenario}"))
+}
+
+/// The subset of a stored JSON report needed for firmware coverage.
+#[derive(Deserialize)]
+struct StoredReport {
+    #[serde(default = "default_firmware")]
+    firmware: String,
+    phases: Vec<StoredPhase>,
+}
+
+#[derive(Deserialize)]
+struct StoredPhase {
+    scenario: String,
+    passed: bool,
+}
+
+fn default_firmware() -> String {
+    "uefi".to_string()
+}
+
+/// Latest result per `(scenario, firmware)` across the stored reports of one distro.
+///
+/// Report stems start with a UTC timestamp, so reading them in name order lets newer runs win.
+pub(crate) fn firmware_results(
+    root: &Path,
+    distro_id: &str,
+) -> Result<BTreeMap<(String, String), bool>> {
+    let dir = reports_dir(root, distro_id);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(err) => {
+            return Err(err).with_context(|| format!("reading reports '{}'", dir.display()));
+        }
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut results = BTreeMap::new();
+    for path in paths {
+        let parsed = fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<StoredReport>(&raw).ok());
+        let Some(report) = parsed else {
+            eprintln!("[report] skipping unreadable report {}", path.display());
+            continue;
+        };
+        for phase in report.phases {
+            results.insert((phase.scenario, report.firmware.clone()), phase.passed);
+        }
+    }
+    Ok(results)
+}
+
+/// Prints which firmware each checkpoint in `ladder` last passed or failed under.
+pub(crate) fn print_firmware_status(root: &Path, distro_id: &str, ladder: &[&str]) -> Result<()> {
+    let results = firmware_results(root, distro_id)?;
+    println!();
+    println!(
+        "Firmware coverage ({}):",
+        reports_dir(root, distro_id).display()
+    );
+    println!("{:<24} {:<6} {:<6}", "checkpoint", "uefi", "bios");
+    for key in ladder {
+        let cell = |firmware: &str| match results.get(&(key.to_string(), firmware.to_string())) {
+            Some(true) => "pass",
+            Some(false) => "FAIL",
+            None => "-",
+        };
+        println!("{key:<24} {:<6} {:<6}", cell("uefi"), cell("bios"));
+    }
+    Ok(())
+}
+
+fn log_tail(log_path: &Path, lines: usize) -> String {
+    let raw = fs::read_to_string(log_path).unwrap_or_default();
+    let all: Vec<&str> = raw.lines().collect();
+    let start = all.len().saturating_sub(lines);
+    let mut tail = all[start..].join("\n");
+    tail.push('\n');
+    tail
+}
+
+fn xml_escape(raw: &str) -> String {
+    let mut escaped = String::with_capacity(raw.len());
+    for ch in raw.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            ch if ch.is_control() && ch != '\n' && ch != '\t' => {}
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Formats `time` as `YYYY-MM-DDTHH:MM:SSZ` without pulling in a date crate.
+pub(crate) fn rfc3339_utc(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|dur| dur.as_secs())
+        .unwrap_or(0);
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    // Civil-from-days (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3_600,
+        (rem % 3_600) / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rfc3339_utc_formats_known_instants() {
+        assert_eq!(rfc3339_utc(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(rfc3339_utc(leap_day), "2024-02-29T12:34:56Z");
+    }
+
+    #[test]
+    fn markers_close_phases_and_failure_adds_trailing_phase() {
+        let run = CapturedRun {
+            status: std::process::Command::new("true").status().expect("true"),
+            started: UNIX_EPOCH,
+            elapsed: Duration::from_secs(50),
+            markers: vec![
+                (Duration::from_secs(10), "LIVE BOOT PASSED".to_string()),
+                (Duration::from_secs(30), "LIVE TOOLS PASSED".to_string()),
+            ],
+            stderr: String::new(),
+            accelerator: Some("tcg".to_string()),
+        };
+        let report = ScenarioReport::from_run(
+            "test-up-to",
+            "install",
+            "levitate",
+            "uefi",
+            &run,
+            Some("install <failed> & stopped".to_string()),
+            Path::new("/tmp/run.log"),
+        );
+        let names: Vec<_> = report.phases.iter().map(|p| p.scenario.as_str()).collect();
+        assert_eq!(names, vec!["live-boot", "live-tools", "install"]);
+        assert_eq!(report.failed_scenario(), Some("install"));
+        assert_eq!(report.accelerator, "tcg");
+        assert_eq!(report.phases[1].duration_secs, 20.0);
+        assert!(!report.phases[2].passed);
+        assert!(report.pass_marker.is_none());
+        assert!(
+            report
+                .to_junit()
+                .contains("message=\"install &lt;failed&gt; &amp; stopped\"")
+        );
+    }
+
+    #[test]
+    fn failed_rung_follows_the_last_pass_marker() {
+        let markers = |keys: &[&str]| -> Vec<(Duration, String)> {
+            keys.iter()
+                .map(|marker| (Duration::ZERO, marker.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            failed_rung("install", &markers(&["LIVE BOOT PASSED"])),
+            "live-tools"
+        );
+        assert_eq!(
+            failed_rung("runtime", &markers(&["INSTALL PASSED"])),
+            "installed-boot"
+        );
+        assert_eq!(failed_rung("install", &[]), "install");
+        assert_eq!(
+            failed_rung("install", &markers(&["INSTALL PASSED"])),
+            "install"
+        );
+    }
+
+    #[test]
+    fn firmware_results_keep_the_latest_run_per_firmware() {
+        let root = crate::tasks::testing::test_temp_dir("firmware-results");
+        let dir = reports_dir(&root, "levitate");
+        fs::create_dir_all(&dir).expect("reports dir");
+        let report = |firmware: Option<&str>, passed: bool| {
+            let firmware = firmware
+                .map(|fw| format!("\"firmware\":\"{fw}\","))
+                .unwrap_or_default();
+            format!("{{{firmware}\"phases\":[{{\"scenario\":\"live-boot\",\"passed\":{passed}}}]}}")
+        };
+        // Schema 1 report without a firmware field counts as UEFI.
+        fs::write(
+            dir.join("20260101T000000Z-live-boot.json"),
+            report(None, false),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("20260102T000000Z-live-boot.json"),
+            report(Some("uefi"), true),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("20260103T000000Z-live-boot.json"),
+            report(Some("bios"), false),
+        )
+        .unwrap();
+        let results = firmware_results(&root, "levitate").expect("results");
+        let _ = fs::remove_dir_all(&root);
+
+        let key = |firmware: &str| ("live-boot".to_string(), firmware.to_string());
+        assert_eq!(results.get(&key("uefi")), Some(&true));
+        assert_eq!(results.get(&key("bios")), Some(&false));
+    }
+
+    #[test]
+    fn run_captured_keeps_stderr_apart_and_logs_unterminated_output() {
+        let root = crate::tasks::testing::test_temp_dir("run-captured");
+        let log_path = root.join("run.log");
+        let mut cmd = Command::new("sh");
+        cmd.args([
+            "-c",
+            "echo 'LIVE BOOT PASSED'; echo 'Accelerator: tcg (no kvm)' >&2; printf tail",
+        ]);
+        let run = run_captured(&mut cmd, &log_path, false).expect("run");
+        let log = fs::read_to_string(&log_path).expect("log");
+        let _ = fs::remove_dir_all(&root);
+
+        assert!(run.status.success());
+        assert_eq!(run.markers.len(), 1);
+        assert_eq!(run.markers[0].1, "LIVE BOOT PASSED");
+        assert_eq!(run.stderr, "Accelerator: tcg (no kvm)\n");
+        assert_eq!(run.accelerator.as_deref(), Some("tcg"));
+        // The two streams interleave in arrival order.
+        assert!(log.contains("LIVE BOOT PASSED\n") && log.contains("tail"));
+    }
+}
@@ -1,8 +1,27 @@
+use super::ab_trial::{self, TrialPath, TrialVm};
+use super::boot_patterns::{
+    BootPattern, LiveBootPatterns, SECURE_BOOT_REFUSAL, kernel_cmdline_from_log,
+};
+use super::boot_timeline::{TimelineSpec, TimelineStore, TimelineTracker, artifact_input_key};
+use super::crash::{self, GuestCrash};
+use super::direct_boot::DirectBootArtifacts;
+use super::flake;
+use super::hw_profile::{self, HardwareProfile, ScratchDisks};
+use super::injection::{InjectionManifest, Provisioning};
+use super::matrix;
+use super::registry::{self, SshEndpoint, VmRecord};
+use super::report;
+use super::secure_boot::{self, SecureBootSession, UkiVariant};
+use super::share::{HostShare, ShareSession};
+use super::snapshots::{self, SnapshotMeta};
+use super::visual::{self, VisualSession};
+use crate::util::asciicast::CastWriter;
 use anyhow::{Context, Result, bail};
 use install_tests::scenarios::{
     ScenarioId, parse_scenario_name, resolve_iso_artifact_for_scenario,
     resolve_latest_install_runtime,
 };
+use std::cell::RefCell;
 use std::fs::OpenOptions;
 use std::fs::{self, File};
 use std::io::{Read, Write};
@@ -53,7 +72,7 @@ impl WindowConfig {
             WindowMode::RemoteVnc => {
                 let vnc_bind_host = detect_vnc_bind_host()?;
                 let vnc_host = detect_vnc_advertise_host()?;
-                let vnc_port = allocate_local_port(vnc_bind_host, 5900, 5999)?;
+                let vnc_port = allocate_local_port(vnc_bind_host, 5900, 5999, &[])?;
                 (
                     None,
                     None,
@@ -100,23 +119,154 @@ impl WindowConfig {
     }
 }
 
-pub fn boot(
-    target: String,
-    distro: crate::cli::BootDistro,
-    inject: Option<String>,
-    inject_file: Option<PathBuf>,
-    ssh: bool,
-    ssh_port: u16,
-    ssh_timeout: u64,
-    no_shell: bool,
-    window: bool,
-    ssh_private_key: Option<PathBuf>,
-) -> Result<()> {
+/// Spawn-point options for disk-backed checkpoint boots.
+#[derive(Clone, Debug, Default)]
+pub struct SpawnOptions {
+    /// Save the overlay as a new named snapshot after the VM exits.
+    pub persist: Option<String>,
+    /// Boot a named snapshot instead of the checkpoint's recorded base.
+    pub snapshot: Option<String>,
+}
+
+/// `scenarios boot` flags, as parsed from the command line.
+#[derive(Clone, Debug)]
+pub struct BootOptions {
+    pub inject: Option<String>,
+    pub inject_file: Option<PathBuf>,
+    pub inject_manifest: Option<PathBuf>,
+    pub ssh: bool,
+    pub ssh_port: u16,
+    pub ssh_timeout: u64,
+    pub no_shell: bool,
+    pub window: bool,
+    pub ssh_private_key: Option<PathBuf>,
+    pub accel: crate::cli::AccelMode,
+    pub firmware: crate::cli::Firmware,
+    pub arch: crate::cli::Arch,
+    pub hw_profile: Option<String>,
+    pub pvpanic: bool,
+    pub share: Vec<String>,
+    pub record: Option<PathBuf>,
+    pub visual: Option<crate::cli::VisualMode>,
+    pub spawn: SpawnOptions,
+    pub direct: bool,
+    pub cmdline_append: Option<String>,
+    pub rebase: bool,
+}
+
+pub fn boot(target: String, distro: crate::cli::BootDistro, opts: BootOptions) -> Result<()> {
+    let BootOptions {
+        inject,
+        inject_file,
+        inject_manifest,
+        ssh,
+        ssh_port,
+        ssh_timeout,
+        no_shell,
+        window,
+        ssh_private_key,
+        accel,
+        firmware,
+        arch,
+        hw_profile,
+        pvpanic,
+        share,
+        record,
+        visual,
+        spawn,
+        direct,
+        cmdline_append,
+        rebase,
+    } = opts;
     let root = crate::util::repo::repo_root()?;
-    let cfg = BootConfig::for_distro(distro);
     let scenario =
         parse_scenario_name(&target).with_context(|| format!("parsing scenario '{}'", target))?;
+    let hw = HardwareProfile::resolve(&root, scenario.key(), hw_profile.as_deref())?;
+    let mut cfg = BootConfig::for_distro(distro, hw);
+    cfg.firmware = firmware;
+    cfg.arch = arch;
+    cfg.pvpanic = pvpanic;
+    cfg.record = record;
+    cfg.timeline_rebase = rebase;
+    let manifest = inject_manifest
+        .as_deref()
+        .map(InjectionManifest::load)
+        .transpose()?;
+    if let Some(manifest) = &manifest {
+        cfg.provisioning = manifest.provisioning()?;
+    }
+    let disk_checkpoint = snapshots::checkpoint_label(scenario.key()).is_some();
+
+    if arch != crate::cli::Arch::X86_64 {
+        if !matches!(scenario, ScenarioId::LiveBoot | ScenarioId::LiveTools) || direct {
+            bail!(
+                "`--arch {0}` boots the {0} live ISO; it applies to live-boot and live-tools (without `--direct`), not '{1}'.",
+                arch.as_str(),
+                scenario.key()
+            );
+        }
+        if firmware != crate::cli::Firmware::Uefi {
+            bail!(
+                "`--arch {}` boots through AAVMF; `--firmware {}` is x86_64-only.",
+                arch.as_str(),
+                firmware.as_str()
+            );
+        }
+        if window || visual.is_some() {
+            bail!(
+                "`--arch {}` runs on the serial console or over SSH; `--window` and `--visual` are x86_64-only.",
+                arch.as_str()
+            );
+        }
+    }
+
+    if !disk_checkpoint && (spawn.persist.is_some() || spawn.snapshot.is_some()) {
+        bail!(
+            "`--persist`/`--snapshot` apply to disk-backed checkpoints (install, installed-boot, ...), not '{}'.",
+            scenario.key()
+        );
+    }
+    if let Some(name) = &spawn.persist {
+        snapshots::validate_persist_name(name)?;
+    }
+    if direct && !matches!(scenario, ScenarioId::LiveBoot | ScenarioId::LiveTools) {
+        bail!(
+            "`--direct` boots the staged live kernel and initramfs; it applies to live-boot and live-tools, not '{}'.",
+            scenario.key()
+        );
+    }
+    if firmware == crate::cli::Firmware::Secboot {
+        let installed = match scenario {
+            ScenarioId::LiveBoot if !direct => false,
+            ScenarioId::InstalledBoot => true,
+            _ => bail!(
+                "`--firmware secboot` boots the test-signed UKI; it applies to live-boot (without `--direct`) and installed-boot, not '{}'.",
+                scenario.key()
+            ),
+        };
+        let uki = secure_boot::staged_uki(&root, cfg.distro_id, installed)?;
+        cfg.secure_boot = Some(SecureBootSession::prepare(
+            &root,
+            &uki,
+            UkiVariant::Signed,
+            temp_file_path("levitate-secboot"),
+        )?);
+    }
 
+    if rebase
+        && (!matches!(scenario, ScenarioId::LiveBoot | ScenarioId::LiveTools) || !(no_shell || ssh))
+    {
+        bail!(
+            "`--rebase` records the boot timeline of monitored live boots; use it with live-boot or live-tools and `--no-shell` or `--ssh`."
+        );
+    }
+    if manifest.as_ref().is_some_and(InjectionManifest::provisions)
+        && (!matches!(scenario, ScenarioId::LiveBoot | ScenarioId::LiveTools) || !ssh)
+    {
+        bail!(
+            "hostname, SSH keys and files from `--inject-manifest` are copied in over SSH once the guest answers; use live-boot or live-tools with `--ssh` (`[env]` alone works for every boot)."
+        );
+    }
     if window && ssh {
         bail!(
             "`--window` cannot be combined with `--ssh`.\n\
@@ -124,6 +274,22 @@ pub fn boot(
              or `cargo xtask scenarios boot live-boot <distro> --ssh` for SSH workflow."
         );
     }
+    if window && cfg.record.is_some() {
+        bail!(
+            "`--record` captures the serial console, which `--window` moves to the graphical display."
+        );
+    }
+    if let Some(mode) = visual {
+        if !matches!(scenario, ScenarioId::LiveBoot | ScenarioId::LiveTools) || !no_shell || ssh {
+            bail!(
+                "`--visual` runs in the monitored serial boot; use it with live-boot or live-tools and `--no-shell` (without `--ssh`)."
+            );
+        }
+        let session =
+            VisualSession::load(&root, cfg.distro_id, scenario.key(), cfg.firmware, mode)?;
+        session.print_summary();
+        cfg.visual = Some(session);
+    }
     if window && no_shell {
         bail!(
             "`--window` cannot be combined with `--no-shell`.\n\
@@ -137,21 +303,84 @@ pub fn boot(
         None
     };
 
+    let (accelerator, reason) = Accelerator::resolve(accel, cfg.arch)?;
+    eprintln!("Accelerator: {} ({reason})", accelerator.as_str());
+    if accelerator == Accelerator::Tcg {
+        eprintln!(
+            "  note: TCG software emulation is much slower than KVM; boot and SSH timeouts are scaled by {}x.",
+            accelerator.timeout_scale()
+        );
+    }
+    cfg.accel = accelerator;
+    eprintln!("Firmware: {}", cfg.firmware.as_str());
+    if cfg.arch != crate::cli::Arch::X86_64 {
+        eprintln!("Architecture: {}", cfg.arch.as_str());
+    }
+    eprintln!("Hardware profile: {}", cfg.hw.summary());
+    if !share.is_empty() {
+        let shares = share
+            .iter()
+            .map(|raw| HostShare::parse(raw))
+            .collect::<Result<Vec<_>>>()?;
+        let session = ShareSession::start(shares, temp_file_path("levitate-shares"))?;
+        session.print_summary();
+        cfg.shares = Some(session);
+    }
+    let vm = VmControl::allocate(cfg.distro_id, scenario.key())?;
+    eprintln!(
+        "VM id: {} (QMP: {}; `cargo xtask scenarios vm {} <shutdown|screenshot|keys|status>`, `cargo xtask scenarios ps`)",
+        vm.id,
+        vm.qmp_socket.display(),
+        vm.id
+    );
+    cfg.vm = Some(vm);
+
     match scenario {
         ScenarioId::LiveBoot | ScenarioId::LiveTools => {
-            let iso_path = resolve_interactive_iso(&cfg, scenario)?;
-            let label = if scenario == ScenarioId::LiveBoot {
-                "Live boot ISO"
+            let scratch = ScratchDisks::create(
+                &root,
+                cfg.hw.live_disks(),
+                temp_file_path("levitate-hw-disks"),
+            )?;
+            cfg.scratch_disks = scratch.images.clone();
+            let (label, iso_path, direct_artifacts);
+            let media = if direct {
+                label = if scenario == ScenarioId::LiveBoot {
+                    "Live boot (direct kernel)"
+                } else {
+                    "Live tools (direct kernel)"
+                };
+                direct_artifacts = DirectBootArtifacts::resolve(
+                    &root,
+                    cfg.distro_id,
+                    scenario.key(),
+                    cmdline_append.as_deref(),
+                )?;
+                eprintln!(
+                    "Direct kernel boot: kernel={} initrd={} rootfs={}",
+                    direct_artifacts.kernel.display(),
+                    direct_artifacts.initrd.display(),
+                    direct_artifacts.rootfs.display()
+                );
+                eprintln!("  cmdline: {}", direct_artifacts.cmdline);
+                BootMedia::Direct(&direct_artifacts)
             } else {
-                "Live tools ISO"
+                label = if scenario == ScenarioId::LiveBoot {
+                    "Live boot ISO"
+                } else {
+                    "Live tools ISO"
+                };
+                iso_path = resolve_interactive_iso(&cfg, scenario)?;
+                BootMedia::Iso(&iso_path)
             };
             boot_live_iso(
                 &root,
                 &cfg,
                 label,
-                &iso_path,
+                media,
                 inject,
                 inject_file,
+                manifest.as_ref(),
                 ssh,
                 ssh_port,
                 ssh_timeout,
@@ -160,28 +389,104 @@ pub fn boot(
                 ssh_private_key,
             )
         }
-        ScenarioId::InstalledBoot => {
-            if ssh {
-                bail!(
-                    "`--ssh` is only supported for the live-boot scenario; use `cargo xtask scenarios boot live-boot --ssh`."
-                );
-            }
-            boot_installed_disk(&root, &cfg, inject, inject_file, window_cfg.as_ref())
+        _ if disk_checkpoint => {
+            // The serial console stays in the foreground; `--ssh` only forwards the port and
+            // publishes it for `scenarios exec`/`cp`.
+            let ssh = if ssh {
+                ensure_ssh_port_available(ssh_port)?;
+                Some((ssh_port, resolve_ssh_private_key(ssh_private_key)?))
+            } else {
+                None
+            };
+            boot_installed_disk(
+                &root,
+                &cfg,
+                scenario,
+                inject,
+                inject_file,
+                manifest.as_ref(),
+                window_cfg.as_ref(),
+                &spawn,
+                ssh.as_ref(),
+            )
         }
         _ => bail!(
-            "scenario '{}' is automated. Interactive targets: live-boot, live-tools, installed-boot.",
+            "scenario '{}' is automated. Interactive targets: live-boot, live-tools, and disk checkpoints (install, installed-boot, automated-login, runtime).",
             scenario.key()
         ),
     }
 }
 
+/// Firmware, architecture and accelerator of the VMs install-tests spawns for
+/// `test`/`test-up-to`.
+#[derive(Clone, Copy, Debug)]
+pub struct HarnessVm {
+    pub firmware: crate::cli::Firmware,
+    pub arch: crate::cli::Arch,
+    pub accel: crate::cli::AccelMode,
+}
+
+impl HarnessVm {
+    /// Secure Boot needs enrolled keys and the signed UKI, which only the interactive boots set
+    /// up; SeaBIOS only exists for x86_64.
+    fn validate(self) -> Result<()> {
+        if self.firmware == crate::cli::Firmware::Secboot {
+            bail!(
+                "`--firmware secboot` is interactive-only; use `cargo xtask scenarios boot <live-boot|installed-boot> --firmware secboot` or `cargo xtask scenarios secboot-refusal`."
+            );
+        }
+        if self.arch != crate::cli::Arch::X86_64 && self.firmware != crate::cli::Firmware::Uefi {
+            bail!(
+                "`--arch {}` boots through AAVMF; `--firmware {}` is x86_64-only.",
+                self.arch.as_str(),
+                self.firmware.as_str()
+            );
+        }
+        // The harness resolves `auto` itself; an unusable `--accel kvm` fails here instead.
+        Accelerator::resolve(self.accel, self.arch)?;
+        Ok(())
+    }
+
+    fn apply(self, cmd: &mut Command) {
+        cmd.env(FIRMWARE_ENV, self.firmware.as_str())
+            .env(ARCH_ENV, self.arch.as_str())
+            .env(ACCEL_ENV, self.accel.as_str());
+    }
+
+    /// Records the checkpoint spawn point after a passing run. Disk checkpoint boots are
+    /// x86_64-only, so runs for other architectures leave the recorded spawn points alone.
+    fn record_checkpoint(
+        self,
+        root: &Path,
+        distro_id: &str,
+        scenario_key: &str,
+        before: Option<&snapshots::RuntimeFingerprint>,
+    ) -> Result<()> {
+        if self.arch != crate::cli::Arch::X86_64 {
+            eprintln!(
+                "[snapshots] {} run: spawn points are recorded for x86_64 runs only",
+                self.arch.as_str()
+            );
+            return Ok(());
+        }
+        snapshots::record_checkpoint(root, distro_id, scenario_key, before)
+    }
+}
+
 pub fn test(
     target: String,
     distro: crate::cli::HarnessDistro,
     inject: Option<String>,
     inject_file: Option<PathBuf>,
+    inject_manifest: Option<PathBuf>,
+    vm: HarnessVm,
     force: bool,
+    retries: u32,
 ) -> Result<()> {
+    vm.validate()?;
+    let manifest_injection = harness_manifest_injection(inject_manifest.as_deref())?;
+    let inject_file =
+        inject_file.or_else(|| manifest_injection.as_ref().map(|inj| inj.path.clone()));
     let scenario =
         parse_scenario_name(&target).with_context(|| format!("parsing scenario '{}'", target))?;
     let mut args = vec![
@@ -194,7 +499,24 @@ pub fn test(
         args.push("--force".to_string());
     }
     let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
-    run_install_tests(&arg_refs, inject, inject_file)
+    let root = crate::util::repo::repo_root()?;
+    hw_profile::ensure_unmapped_for_install_tests(&root, &[scenario.key()])?;
+    let before = snapshots::runtime_fingerprint(distro.id());
+    let quarantined = run_install_tests_reported(
+        &root,
+        &arg_refs,
+        inject,
+        inject_file,
+        vm,
+        "test",
+        distro.id(),
+        scenario.key(),
+        retries,
+    )?;
+    if quarantined.is_some() {
+        return Ok(());
+    }
+    vm.record_checkpoint(&root, distro.id(), scenario.key(), before.as_ref())
 }
 
 pub fn test_up_to(
@@ -202,63 +524,625 @@ pub fn test_up_to(
     distro: crate::cli::HarnessDistro,
     inject: Option<String>,
     inject_file: Option<PathBuf>,
+    inject_manifest: Option<PathBuf>,
+    vm: HarnessVm,
+    retries: u32,
 ) -> Result<()> {
+    vm.validate()?;
+    let manifest_injection = harness_manifest_injection(inject_manifest.as_deref())?;
+    let inject_file =
+        inject_file.or_else(|| manifest_injection.as_ref().map(|inj| inj.path.clone()));
     let scenario =
         parse_scenario_name(&target).with_context(|| format!("parsing scenario '{}'", target))?;
-    run_install_tests(
-        &["--distro", distro.id(), "--up-to-scenario", scenario.key()],
-        inject,
-        inject_file,
-    )
+    let root = crate::util::repo::repo_root()?;
+    hw_profile::ensure_unmapped_for_install_tests(&root, &matrix::ladder_through(scenario.key()))?;
+    // Disk checkpoints share one install runtime disk, so the ladder is run one disk checkpoint
+    // at a time and each passed one is recorded before the next rung changes the disk. The
+    // harness serves rungs that already passed from its cache.
+    for stop in matrix::disk_checkpoint_stops(scenario.key()) {
+        let before = snapshots::runtime_fingerprint(distro.id());
+        let quarantined = run_install_tests_reported(
+            &root,
+            &["--distro", distro.id(), "--up-to-scenario", stop],
+            inject.clone(),
+            inject_file.clone(),
+            vm,
+            "test-up-to",
+            distro.id(),
+            stop,
+            retries,
+        )?;
+        if let Some(failed) = quarantined {
+            // The harness stops at the first failure, so a quarantined checkpoint below the
+            // target leaves the rest of the ladder unverified.
+            let skipped = matrix::checkpoints_after(&failed, scenario.key());
+            if skipped > 0 {
+                bail!(
+                    "quarantined checkpoint '{failed}' failed; skipped {skipped} checkpoints up to '{}'",
+                    scenario.key()
+                );
+            }
+            return Ok(());
+        }
+        vm.record_checkpoint(&root, distro.id(), stop, before.as_ref())?;
+    }
+    Ok(())
 }
 
 pub fn status(distro: crate::cli::HarnessDistro) -> Result<()> {
-    run_install_tests(&["--distro", distro.id(), "--status"], None, None)
+    run_install_tests(&["--distro", distro.id(), "--status"], None, None)?;
+    let root = crate::util::repo::repo_root()?;
+    report::print_firmware_status(&root, distro.id(), &matrix::ladder_through("runtime"))
 }
 
 pub fn reset(distro: crate::cli::HarnessDistro) -> Result<()> {
     run_install_tests(&["--distro", distro.id(), "--reset"], None, None)
 }
 
+/// Negative Secure Boot check: the live UKI unsigned, then signed-and-tampered, must each be
+/// refused by OVMF with the test keys enrolled.
+pub fn secboot_refusal(distro: crate::cli::BootDistro, accel: crate::cli::AccelMode) -> Result<()> {
+    let root = crate::util::repo::repo_root()?;
+    let hw = HardwareProfile::resolve(&root, ScenarioId::LiveBoot.key(), None)?;
+    let mut cfg = BootConfig::for_distro(distro, hw);
+    cfg.firmware = crate::cli::Firmware::Secboot;
+    let (accelerator, reason) = Accelerator::resolve(accel, cfg.arch)?;
+    eprintln!("Accelerator: {} ({reason})", accelerator.as_str());
+    cfg.accel = accelerator;
+
+    let uki = secure_boot::staged_uki(&root, cfg.distro_id, false)?;
+    let iso_path = resolve_interactive_iso(&cfg, ScenarioId::LiveBoot)?;
+    let patterns = LiveBootPatterns::load(&root, cfg.distro_id, cfg.firmware)?;
+    let refusal = BootPattern::parse(SECURE_BOOT_REFUSAL, "secure-boot refusal")?;
+    for variant in [UkiVariant::Unsigned, UkiVariant::Tampered] {
+        cfg.secure_boot = Some(SecureBootSession::prepare(
+            &root,
+            &uki,
+            variant,
+            temp_file_path("levitate-secboot"),
+        )?);
+        let log_path = temp_log_path("levitate-secboot-refusal");
+        let mut cmd = qemu_base_command(&root, &cfg, BootMedia::Iso(&iso_path), None, None, None)?;
+        let child = spawn_qemu_with_log(&mut cmd, &log_path, false, None)?;
+        monitor_secboot_refusal(child, &log_path, &cfg, &patterns, &refusal, variant)?;
+        let _ = fs::remove_file(&log_path);
+    }
+    eprintln!(
+        "Secure Boot refused unsigned and tampered {} UKIs.",
+        cfg.pretty_name
+    );
+    Ok(())
+}
+
+fn monitor_secboot_refusal(
+    mut child: Child,
+    log_path: &Path,
+    cfg: &BootConfig,
+    patterns: &LiveBootPatterns,
+    refusal: &BootPattern,
+    variant: UkiVariant,
+) -> Result<()> {
+    let timeout_secs = cfg.accel.scale_timeout(60);
+    let deadline = Instant::now() + Duration::from_secs(timeout_secs);
+    loop {
+        let content = read_serial_log(log_path);
+        if let Some(refused) = refusal.find(&content) {
+            let _ = child.kill();
+            let _ = child.wait();
+            eprintln!("{} UKI refused: {refused}", variant.as_str());
+            return Ok(());
+        }
+        if let Some(success) = patterns.detect_success(&content) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return bail_with_tail(
+                &format!(
+                    "Secure Boot accepted the {} UKI ({success})",
+                    variant.as_str()
+                ),
+                log_path,
+                None::<&str>,
+            );
+        }
+        if child.try_wait()?.is_some() {
+            return bail_with_tail(
+                &format!(
+                    "QEMU exited before Secure Boot refused the {} UKI",
+                    variant.as_str()
+                ),
+                log_path,
+                None::<&str>,
+            );
+        }
+        if Instant::now() > deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return bail_with_tail(
+                &format!(
+                    "Timed out waiting for Secure Boot to refuse the {} UKI ({timeout_secs}s, accel={})",
+                    variant.as_str(),
+                    cfg.accel.as_str()
+                ),
+                log_path,
+                Some("No firmware Access Denied / Security Violation message observed."),
+            );
+        }
+        sleep(Duration::from_millis(250));
+    }
+}
+
+/// Runs the 07Update A/B trial-boot paths, each on its own throwaway overlay of the 06Runtime
+/// spawn point, and prints one pass marker per path that passes.
+pub fn ab_trial(
+    distro: crate::cli::BootDistro,
+    paths: &[crate::cli::AbTrialPath],
+    accel: crate::cli::AccelMode,
+    snapshot: Option<String>,
+    boot_timeout: u64,
+    ssh_private_key: Option<PathBuf>,
+) -> Result<()> {
+    let root = crate::util::repo::repo_root()?;
+    let hw = HardwareProfile::resolve(&root, ScenarioId::Runtime.key(), None)?;
+    let mut cfg = BootConfig::for_distro(distro, hw);
+    let (accelerator, reason) = Accelerator::resolve(accel, cfg.arch)?;
+    eprintln!("Accelerator: {} ({reason})", accelerator.as_str());
+    cfg.accel = accelerator;
+    let key = resolve_ssh_private_key(ssh_private_key)?;
+    let base = resolve_spawn_base(&root, &cfg, ScenarioId::Runtime, snapshot.as_deref())?;
+    eprintln!("A/B trial base: {} ({})", base.label, base.disk.display());
+    let boot_timeout = Duration::from_secs(accelerator.scale_timeout(boot_timeout));
+
+    let mut failed = Vec::new();
+    for path in paths.iter().map(|path| TrialPath::from_cli(*path)) {
+        let overlay_dir = temp_file_path("levitate-ab-trial");
+        fs::create_dir_all(&overlay_dir)
+            .with_context(|| format!("creating overlay dir '{}'", overlay_dir.display()))?;
+        let result = boot_ab_trial_overlay(
+            &root,
+            &mut cfg,
+            &base,
+            &overlay_dir,
+            &key,
+            boot_timeout,
+            path,
+        );
+        cfg.vm = None;
+        let _ = fs::remove_dir_all(&overlay_dir);
+        match result {
+            Ok(()) => println!("{}", path.pass_marker()),
+            Err(err) => {
+                eprintln!("[ab-trial] {} failed: {err:#}", path.as_str());
+                failed.push(path.as_str());
+            }
+        }
+    }
+    if !failed.is_empty() {
+        bail!(
+            "A/B trial path(s) failed for {}: {}",
+            cfg.pretty_name,
+            failed.join(", ")
+        );
+    }
+    eprintln!("A/B trial boot verified for {}.", cfg.pretty_name);
+    Ok(())
+}
+
+fn boot_ab_trial_overlay(
+    root: &Path,
+    cfg: &mut BootConfig,
+    base: &SpawnBase,
+    overlay_dir: &Path,
+    key: &Path,
+    boot_timeout: Duration,
+    path: TrialPath,
+) -> Result<()> {
+    let (disk, vars) = create_spawn_overlay(root, base, overlay_dir)?;
+    cfg.vm = Some(VmControl::allocate(
+        cfg.distro_id,
+        &format!("ab-{}", path.as_str()),
+    )?);
+    let cfg = &*cfg;
+    let vm = cfg.vm.as_ref().expect("VM control was just allocated");
+    let ssh_port = allocate_local_port(Ipv4Addr::LOCALHOST, 2222, 2999, &[])?;
+    let mut cmd = disk_qemu_command(root, cfg, &[(disk, "qcow2")], &vars, None, Some(ssh_port))?;
+    // No `-no-reboot`: the trial paths reboot the guest in place.
+    apply_qemu_console_mode(&mut cmd, None, false);
+    apply_qemu_runtime_env(&mut cmd, root, None)?;
+    let log_path = overlay_dir.join("serial.log");
+    eprintln!(
+        "[ab-trial] {}: booting {} (VM {}, ssh 127.0.0.1:{ssh_port}, serial {})",
+        path.as_str(),
+        cfg.pretty_name,
+        vm.id,
+        log_path.display()
+    );
+    let mut child = spawn_qemu_with_log(&mut cmd, &log_path, false, None)?;
+    vm.register_console(&log_path, None)?;
+    let known_hosts = overlay_dir.join("known_hosts");
+    fs::write(&known_hosts, "").context("creating known-hosts scratch file")?;
+    vm.publish_ssh(ssh_port, key, &known_hosts)?;
+    let ssh = SshEndpoint {
+        port: ssh_port,
+        private_key: std::path::absolute(key).context("resolving SSH private key path")?,
+        known_hosts,
+    };
+    let mut trial = TrialVm {
+        ssh,
+        qmp_socket: &vm.qmp_socket,
+        child: &mut child,
+        boot_timeout,
+    };
+    let result = ab_trial::run_path(&mut trial, path);
+    shutdown_vm(&mut child, Some(vm));
+    if let Err(err) = result {
+        return bail_with_tail(&format!("{err:#}"), &log_path, None::<&str>);
+    }
+    Ok(())
+}
+
 struct BootConfig {
     distro_id: &'static str,
     pretty_name: &'static str,
+    accel: Accelerator,
+    vm: Option<VmControl>,
+    firmware: crate::cli::Firmware,
+    arch: crate::cli::Arch,
+    /// Prepared keys, vars and signed UKI when `firmware` is `secboot`.
+    secure_boot: Option<SecureBootSession>,
+    /// Attach the pvpanic device so a guest panic surfaces as the `guest-panicked` QMP state.
+    pvpanic: bool,
+    /// `--share` host directory exports.
+    shares: Option<ShareSession>,
+    /// `--record` asciicast path for the serial console.
+    record: Option<PathBuf>,
+    /// `--visual` screendump checkpoints; also gives headless boots a display adapter.
+    visual: Option<VisualSession>,
+    /// `--rebase`: adopt this boot's artifact input key as the boot-timeline baseline.
+    timeline_rebase: bool,
+    /// Hostname, SSH keys and files from `--inject-manifest`, copied in once SSH answers.
+    provisioning: Provisioning,
+    hw: HardwareProfile,
+    /// Blank profile disks attached to live boots.
+    scratch_disks: Vec<PathBuf>,
 }
 
 impl BootConfig {
-    fn for_distro(distro: crate::cli::BootDistro) -> Self {
-        match distro {
-            crate::cli::BootDistro::Levitate => Self {
-                distro_id: "levitate",
-                pretty_name: "LevitateOS",
-            },
-            crate::cli::BootDistro::Acorn => Self {
-                distro_id: "acorn",
-                pretty_name: "AcornOS",
-            },
-            crate::cli::BootDistro::Iuppiter => Self {
-                distro_id: "iuppiter",
-                pretty_name: "IuppiterOS",
-            },
-            crate::cli::BootDistro::Ralph => Self {
-                distro_id: "ralph",
-                pretty_name: "RalphOS",
+    fn for_distro(distro: crate::cli::BootDistro, hw: HardwareProfile) -> Self {
+        let (distro_id, pretty_name) = match distro {
+            crate::cli::BootDistro::Levitate => ("levitate", "LevitateOS"),
+            crate::cli::BootDistro::Acorn => ("acorn", "AcornOS"),
+            crate::cli::BootDistro::Iuppiter => ("iuppiter", "IuppiterOS"),
+            crate::cli::BootDistro::Ralph => ("ralph", "RalphOS"),
+        };
+        Self {
+            distro_id,
+            pretty_name,
+            accel: Accelerator::Kvm,
+            vm: None,
+            firmware: crate::cli::Firmware::Uefi,
+            arch: crate::cli::Arch::X86_64,
+            secure_boot: None,
+            pvpanic: false,
+            shares: None,
+            record: None,
+            visual: None,
+            timeline_rebase: false,
+            provisioning: Provisioning::default(),
+            hw,
+            scratch_disks: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum Accelerator {
+    Kvm,
+    Tcg,
+}
+
+impl Accelerator {
+    /// Resolves the requested accelerator mode and returns a human-readable reason for the choice.
+    /// KVM only runs guests of the host's own architecture; others always use TCG.
+    pub(super) fn resolve(
+        mode: crate::cli::AccelMode,
+        arch: crate::cli::Arch,
+    ) -> Result<(Self, String)> {
+        let host = crate::cli::Arch::host();
+        if host != Some(arch) {
+            let host = host.map_or(std::env::consts::ARCH, crate::cli::Arch::as_str);
+            if let crate::cli::AccelMode::Kvm = mode {
+                bail!(
+                    "`--accel kvm` cannot run an {} guest on this {host} host.\n\
+                     Use `--accel tcg` (or `--accel auto`) to boot with software emulation.",
+                    arch.as_str()
+                );
+            }
+            return Ok((
+                Self::Tcg,
+                format!("{} guest on an {host} host needs TCG", arch.as_str()),
+            ));
+        }
+        match mode {
+            crate::cli::AccelMode::Kvm => {
+                if let Err(err) = probe_kvm_access() {
+                    bail!(
+                        "`--accel kvm` requested but /dev/kvm is not usable: {err}\n\
+                         Use `--accel tcg` (or `--accel auto`) to boot with software emulation."
+                    );
+                }
+                Ok((Self::Kvm, "requested via --accel kvm".to_string()))
+            }
+            crate::cli::AccelMode::Tcg => Ok((Self::Tcg, "requested via --accel tcg".to_string())),
+            crate::cli::AccelMode::Auto => match probe_kvm_access() {
+                Ok(()) => Ok((Self::Kvm, "auto: /dev/kvm is accessible".to_string())),
+                Err(err) => Ok((
+                    Self::Tcg,
+                    format!("auto: /dev/kvm is not usable ({err}); falling back to TCG"),
+                )),
             },
         }
     }
+
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            Self::Kvm => "kvm",
+            Self::Tcg => "tcg",
+        }
+    }
+
+    fn qemu_args(self) -> &'static [&'static str] {
+        match self {
+            Self::Kvm => &["-enable-kvm", "-cpu", "host"],
+            // `max` exposes every feature TCG can emulate, which keeps x86-64-v3 userlands bootable
+            // without depending on the host CPU model.
+            Self::Tcg => &["-accel", "tcg,thread=multi", "-cpu", "max"],
+        }
+    }
+
+    fn timeout_scale(self) -> u64 {
+        match self {
+            Self::Kvm => 1,
+            Self::Tcg => std::env::var("LEVITATE_TCG_TIMEOUT_SCALE")
+                .ok()
+                .and_then(|raw| raw.trim().parse::<u64>().ok())
+                .filter(|scale| *scale > 0)
+                .unwrap_or(4),
+        }
+    }
+
+    fn scale_timeout(self, secs: u64) -> u64 {
+        secs.saturating_mul(self.timeout_scale())
+    }
+}
+
+/// QMP control channel and registry entry for one scenario VM.
+struct VmControl {
+    id: String,
+    qmp_socket: PathBuf,
+    record: RefCell<VmRecord>,
+}
+
+impl VmControl {
+    fn allocate(distro_id: &str, scenario_key: &str) -> Result<Self> {
+        let dir = crate::util::qmp::runtime_dir();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("creating scenario runtime dir '{}'", dir.display()))?;
+        // Listing prunes entries left behind by VMs that are gone.
+        let running = registry::list()?;
+        let base = format!("{distro_id}-{scenario_key}");
+        for attempt in 1..=64u32 {
+            let id = if attempt == 1 {
+                base.clone()
+            } else {
+                format!("{base}-{attempt}")
+            };
+            let qmp_socket = crate::util::qmp::socket_path(&id);
+            if running.iter().any(|record| record.id == id) {
+                continue;
+            }
+            if qmp_socket.exists() {
+                if std::os::unix::net::UnixStream::connect(&qmp_socket).is_ok() {
+                    continue;
+                }
+                // Stale socket left behind by a QEMU process that no longer exists.
+                let _ = fs::remove_file(&qmp_socket);
+            }
+            let record = VmRecord {
+                id: id.clone(),
+                distro: distro_id.to_string(),
+                scenario: scenario_key.to_string(),
+                owner_pid: std::process::id(),
+                started_unix: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|dur| dur.as_secs())
+                    .unwrap_or(0),
+                qmp_socket: qmp_socket.clone(),
+                serial_log: None,
+                vnc: None,
+                ssh: None,
+            };
+            record.write()?;
+            return Ok(Self {
+                id,
+                qmp_socket,
+                record: RefCell::new(record),
+            });
+        }
+        bail!(
+            "No free scenario VM id for '{base}' under {}",
+            dir.display()
+        )
+    }
+
+    /// Updates and rewrites the registry entry.
+    fn update(&self, edit: impl FnOnce(&mut VmRecord)) -> Result<()> {
+        let mut record = self.record.borrow_mut();
+        edit(&mut record);
+        record.write()
+    }
+
+    /// Publishes the VM's SSH endpoint for `scenarios exec`/`cp`/`attach`.
+    fn publish_ssh(&self, port: u16, private_key: &Path, known_hosts: &Path) -> Result<()> {
+        let ssh = SshEndpoint {
+            port,
+            private_key: std::path::absolute(private_key)
+                .context("resolving SSH private key path")?,
+            known_hosts: known_hosts.to_path_buf(),
+        };
+        self.update(|record| record.ssh = Some(ssh))?;
+        eprintln!(
+            "  exec/cp: `cargo xtask scenarios exec {0} -- <cmd>`, `cargo xtask scenarios cp <src> {0}:<path>`",
+            self.id
+        );
+        Ok(())
+    }
+
+    /// Records where another terminal can follow the console (`scenarios attach --serial`).
+    fn register_console(&self, serial_log: &Path, vnc: Option<String>) -> Result<()> {
+        self.update(|record| {
+            record.serial_log = Some(serial_log.to_path_buf());
+            record.vnc = vnc;
+        })
+    }
+
+    fn qemu_args(&self) -> [String; 4] {
+        [
+            "-qmp".to_string(),
+            format!("unix:{},server=on,wait=off", self.qmp_socket.display()),
+            "-pidfile".to_string(),
+            registry::pid_path(&self.id).display().to_string(),
+        ]
+    }
+
+    /// Whether QEMU reports the `guest-panicked` run state (only reachable with pvpanic).
+    fn guest_panicked(&self) -> bool {
+        crate::util::qmp::QmpClient::connect(&self.qmp_socket, Duration::from_secs(2))
+            .and_then(|mut client| client.query_status())
+            .is_ok_and(|status| status.status == "guest-panicked")
+    }
+
+    /// QEMU's run state when the guest no longer runs (powered off under `-no-shutdown`,
+    /// panicked, stopped); an ACPI powerdown request would go unanswered then.
+    fn halted_state(&self) -> Option<String> {
+        crate::util::qmp::QmpClient::connect(&self.qmp_socket, Duration::from_secs(2))
+            .and_then(|mut client| client.query_status())
+            .ok()
+            .filter(|status| !status.running)
+            .map(|status| status.status)
+    }
+
+    /// Requests ACPI powerdown and waits for QEMU to exit. Returns false if it is still running.
+    fn powerdown(&self, child: &mut Child, timeout: Duration) -> Result<bool> {
+        let mut client =
+            crate::util::qmp::QmpClient::connect(&self.qmp_socket, Duration::from_secs(5))?;
+        client.system_powerdown()?;
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            if child.try_wait()?.is_some() {
+                return Ok(true);
+            }
+            sleep(Duration::from_millis(250));
+        }
+        Ok(false)
+    }
+}
+
+impl Drop for VmControl {
+    fn drop(&mut self) {
+        registry::remove(&self.id);
+    }
+}
+
+/// Seconds a running guest gets to power off after the ACPI request before QEMU is killed;
+/// `LEVITATE_SCENARIO_POWERDOWN_TIMEOUT` overrides it.
+const DEFAULT_POWERDOWN_TIMEOUT_SECS: u64 = 10;
+
+/// Stops a scenario VM via ACPI powerdown, falling back to kill after the powerdown timeout.
+/// A guest that has already halted is not waited for.
+fn shutdown_vm(child: &mut Child, vm: Option<&VmControl>) {
+    if let Ok(Some(_)) = child.try_wait() {
+        return;
+    }
+    if let Some(vm) = vm {
+        if let Some(state) = vm.halted_state() {
+            eprintln!(
+                "VM {} is no longer running ({state}); stopping QEMU.",
+                vm.id
+            );
+            let _ = child.kill();
+            let _ = child.wait();
+            return;
+        }
+        let timeout_secs = std::env::var("LEVITATE_SCENARIO_POWERDOWN_TIMEOUT")
+            .ok()
+            .and_then(|raw| raw.trim().parse::<u64>().ok())
+            .unwrap_or(DEFAULT_POWERDOWN_TIMEOUT_SECS);
+        match vm.powerdown(child, Duration::from_secs(timeout_secs)) {
+            Ok(true) => return,
+            Ok(false) => eprintln!(
+                "VM {} did not power off within {timeout_secs}s; killing QEMU.",
+                vm.id
+            ),
+            Err(err) => eprintln!(
+                "ACPI powerdown via QMP failed for VM {}: {err:#}; killing QEMU.",
+                vm.id
+            ),
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+fn probe_kvm_access() -> Result<()> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/kvm")
+        .map(|_| ())
+        .context("opening /dev/kvm read-write")
 }
 
 fn resolve_interactive_iso(cfg: &BootConfig, scenario: ScenarioId) -> Result<PathBuf> {
     let iso = resolve_iso_artifact_for_scenario(cfg.distro_id, scenario)?.ok_or_else(|| {
         anyhow::anyhow!("scenario '{}' is not backed by a live ISO", scenario.key())
     })?;
-    Ok(iso.path)
+    if cfg.arch == crate::cli::Arch::X86_64 {
+        return Ok(iso.path);
+    }
+    let path = arch_iso_path(&iso.path, cfg.arch)?;
+    if !path.is_file() {
+        bail!(
+            "missing {} live ISO: {} (next to the x86_64 ISO {})",
+            cfg.arch.as_str(),
+            path.display(),
+            iso.path.display()
+        );
+    }
+    Ok(path)
+}
+
+/// Live ISOs are named per architecture (`levitateos-x86_64.iso`); the harness resolves the
+/// x86_64 one and other architectures sit next to it.
+fn arch_iso_path(x86_iso: &Path, arch: crate::cli::Arch) -> Result<PathBuf> {
+    let name = x86_iso
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| name.contains("x86_64"))
+        .with_context(|| {
+            format!(
+                "cannot derive the {} ISO name from '{}' (no x86_64 in the file name)",
+                arch.as_str(),
+                x86_iso.display()
+            )
+        })?;
+    Ok(x86_iso.with_file_name(name.replace("x86_64", arch.as_str())))
 }
 
 struct BootInjection {
     path: PathBuf,
     cleanup: bool,
     media_iso: Option<PathBuf>,
+    staging_dir: Option<PathBuf>,
 }
 
 impl Drop for BootInjection {
@@ -266,16 +1150,56 @@ impl Drop for BootInjection {
         if self.cleanup {
             let _ = fs::remove_file(&self.path);
         }
+        if let Some(dir) = &self.staging_dir {
+            let _ = fs::remove_dir_all(dir);
+        }
         if let Some(path) = &self.media_iso {
             let _ = fs::remove_file(path);
         }
     }
 }
 
+/// Packs `--inject-manifest` for install-tests, which takes the payload as `--inject-file`. Only
+/// `[env]` reaches the harness; it has no SSH step to copy in hostname, keys or files.
+fn harness_manifest_injection(path: Option<&Path>) -> Result<Option<BootInjection>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+    let manifest = InjectionManifest::load(path)?;
+    if manifest.provisions() {
+        bail!(
+            "install-tests takes only the `[env]` of '{}'; hostname, SSH keys and files are copied in by `scenarios boot live-boot <distro> --ssh`.",
+            path.display()
+        );
+    }
+    boot_injection_payload(None, None, Some(&manifest))
+}
+
 fn boot_injection_payload(
     inject: Option<String>,
     inject_file: Option<PathBuf>,
+    manifest: Option<&InjectionManifest>,
 ) -> Result<Option<BootInjection>> {
+    if let Some(manifest) = manifest {
+        let staging_dir = temp_file_path("levitate-boot-injection");
+        fs::create_dir_all(&staging_dir)
+            .with_context(|| format!("creating injection staging '{}'", staging_dir.display()))?;
+        // Owns the staging dir from here on so it is removed if packing fails or packs nothing.
+        let mut injection = BootInjection {
+            path: staging_dir.join("boot-injection.env"),
+            cleanup: false,
+            media_iso: None,
+            staging_dir: Some(staging_dir.clone()),
+        };
+        let Some(payload) = manifest
+            .pack_env(&staging_dir)
+            .context("packing injection manifest env")?
+        else {
+            return Ok(None);
+        };
+        injection.path = payload;
+        return Ok(Some(injection));
+    }
     if let Some(path) = inject_file {
         if !path.is_file() {
             bail!("--inject-file is not a readable file: {}", path.display());
@@ -284,6 +1208,7 @@ fn boot_injection_payload(
             path,
             cleanup: false,
             media_iso: None,
+            staging_dir: None,
         }));
     }
 
@@ -293,7 +1218,7 @@ fn boot_injection_payload(
     };
     let raw = inject.trim();
     if raw.is_empty() {
-        return Ok(None);
+        bail!("--inject payload is empty; expected KEY=VALUE pairs separated by commas");
     }
 
     let mut lines = Vec::new();
@@ -315,7 +1240,7 @@ fn boot_injection_payload(
         }
     }
     if lines.is_empty() {
-        return Ok(None);
+        bail!("--inject payload '{raw}' contains no KEY=VALUE pairs");
     }
 
     let ts = SystemTime::now()
@@ -330,16 +1255,61 @@ fn boot_injection_payload(
         path,
         cleanup: true,
         media_iso: None,
+        staging_dir: None,
     }))
 }
 
+/// Appends the `--share` mount requests to the injection payload, creating one when needed. The
+/// caller's `--inject-file` is copied rather than modified.
+fn with_share_requests(
+    injection: Option<BootInjection>,
+    cfg: &BootConfig,
+) -> Result<Option<BootInjection>> {
+    let Some(shares) = &cfg.shares else {
+        return Ok(injection);
+    };
+    let mut payload = match &injection {
+        Some(existing) => fs::read_to_string(&existing.path).with_context(|| {
+            format!(
+                "reading boot injection payload '{}'",
+                existing.path.display()
+            )
+        })?,
+        None => String::new(),
+    };
+    if !payload.is_empty() && !payload.ends_with('\n') {
+        payload.push('\n');
+    }
+    for line in shares.payload_lines() {
+        payload.push_str(&line);
+        payload.push('\n');
+    }
+    let path = temp_file_path("levitate-boot-injection").with_extension("env");
+    fs::write(&path, payload)
+        .with_context(|| format!("writing boot injection payload '{}'", path.display()))?;
+
+    let mut injection = injection.unwrap_or(BootInjection {
+        path: PathBuf::new(),
+        cleanup: false,
+        media_iso: None,
+        staging_dir: None,
+    });
+    if injection.cleanup {
+        let _ = fs::remove_file(&injection.path);
+    }
+    injection.path = path;
+    injection.cleanup = true;
+    Ok(Some(injection))
+}
+
 fn boot_live_iso(
     root: &Path,
     cfg: &BootConfig,
     scenario_label: &'static str,
-    iso_path: &Path,
+    media: BootMedia<'_>,
     inject: Option<String>,
     inject_file: Option<PathBuf>,
+    manifest: Option<&InjectionManifest>,
     ssh: bool,
     ssh_port: u16,
     ssh_timeout: u64,
@@ -347,16 +1317,17 @@ fn boot_live_iso(
     window: Option<&WindowConfig>,
     ssh_private_key: Option<PathBuf>,
 ) -> Result<()> {
-    let mut injection = boot_injection_payload(inject, inject_file)?;
+    let injection = boot_injection_payload(inject, inject_file, manifest)?;
+    let mut injection = with_share_requests(injection, cfg)?;
     if let Some(inj) = injection.as_mut() {
-        inj.media_iso = Some(create_boot_injection_iso(&inj.path)?);
+        inj.media_iso = Some(create_boot_injection_iso(inj)?);
     }
     if ssh {
         boot_live_iso_ssh(
             root,
             cfg,
             scenario_label,
-            iso_path,
+            media,
             injection,
             ssh_port,
             ssh_timeout,
@@ -365,52 +1336,79 @@ fn boot_live_iso(
             ssh_private_key,
         )
     } else {
-        boot_live_iso_serial(root, cfg, iso_path, injection, no_shell, window)
+        boot_live_iso_serial(
+            root,
+            cfg,
+            scenario_label,
+            media,
+            injection,
+            no_shell,
+            window,
+        )
     }
 }
 
 fn boot_live_iso_serial(
     root: &Path,
     cfg: &BootConfig,
-    iso_path: &Path,
+    scenario_label: &'static str,
+    media: BootMedia<'_>,
     injection: Option<BootInjection>,
     no_shell: bool,
     window: Option<&WindowConfig>,
 ) -> Result<()> {
     if no_shell {
+        let patterns = LiveBootPatterns::load(root, cfg.distro_id, cfg.firmware)?;
+        let spec = TimelineSpec::load(root, cfg.firmware)?;
         let log_path = temp_log_path("levitate-live-boot-serial");
-        let mut cmd = qemu_base_command(root, iso_path, injection.as_ref(), None, window)?;
-        let child = spawn_qemu_with_log(&mut cmd, &log_path, false)?;
-        monitor_live_iso_serial(child, &log_path)?;
+        if let Some(vm) = &cfg.vm {
+            vm.register_console(&log_path, None)?;
+        }
+        let mut cmd = qemu_base_command(root, cfg, media, injection.as_ref(), None, window)?;
+        let mut timeline = TimelineTracker::start(spec, media.timeline_store(root, cfg));
+        let child = spawn_qemu_with_log(&mut cmd, &log_path, false, cfg.record.as_deref())?;
+        monitor_live_iso_serial(child, &log_path, cfg, &patterns, &mut timeline)?;
         let _ = fs::remove_file(&log_path);
         return Ok(());
     }
 
     if window.is_some() {
         eprintln!(
-            "Booting {} live ISO in window mode... (Ctrl-C to stop)",
-            cfg.pretty_name
+            "Booting {} {} in window mode... (Ctrl-C to stop)",
+            cfg.pretty_name, scenario_label
         );
     } else {
-        eprintln!("Booting {} live ISO... (Ctrl-A X to exit)", cfg.pretty_name);
+        eprintln!(
+            "Booting {} {}... (Ctrl-A X to exit)",
+            cfg.pretty_name, scenario_label
+        );
     }
-    let mut cmd = qemu_base_command(root, iso_path, injection.as_ref(), None, window)?;
+    let mut cmd = qemu_base_command(root, cfg, media, injection.as_ref(), None, window)?;
     if let Some(window_cfg) = window {
         run_window_mode_foreground(&mut cmd, window_cfg)
     } else {
-        run_checked(&mut cmd)
+        run_serial_console(&mut cmd, cfg.record.as_deref())
     }
 }
 
-fn monitor_live_iso_serial(mut child: Child, log_path: &Path) -> Result<()> {
-    let booted_message = "switching root to live system";
-    let default_timeout = 120u64;
+fn monitor_live_iso_serial(
+    mut child: Child,
+    log_path: &Path,
+    cfg: &BootConfig,
+    patterns: &LiveBootPatterns,
+    timeline: &mut TimelineTracker<'_>,
+) -> Result<()> {
+    let accel = cfg.accel;
+    let default_timeout = accel.scale_timeout(120);
     let timeout_secs = std::env::var("LEVITATE_LIVE_BOOT_SERIAL_TIMEOUT")
         .ok()
         .or_else(|| std::env::var("LEVITATE_STAGE01_SERIAL_TIMEOUT").ok())
         .and_then(|raw| raw.parse::<u64>().ok())
         .unwrap_or(default_timeout);
+    let accel_label = accel.as_str();
     let deadline = Instant::now() + Duration::from_secs(timeout_secs.max(1));
+    let visual_settle = Duration::from_secs(accel.scale_timeout(VISUAL_SETTLE_SECS));
+    let mut ready_at = None;
 
     loop {
         if let Some(exit_status) = child.try_wait()? {
@@ -419,59 +1417,195 @@ fn monitor_live_iso_serial(mut child: Child, log_path: &Path) -> Result<()> {
                 None => "QEMU exited by signal".to_string(),
             };
             return bail_with_tail(
-                &format!("{reason} before live boot completed"),
+                &format!("{reason} before live boot completed (accel={accel_label})"),
                 log_path,
                 None::<&str>,
             );
         }
 
-        if let Some(pat) = detect_boot_regression(log_path)? {
+        let content = read_serial_log(log_path);
+        timeline.observe(&content);
+        if let Some(crash) = detect_guest_crash(cfg, patterns, log_path, &content) {
+            return bail_guest_crash(&mut child, log_path, &crash);
+        }
+        if let Some(fatal) = patterns.detect_fatal(&content) {
             let _ = child.kill();
             let _ = child.wait();
             return bail_with_tail(
-                &format!("Detected boot regression while waiting for live boot handoff: {pat}"),
+                &format!(
+                    "Detected boot regression while waiting for live boot handoff ({}): {fatal}",
+                    patterns.distro_id
+                ),
                 log_path,
                 None::<&str>,
             );
         }
-
-        if detect_live_boot_success(log_path, booted_message) {
-            let _ = child.kill();
-            let _ = child.wait();
-            return Ok(());
+        if let (Some(visual), Some(vm)) = (&cfg.visual, &cfg.vm) {
+            let observed = visual.observe(&content, &vm.qmp_socket);
+            if observed.is_err() {
+                shutdown_vm(&mut child, cfg.vm.as_ref());
+                return observed;
+            }
         }
 
-        if Instant::now() > deadline {
+        if let Some(success) = patterns.detect_success(&content) {
+            let ready_at = *ready_at.get_or_insert_with(Instant::now);
+            timeline.mark_ready();
+            // Visual checkpoints may sit past the handoff (login screen, kiosk); give them a
+            // bounded settle window before shutting down.
+            let awaiting_visual = cfg.visual.as_ref().is_some_and(VisualSession::pending)
+                && ready_at.elapsed() < visual_settle;
+            if !awaiting_visual {
+                shutdown_vm(&mut child, cfg.vm.as_ref());
+                eprintln!("Live boot succeeded: {success}");
+                report_serial_kernel_cmdline(patterns, &content, log_path)?;
+                timeline.gate(true)?;
+                return match &cfg.visual {
+                    Some(visual) => visual.finish(),
+                    None => Ok(()),
+                };
+            }
+        } else if Instant::now() > deadline {
             let _ = child.kill();
             let _ = child.wait();
             return bail_with_tail(
-                &format!("Timed out waiting for live-boot serial handoff ({timeout_secs}s)"),
+                &format!(
+                    "Timed out waiting for live-boot serial handoff ({timeout_secs}s, accel={accel_label})"
+                ),
                 log_path,
                 Some("No root-switch handoff marker observed."),
             );
         }
 
-        sleep(Duration::from_secs(1));
+        // Short poll interval keeps boot-timeline phase boundaries precise.
+        sleep(Duration::from_millis(250));
     }
 }
 
-fn detect_live_boot_success(log_path: &Path, pattern: &str) -> bool {
-    let content = match fs::read_to_string(log_path) {
-        Ok(raw) => raw,
-        Err(_) => return false,
+/// How long a live boot keeps running after its handoff for pending `--visual` checkpoints.
+const VISUAL_SETTLE_SECS: u64 = 60;
+/// Title stored in `--record` casts.
+const SERIAL_CAST_TITLE: &str = "levitate scenario serial console";
+/// How long an unfinished crash report may keep printing before the scenario fails on it.
+const CRASH_REPORT_GRACE: Duration = Duration::from_secs(2);
+
+/// Looks for a guest kernel crash on the serial console, or a pvpanic `guest-panicked` state.
+fn detect_guest_crash(
+    cfg: &BootConfig,
+    patterns: &LiveBootPatterns,
+    log_path: &Path,
+    content: &str,
+) -> Option<GuestCrash> {
+    let panicked = cfg.pvpanic && cfg.vm.as_ref().is_some_and(VmControl::guest_panicked);
+    match patterns.detect_crash(content) {
+        Some(crash) if crash.complete => return Some(crash),
+        None if !panicked => return None,
+        _ => {}
+    }
+    // Let the rest of the trace reach the serial log before capturing it.
+    sleep(CRASH_REPORT_GRACE);
+    patterns
+        .detect_crash(&read_serial_log(log_path))
+        .or_else(|| {
+            panicked.then(|| GuestCrash {
+                kind: "panic",
+                headline: "pvpanic reported guest-panicked (no report on the serial console)"
+                    .to_string(),
+                trace: String::new(),
+                complete: false,
+            })
+        })
+}
+
+fn bail_guest_crash(child: &mut Child, log_path: &Path, crash: &GuestCrash) -> Result<()> {
+    let _ = child.kill();
+    let _ = child.wait();
+    let trace = if crash.trace.is_empty() {
+        String::new()
+    } else {
+        format!("Kernel crash report:\n{}\n", crash.trace)
     };
+    bail_with_tail(&format!("Detected {crash}"), log_path, Some(&trace))
+}
+
+fn read_serial_log(log_path: &Path) -> String {
+    fs::read_to_string(log_path).unwrap_or_default()
+}
+
+/// Checks `required_kernel_cmdline` against the serial log when the kernel printed its command line.
+fn report_serial_kernel_cmdline(
+    patterns: &LiveBootPatterns,
+    content: &str,
+    log_path: &Path,
+) -> Result<()> {
+    if patterns.required_kernel_cmdline.is_empty() {
+        return Ok(());
+    }
+    let Some(cmdline) = kernel_cmdline_from_log(content) else {
+        eprintln!(
+            "  note: kernel command line not present in serial log; required_kernel_cmdline is verified over SSH (`--ssh`)."
+        );
+        return Ok(());
+    };
+    let missing = patterns.missing_kernel_cmdline(&cmdline);
+    if !missing.is_empty() {
+        return bail_with_tail(
+            &format!(
+                "Booted kernel command line is missing required arguments for {}: {}",
+                patterns.distro_id,
+                missing.join(" ")
+            ),
+            log_path,
+            Some(&format!("Observed command line: {cmdline}")),
+        );
+    }
+    eprintln!("Kernel command line contains all required arguments.");
+    Ok(())
+}
 
-    let needle = pattern.to_lowercase();
-    content
-        .lines()
-        .any(|line| line.to_lowercase().contains(&needle))
+fn verify_guest_kernel_cmdline(
+    patterns: &LiveBootPatterns,
+    ssh_port: u16,
+    private_key: &Path,
+    known_hosts: &Path,
+) -> Result<()> {
+    if patterns.required_kernel_cmdline.is_empty() {
+        return Ok(());
+    }
+    let mut args = common_ssh_args(private_key, ssh_port, known_hosts);
+    args.push("-n".to_string());
+    args.push("-o".to_string());
+    args.push("BatchMode=yes".to_string());
+    args.push("root@127.0.0.1".to_string());
+    args.push("cat /proc/cmdline".to_string());
+    let output = Command::new("ssh")
+        .args(&args)
+        .output()
+        .context("reading guest /proc/cmdline over SSH")?;
+    if !output.status.success() {
+        bail!(
+            "reading guest /proc/cmdline over SSH failed with status {}",
+            output.status
+        );
+    }
+    let cmdline = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let missing = patterns.missing_kernel_cmdline(&cmdline);
+    if !missing.is_empty() {
+        bail!(
+            "Guest /proc/cmdline is missing required arguments for {}: {}\nObserved command line: {cmdline}",
+            patterns.distro_id,
+            missing.join(" ")
+        );
+    }
+    eprintln!("Guest /proc/cmdline contains all required arguments.");
+    Ok(())
 }
 
 fn boot_live_iso_ssh(
     root: &Path,
     cfg: &BootConfig,
     scenario_label: &'static str,
-    iso_path: &Path,
+    media: BootMedia<'_>,
     injection: Option<BootInjection>,
     ssh_port: u16,
     ssh_timeout: u64,
@@ -484,10 +1618,16 @@ fn boot_live_iso_ssh(
         cfg.pretty_name, scenario_label
     );
     ensure_ssh_port_available(ssh_port)?;
+    let patterns = LiveBootPatterns::load(root, cfg.distro_id, cfg.firmware)?;
+    let spec = TimelineSpec::load(root, cfg.firmware)?;
 
-    let mut cmd = qemu_base_command(root, iso_path, injection.as_ref(), Some(ssh_port), window)?;
+    let mut cmd = qemu_base_command(root, cfg, media, injection.as_ref(), Some(ssh_port), window)?;
     let log_path = temp_log_path("levitate-live-boot-ssh");
-    let child = spawn_qemu_with_log(&mut cmd, &log_path, true)?;
+    if let Some(vm) = &cfg.vm {
+        vm.register_console(&log_path, None)?;
+    }
+    let mut timeline = TimelineTracker::start(spec, media.timeline_store(root, cfg));
+    let child = spawn_qemu_with_log(&mut cmd, &log_path, true, cfg.record.as_deref())?;
     let result = monitor_live_iso_ssh(
         child,
         &log_path,
@@ -495,6 +1635,9 @@ fn boot_live_iso_ssh(
         ssh_timeout,
         no_shell,
         ssh_private_key,
+        cfg,
+        &patterns,
+        &mut timeline,
     );
     let result = match result {
         Ok(()) => Ok(()),
@@ -517,9 +1660,15 @@ fn monitor_live_iso_ssh(
     ssh_timeout: u64,
     no_shell: bool,
     ssh_private_key: Option<PathBuf>,
+    cfg: &BootConfig,
+    patterns: &LiveBootPatterns,
+    timeline: &mut TimelineTracker<'_>,
 ) -> Result<()> {
     let known_hosts = temp_file_path("levitate-live-boot-ssh-known-hosts");
     fs::write(&known_hosts, "").context("creating known-hosts scratch file")?;
+    let accel = cfg.accel;
+    let ssh_timeout = accel.scale_timeout(ssh_timeout);
+    let accel_label = accel.as_str();
     let deadline = Instant::now() + Duration::from_secs(ssh_timeout.max(1));
     let key = resolve_ssh_private_key(ssh_private_key)?;
     let mut hook_seen = false;
@@ -535,19 +1684,26 @@ fn monitor_live_iso_ssh(
             };
             let _ = fs::remove_file(&known_hosts);
             return bail_with_tail(
-                &format!("{reason} before SSH became ready"),
+                &format!("{reason} before SSH became ready (accel={accel_label})"),
                 log_path,
                 None::<&str>,
             );
         }
 
-        if let Some(pat) = detect_boot_regression(log_path)? {
+        let content = read_serial_log(log_path);
+        timeline.observe(&content);
+        if let Some(crash) = detect_guest_crash(cfg, patterns, log_path, &content) {
+            let _ = fs::remove_file(&known_hosts);
+            return bail_guest_crash(&mut child, log_path, &crash);
+        }
+        if let Some(fatal) = patterns.detect_fatal(&content) {
             let _ = child.kill();
             let _ = child.wait();
             let _ = fs::remove_file(&known_hosts);
             return bail_with_tail(
                 &format!(
-                    "Detected boot regression while waiting for SSH (sshd failure or locale warning): {pat}"
+                    "Detected boot regression while waiting for SSH ({}): {fatal}",
+                    patterns.distro_id
                 ),
                 log_path,
                 None::<&str>,
@@ -562,14 +1718,30 @@ fn monitor_live_iso_ssh(
                 );
             }
         } else if can_ssh_connect(ssh_port, &key, &known_hosts)? {
-            if no_shell {
+            if let Some(vm) = &cfg.vm {
+                vm.publish_ssh(ssh_port, &key, &known_hosts)?;
+            }
+            let checks = timeline
+                .gate(no_shell)
+                .and_then(|()| verify_guest_kernel_cmdline(patterns, ssh_port, &key, &known_hosts))
+                .and_then(|()| {
+                    cfg.provisioning
+                        .apply(&common_ssh_args(&key, ssh_port, &known_hosts))
+                });
+            if let Err(err) = checks {
                 let _ = child.kill();
                 let _ = child.wait();
                 let _ = fs::remove_file(&known_hosts);
+                return Err(err);
+            }
+            if no_shell {
+                shutdown_vm(&mut child, cfg.vm.as_ref());
+                let _ = fs::remove_file(&known_hosts);
                 return Ok(());
             }
 
-            let status = run_interactive_ssh(ssh_port, &key, &known_hosts, &mut child);
+            let status =
+                run_interactive_ssh(ssh_port, &key, &known_hosts, &mut child, cfg.vm.as_ref());
             let _ = fs::remove_file(&known_hosts);
             return status;
         }
@@ -586,7 +1758,9 @@ fn monitor_live_iso_ssh(
                 extra = format!("No boot hook observed yet after {ssh_timeout}s.");
             }
             return bail_with_tail(
-                &format!("Timed out waiting for SSH readiness ({ssh_timeout}s)"),
+                &format!(
+                    "Timed out waiting for SSH readiness ({ssh_timeout}s, accel={accel_label})"
+                ),
                 log_path,
                 Some(&extra),
             );
@@ -686,6 +1860,7 @@ fn run_interactive_ssh(
     private_key: &Path,
     known_hosts: &Path,
     qemu: &mut Child,
+    vm: Option<&VmControl>,
 ) -> Result<()> {
     // Some interactive shells emit cursor-position queries (CSI 6n). If a reply
     // races with session teardown, bytes can leak into the next shell prompt.
@@ -704,8 +1879,7 @@ fn run_interactive_ssh(
 
     flush_tty_input_queue();
 
-    let _ = qemu.kill();
-    let _ = qemu.wait();
+    shutdown_vm(qemu, vm);
     if status.success() {
         Ok(())
     } else {
@@ -755,93 +1929,312 @@ fn resolve_ssh_private_key(arg: Option<PathBuf>) -> Result<PathBuf> {
             fallback.display()
         );
     }
-    Ok(fallback)
+    Ok(fallback)
+}
+
+/// Options shared by `ssh` and `scp` (the port is passed as `-o Port=` because the two spell the
+/// port flag differently).
+pub(super) fn common_ssh_args(key: &Path, ssh_port: u16, known_hosts: &Path) -> Vec<String> {
+    vec![
+        "-o".to_string(),
+        "ConnectTimeout=10".to_string(),
+        "-o".to_string(),
+        "StrictHostKeyChecking=accept-new".to_string(),
+        "-o".to_string(),
+        format!("UserKnownHostsFile={}", known_hosts.display()),
+        "-o".to_string(),
+        "IdentitiesOnly=yes".to_string(),
+        "-i".to_string(),
+        key.display().to_string(),
+        "-o".to_string(),
+        format!("Port={ssh_port}"),
+    ]
+}
+
+/// Read-only base image a checkpoint boot layers its throwaway overlay on.
+struct SpawnBase {
+    disk: PathBuf,
+    vars: PathBuf,
+    format: String,
+    label: String,
+    checkpoint: Option<String>,
+}
+
+fn resolve_spawn_base(
+    root: &Path,
+    cfg: &BootConfig,
+    scenario: ScenarioId,
+    snapshot: Option<&str>,
+) -> Result<SpawnBase> {
+    let checkpoint = snapshots::checkpoint_label(scenario.key()).map(str::to_string);
+    let name = snapshot.unwrap_or(scenario.key());
+    if let Some(found) = snapshots::find(root, cfg.distro_id, name)? {
+        return Ok(SpawnBase {
+            disk: found.disk(),
+            vars: found.vars(),
+            format: "qcow2".to_string(),
+            label: format!("snapshot '{}' ({})", found.meta.name, found.meta.source),
+            checkpoint: found.meta.checkpoint.clone().or(checkpoint),
+        });
+    }
+    if let Some(name) = snapshot {
+        bail!(
+            "no snapshot '{name}' for {}; see `cargo xtask scenarios snapshots ls {}`",
+            cfg.distro_id,
+            cfg.distro_id
+        );
+    }
+    if scenario != ScenarioId::InstalledBoot {
+        bail!(
+            "no recorded spawn point for {} {}; run `cargo xtask scenarios test {} {}` first",
+            cfg.distro_id,
+            checkpoint.as_deref().unwrap_or(scenario.key()),
+            scenario.key(),
+            cfg.distro_id
+        );
+    }
+
+    let runtime = resolve_latest_install_runtime(cfg.distro_id)?;
+    let format = detect_disk_image_format(&runtime.disk_path)?;
+    if format != "qcow2" {
+        eprintln!(
+            "  warning: install scenario produced non-qcow2 disk format '{}'; investigate qemu-img availability for strict qcow2 parity.",
+            format
+        );
+    }
+    Ok(SpawnBase {
+        disk: runtime.disk_path,
+        vars: runtime.ovmf_vars_path,
+        format,
+        label: format!(
+            "install scenario run {} (no recorded spawn point)",
+            runtime.run_id
+        ),
+        checkpoint,
+    })
 }
 
-fn common_ssh_args(key: &Path, ssh_port: u16, known_hosts: &Path) -> Vec<String> {
-    vec![
-        "-o".to_string(),
-        "ConnectTimeout=10".to_string(),
-        "-o".to_string(),
-        "StrictHostKeyChecking=accept-new".to_string(),
-        "-o".to_string(),
-        format!("UserKnownHostsFile={}", known_hosts.display()),
-        "-o".to_string(),
-        "IdentitiesOnly=yes".to_string(),
-        "-i".to_string(),
-        key.display().to_string(),
-        "-p".to_string(),
-        ssh_port.to_string(),
-    ]
+fn boot_installed_disk(
+    root: &Path,
+    cfg: &BootConfig,
+    scenario: ScenarioId,
+    inject: Option<String>,
+    inject_file: Option<PathBuf>,
+    manifest: Option<&InjectionManifest>,
+    window: Option<&WindowConfig>,
+    spawn: &SpawnOptions,
+    ssh: Option<&(u16, PathBuf)>,
+) -> Result<()> {
+    let base = resolve_spawn_base(root, cfg, scenario, spawn.snapshot.as_deref())?;
+    let injection = boot_injection_payload(inject, inject_file, manifest)?;
+    let mut injection = with_share_requests(injection, cfg)?;
+    if let Some(inj) = injection.as_mut() {
+        inj.media_iso = Some(create_boot_injection_iso(inj)?);
+    }
+
+    let overlay_dir = temp_file_path("levitate-spawn");
+    fs::create_dir_all(&overlay_dir)
+        .with_context(|| format!("creating overlay dir '{}'", overlay_dir.display()))?;
+    let result = boot_spawn_overlay(
+        root,
+        cfg,
+        &base,
+        &overlay_dir,
+        injection.as_ref(),
+        window,
+        spawn,
+        ssh,
+    );
+    let _ = fs::remove_dir_all(&overlay_dir);
+    result
 }
 
-fn boot_installed_disk(
+/// Creates the throwaway qcow2 overlay and a writable OVMF vars copy for `base` in
+/// `overlay_dir`, returning their paths.
+fn create_spawn_overlay(
+    root: &Path,
+    base: &SpawnBase,
+    overlay_dir: &Path,
+) -> Result<(PathBuf, PathBuf)> {
+    let disk = overlay_dir.join("overlay.qcow2");
+    snapshots::create_overlay(root, &base.disk, &base.format, &disk)?;
+    let vars = overlay_dir.join("OVMF_VARS.fd");
+    fs::copy(&base.vars, &vars)
+        .with_context(|| format!("copying OVMF vars '{}'", base.vars.display()))?;
+    fs::set_permissions(&vars, std::os::unix::fs::PermissionsExt::from_mode(0o644))
+        .context("making OVMF vars copy writable")?;
+    Ok((disk, vars))
+}
+
+fn boot_spawn_overlay(
     root: &Path,
     cfg: &BootConfig,
-    _inject: Option<String>,
-    _inject_file: Option<PathBuf>,
+    base: &SpawnBase,
+    overlay_dir: &Path,
+    injection: Option<&BootInjection>,
     window: Option<&WindowConfig>,
+    spawn: &SpawnOptions,
+    ssh: Option<&(u16, PathBuf)>,
 ) -> Result<()> {
-    let runtime = resolve_latest_install_runtime(cfg.distro_id)?;
-    let disk = runtime.disk_path;
-    let vars = runtime.ovmf_vars_path;
-    let disk_format = detect_disk_image_format(&disk)?;
-    let ovmf = crate::util::repo::ovmf_path(root)?;
-
-    let mut cmd = qemu_command_for_window(window)?;
-    cmd.args([
-        "-enable-kvm",
-        "-cpu",
-        "host",
-        "-smp",
-        "4",
-        "-m",
-        "4G",
-        "-drive",
-        &format!(
-            "file={},format={},if=virtio",
-            disk.display(),
-            disk_format.as_str()
-        ),
-        "-drive",
-        &format!("if=pflash,format=raw,readonly=on,file={}", ovmf.display()),
-        "-drive",
-        &format!("if=pflash,format=raw,file={}", vars.display()),
-        "-boot",
-        "c",
-        "-netdev",
-        "user,id=net0",
-        "-device",
-        "virtio-net-pci,netdev=net0",
-    ]);
+    let (disk, vars) = create_spawn_overlay(root, base, overlay_dir)?;
+    let mut pstore_seen = Vec::new();
+    if cfg.firmware == crate::cli::Firmware::Uefi {
+        report_efi_pstore(
+            root,
+            cfg,
+            &base.vars,
+            &mut pstore_seen,
+            "left by an earlier boot",
+        );
+    }
+
+    // The installed overlay takes the first profile disk; the rest are blank scratch disks.
+    let Some((_, extra_disks)) = cfg.hw.disks.split_first() else {
+        bail!(
+            "hardware profile '{}' has no disks; disk checkpoints need at least one",
+            cfg.hw.name
+        );
+    };
+    let scratch = ScratchDisks::create(root, extra_disks, overlay_dir.join("scratch"))?;
+    let mut images = vec![(disk.clone(), "qcow2")];
+    images.extend(scratch.images.iter().map(|image| (image.clone(), "qcow2")));
+
+    let mut cmd = disk_qemu_command(
+        root,
+        cfg,
+        &images,
+        &vars,
+        window,
+        ssh.map(|(ssh_port, _)| *ssh_port),
+    )?;
+    if let Some(injection) = injection {
+        apply_boot_injection(&mut cmd, injection);
+    }
     if window.is_some() {
         eprintln!(
-            "Booting installed {} from install scenario run {} in window mode... (Ctrl-C to stop)",
-            cfg.pretty_name, runtime.run_id
+            "Booting installed {} from {} in window mode... (Ctrl-C to stop)",
+            cfg.pretty_name, base.label
         );
     } else {
         eprintln!(
-            "Booting installed {} from install scenario run {}... (Ctrl-A X to exit)",
-            cfg.pretty_name, runtime.run_id
+            "Booting installed {} from {}... (Ctrl-A X to exit)",
+            cfg.pretty_name, base.label
         );
     }
-    eprintln!("  disk: {}", disk.display());
-    eprintln!("  disk format: {}", disk_format);
+    eprintln!("  accel: {}", cfg.accel.as_str());
+    eprintln!("  firmware: {}", cfg.firmware.as_str());
+    eprintln!(
+        "  base: {} ({}, read-only)",
+        base.disk.display(),
+        base.format
+    );
+    eprintln!("  overlay: {} (discarded on exit)", disk.display());
     eprintln!("  ovmf vars: {}", vars.display());
-    if disk_format != "qcow2" {
-        eprintln!(
-            "  warning: install scenario produced non-qcow2 disk format '{}'; investigate qemu-img availability for strict qcow2 parity.",
-            disk_format
-        );
+    if let Some(injection) = injection {
+        eprintln!("  boot injection: {}", injection.path.display());
     }
-    apply_qemu_console_mode(&mut cmd, window);
+    if let (Some((ssh_port, key)), Some(vm)) = (ssh, &cfg.vm) {
+        eprintln!("  ssh: 127.0.0.1:{ssh_port}");
+        let known_hosts = overlay_dir.join("known_hosts");
+        fs::write(&known_hosts, "").context("creating known-hosts scratch file")?;
+        vm.publish_ssh(*ssh_port, key, &known_hosts)?;
+    }
+    apply_qemu_console_mode(&mut cmd, window, cfg.visual.is_some());
+    register_window_console(cfg, window)?;
     cmd.arg("-no-reboot");
 
     apply_qemu_runtime_env(&mut cmd, root, window)?;
-    if let Some(window_cfg) = window {
+    let run = if let Some(window_cfg) = window {
         run_window_mode_foreground(&mut cmd, window_cfg)
     } else {
-        run_checked(&mut cmd)
+        run_serial_console(&mut cmd, cfg.record.as_deref())
+    };
+    if cfg.firmware == crate::cli::Firmware::Uefi {
+        report_efi_pstore(
+            root,
+            cfg,
+            &vars,
+            &mut pstore_seen,
+            "written during this boot",
+        );
+    }
+    run?;
+
+    if let Some(name) = &spawn.persist {
+        let meta = SnapshotMeta {
+            name: name.clone(),
+            distro: cfg.distro_id.to_string(),
+            kind: "persisted".to_string(),
+            checkpoint: base.checkpoint.clone(),
+            source: format!("session on {}", base.label),
+            created_unix: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|dur| dur.as_secs())
+                .unwrap_or(0),
+        };
+        let saved = snapshots::write_snapshot(root, &meta, &disk, &vars)?;
+        eprintln!(
+            "Persisted session as snapshot '{}' ({})",
+            name,
+            saved.dir.display()
+        );
+    }
+    Ok(())
+}
+
+/// QEMU command booting `images` (the installed overlay first) with user networking, forwarding
+/// `ssh_port` to the guest's port 22 when set.
+fn disk_qemu_command(
+    root: &Path,
+    cfg: &BootConfig,
+    images: &[(PathBuf, &str)],
+    vars: &Path,
+    window: Option<&WindowConfig>,
+    ssh_port: Option<u16>,
+) -> Result<Command> {
+    let mut cmd = qemu_command_for_window(window, cfg.arch)?;
+    cmd.args(cfg.accel.qemu_args());
+    if let Some(vm) = &cfg.vm {
+        cmd.args(vm.qemu_args());
+    }
+    cmd.args(cfg.hw.machine_args(cfg.secure_boot.is_some(), cfg.arch));
+    cmd.args(cfg.hw.disk_args(images));
+    // BIOS and Secure Boot runs leave the snapshot vars copy untouched so `--persist` still
+    // records a complete snapshot.
+    apply_firmware(&mut cmd, root, cfg, Some(vars))?;
+    let netdev = match ssh_port {
+        Some(ssh_port) => format!("user,id=net0,hostfwd=tcp:127.0.0.1:{ssh_port}-:22"),
+        None => "user,id=net0".to_string(),
+    };
+    cmd.args(["-boot", "c", "-netdev", &netdev]);
+    cmd.args(cfg.hw.nic_args("net0"));
+    if cfg.pvpanic {
+        cmd.args(crash::pvpanic_args(cfg.arch));
+    }
+    if let Some(shares) = &cfg.shares {
+        cmd.args(shares.qemu_args(cfg.hw.memory_mib()));
+    }
+    Ok(cmd)
+}
+
+/// Copies efi-pstore crash records out of `vars` and lists them. Collection is best effort: a
+/// missing virt-fw-vars only warns.
+fn report_efi_pstore(
+    root: &Path,
+    cfg: &BootConfig,
+    vars: &Path,
+    seen: &mut Vec<String>,
+    origin: &str,
+) {
+    match crash::collect_efi_pstore(root, cfg.distro_id, vars, seen) {
+        Ok(files) if files.is_empty() => {}
+        Ok(files) => {
+            eprintln!("[crash] guest pstore records {origin}:");
+            for file in files {
+                eprintln!("  {}", file.display());
+            }
+        }
+        Err(err) => eprintln!("[crash] skipped pstore collection: {err:#}"),
     }
 }
 
@@ -884,6 +2277,174 @@ fn run_install_tests_in_dir(
     inject: Option<String>,
     inject_file: Option<PathBuf>,
 ) -> Result<()> {
+    let mut cmd = install_tests_command(root, args, inject, inject_file)?;
+    run_checked(&mut cmd).with_context(|| {
+        format!(
+            "Running install-tests scenarios in {}",
+            root.join("testing/install-tests").display()
+        )
+    })
+}
+
+/// Firmware override consumed by install-tests when it spawns scenario VMs (`uefi` or `bios`).
+const FIRMWARE_ENV: &str = "LEVITATE_SCENARIO_FIRMWARE";
+/// Guest architecture override consumed by install-tests (`x86_64` or `aarch64`).
+const ARCH_ENV: &str = "LEVITATE_SCENARIO_ARCH";
+/// Accelerator override consumed by install-tests (`auto`, `kvm` or `tcg`).
+const ACCEL_ENV: &str = "LEVITATE_SCENARIO_ACCEL";
+
+/// Runs install-tests like `run_install_tests_in_dir`, additionally writing JSON and JUnit
+/// reports under `.artifacts/scenarios/<distro>/reports`, retrying failures the flake policy allows up to `retries` times and
+/// recording every attempt in the scenario history. Returns the scenario key when the run failed
+/// in a quarantined scenario: the failure is reported but does not fail the command.
+fn run_install_tests_reported(
+    root: &Path,
+    args: &[&str],
+    inject: Option<String>,
+    inject_file: Option<PathBuf>,
+    vm: HarnessVm,
+    mode: &str,
+    distro_id: &str,
+    scenario_key: &str,
+    retries: u32,
+) -> Result<Option<String>> {
+    let policy = flake::FlakePolicy::load(root)?;
+    let artifact_key = history_artifact_key(root, distro_id);
+    let mut attempt = 1;
+    loop {
+        let (scenario_report, error, harness_stderr) = run_install_tests_attempt(
+            root,
+            args,
+            inject.clone(),
+            inject_file.clone(),
+            vm,
+            mode,
+            distro_id,
+            scenario_key,
+        )?;
+        let outcome = match &error {
+            None => flake::Outcome::Pass,
+            Some(_) => policy.classify_failure(&harness_stderr),
+        };
+        let entries = flake::entries_for_run(&scenario_report, &artifact_key, attempt, outcome);
+        if let Err(err) = flake::append(root, distro_id, &entries) {
+            eprintln!("[flake] failed to record scenario history: {err:#}");
+        }
+        let Some(error) = error else {
+            if attempt > 1 {
+                eprintln!("[flake] {scenario_key}: passed on attempt {attempt}");
+            }
+            return Ok(None);
+        };
+
+        // Quarantine and the flake log key on the rung that broke, not on where the run stopped.
+        let failed = scenario_report
+            .failed_scenario()
+            .unwrap_or(scenario_key)
+            .to_string();
+        eprintln!(
+            "[flake] {failed}: {} on attempt {attempt} of {}",
+            outcome.as_str(),
+            retries + 1
+        );
+        if attempt <= retries && policy.retries(outcome) {
+            attempt += 1;
+            continue;
+        }
+        if let Some(entry) = policy.quarantined(distro_id, &failed) {
+            eprintln!(
+                "[flake] {failed} is quarantined ({}); reporting without failing:\n  {error:#}",
+                entry.reason
+            );
+            return Ok(Some(failed));
+        }
+        return Err(error);
+    }
+}
+
+/// Key of the boot inputs for the scenario history: the live ISO's build inputs.
+fn history_artifact_key(root: &Path, distro_id: &str) -> String {
+    match resolve_iso_artifact_for_scenario(distro_id, ScenarioId::LiveBoot) {
+        Ok(Some(iso)) => artifact_input_key(root, distro_id, &iso.path),
+        _ => "unknown".to_string(),
+    }
+}
+
+/// One harness run with its report written; the error describes a failed run. Also returns the
+/// harness's stderr, which is what failures are classified from.
+fn run_install_tests_attempt(
+    root: &Path,
+    args: &[&str],
+    inject: Option<String>,
+    inject_file: Option<PathBuf>,
+    vm: HarnessVm,
+    mode: &str,
+    distro_id: &str,
+    scenario_key: &str,
+) -> Result<(report::ScenarioReport, Option<anyhow::Error>, String)> {
+    let mut cmd = install_tests_command(root, args, inject, inject_file)?;
+    vm.apply(&mut cmd);
+    let stem = report::report_stem(root, distro_id, scenario_key);
+    let log_path = stem.with_extension("log");
+    let run = report::run_captured(&mut cmd, &log_path, true).with_context(|| {
+        format!(
+            "Running install-tests scenarios in {}",
+            root.join("testing/install-tests").display()
+        )
+    })?;
+    let failure =
+        (!run.status.success()).then(|| format!("Command failed with status {}", run.status));
+    // A crash report in the harness output explains the failure better than the exit status.
+    let crash = failure.as_ref().and_then(|_| {
+        crash::CrashPatterns::new().detect(&fs::read_to_string(&log_path).unwrap_or_default())
+    });
+    let failure = match &crash {
+        Some(crash) => Some(format!("Detected {crash}")),
+        None => failure,
+    };
+
+    let mut scenario_report = report::ScenarioReport::from_run(
+        mode,
+        scenario_key,
+        distro_id,
+        vm.firmware.as_str(),
+        &run,
+        failure.clone(),
+        &log_path,
+    );
+    match scenario_report.write(&stem) {
+        Ok((json_path, junit_path)) => eprintln!(
+            "[report] {}: {} ({})",
+            scenario_key,
+            json_path.display(),
+            junit_path.display()
+        ),
+        Err(err) => eprintln!("[report] failed to write scenario report: {err:#}"),
+    }
+
+    let error = failure.map(|failure| {
+        let tail = scenario_report
+            .log_tail_path
+            .as_ref()
+            .map(|path| format!("\nLog tail: {}", path.display()))
+            .unwrap_or_default();
+        let trace = crash
+            .map(|crash| format!("\nKernel crash report:\n{}", crash.trace))
+            .unwrap_or_default();
+        anyhow::anyhow!(
+            "Running install-tests scenarios in {}: {failure}{tail}{trace}",
+            root.join("testing/install-tests").display()
+        )
+    });
+    Ok((scenario_report, error, run.stderr))
+}
+
+pub(super) fn install_tests_command(
+    root: &Path,
+    args: &[&str],
+    inject: Option<String>,
+    inject_file: Option<PathBuf>,
+) -> Result<Command> {
     let install_tests_dir = root.join("testing/install-tests");
     if !install_tests_dir.is_dir() {
         bail!(
@@ -905,82 +2466,235 @@ fn run_install_tests_in_dir(
     }
 
     crate::util::tools_env::apply_to_command(&mut cmd, root)?;
-    run_checked(&mut cmd).with_context(|| {
-        format!(
-            "Running install-tests scenarios in {}",
-            install_tests_dir.display()
-        )
-    })
+    Ok(cmd)
+}
+
+/// Boot source for live scenarios: a mastered ISO or the staged kernel/initramfs/rootfs.
+#[derive(Clone, Copy)]
+enum BootMedia<'a> {
+    Iso(&'a Path),
+    Direct(&'a DirectBootArtifacts),
+}
+
+impl BootMedia<'_> {
+    fn timeline_store<'r>(self, root: &'r Path, cfg: &'r BootConfig) -> TimelineStore<'r> {
+        let (media, primary) = match self {
+            BootMedia::Iso(iso_path) => ("iso", iso_path),
+            BootMedia::Direct(artifacts) => ("direct", artifacts.initrd.as_path()),
+        };
+        // Non-default firmware gets its own baseline; SeaBIOS, OVMF and Secure Boot OVMF spend
+        // very different time in firmware.
+        let media = match cfg.firmware {
+            crate::cli::Firmware::Uefi => media.to_string(),
+            firmware => format!("{media}-{}", firmware.as_str()),
+        };
+        // Other architectures boot different artifacts, mostly under TCG.
+        let media = match cfg.arch {
+            crate::cli::Arch::X86_64 => media,
+            arch => format!("{media}-{}", arch.as_str()),
+        };
+        TimelineStore {
+            root,
+            distro_id: cfg.distro_id,
+            input_key: artifact_input_key(root, cfg.distro_id, primary),
+            media,
+            accel: cfg.accel.as_str(),
+            rebase: cfg.timeline_rebase,
+        }
+    }
 }
 
 fn qemu_base_command(
     root: &Path,
-    iso_path: &Path,
+    cfg: &BootConfig,
+    media: BootMedia<'_>,
     injection: Option<&BootInjection>,
     ssh_port: Option<u16>,
     window: Option<&WindowConfig>,
 ) -> Result<Command> {
-    let ovmf = crate::util::repo::ovmf_path(root)?;
-    let mut cmd = qemu_command_for_window(window)?;
-    cmd.args([
-        "-enable-kvm",
-        "-cpu",
-        "host",
-        "-smp",
-        "4",
-        "-m",
-        "4G",
-        "-device",
-        "virtio-scsi-pci,id=scsi0",
-        "-device",
-        "scsi-cd,drive=cdrom0,bus=scsi0.0",
-        "-drive",
-        &format!(
-            "id=cdrom0,if=none,format=raw,readonly=on,file={}",
-            iso_path.display()
-        ),
-        "-drive",
-        &format!("if=pflash,format=raw,readonly=on,file={}", ovmf.display()),
-    ]);
-    apply_qemu_console_mode(&mut cmd, window);
-    cmd.arg("-no-reboot");
-    if let Some(injection) = injection {
-        let fw_cfg = format!(
-            "name=opt/levitate/boot-injection,file={}",
-            injection.path.display()
-        );
-        cmd.args(["-fw_cfg", &fw_cfg]);
-        if let Some(media_iso) = &injection.media_iso {
+    let mut cmd = qemu_command_for_window(window, cfg.arch)?;
+    cmd.args(cfg.accel.qemu_args());
+    if let Some(vm) = &cfg.vm {
+        cmd.args(vm.qemu_args());
+    }
+    cmd.args(cfg.hw.machine_args(cfg.secure_boot.is_some(), cfg.arch));
+    apply_firmware(&mut cmd, root, cfg, None)?;
+    match media {
+        BootMedia::Iso(iso_path) => {
             cmd.args([
                 "-device",
-                "virtio-scsi-pci,id=scsi2",
+                "virtio-scsi-pci,id=scsi0",
                 "-device",
-                "scsi-cd,drive=inject0,bus=scsi2.0",
+                "scsi-cd,drive=cdrom0,bus=scsi0.0",
+                "-drive",
+                &format!(
+                    "id=cdrom0,if=none,format=raw,readonly=on,file={}",
+                    iso_path.display()
+                ),
+            ]);
+        }
+        BootMedia::Direct(artifacts) => {
+            cmd.arg("-kernel")
+                .arg(&artifacts.kernel)
+                .arg("-initrd")
+                .arg(&artifacts.initrd)
+                .args(["-append", &artifacts.cmdline]);
+            cmd.args([
                 "-drive",
                 &format!(
-                    "id=inject0,if=none,format=raw,readonly=on,file={}",
-                    media_iso.display()
+                    "id=rootfs0,if=none,format=raw,readonly=on,file={}",
+                    artifacts.rootfs.display()
                 ),
+                "-device",
+                "virtio-blk-pci,drive=rootfs0,serial=rootfs",
             ]);
+            if let Some(overlay) = &artifacts.overlay {
+                cmd.args([
+                    "-drive",
+                    &format!(
+                        "id=overlay0,if=none,format=raw,readonly=on,file={}",
+                        overlay.display()
+                    ),
+                    "-device",
+                    "virtio-blk-pci,drive=overlay0,serial=overlay",
+                ]);
+            }
         }
     }
+    let scratch: Vec<(PathBuf, &str)> = cfg
+        .scratch_disks
+        .iter()
+        .map(|image| (image.clone(), "qcow2"))
+        .collect();
+    cmd.args(cfg.hw.disk_args(&scratch));
+    apply_qemu_console_mode(&mut cmd, window, cfg.visual.is_some());
+    register_window_console(cfg, window)?;
+    cmd.arg("-no-reboot");
+    if let Some(injection) = injection {
+        apply_boot_injection(&mut cmd, injection);
+    }
     if let Some(ssh_port) = ssh_port {
         cmd.args([
             "-netdev",
             &format!("user,id=net0,hostfwd=tcp:127.0.0.1:{ssh_port}-:22"),
-            "-device",
-            "virtio-net-pci,netdev=net0",
         ]);
     } else {
         cmd.args(["-netdev", "user,id=net0"]);
-        cmd.args(["-device", "virtio-net-pci,netdev=net0"]);
+    }
+    cmd.args(cfg.hw.nic_args("net0"));
+    if cfg.pvpanic {
+        cmd.args(crash::pvpanic_args(cfg.arch));
+    }
+    if let Some(shares) = &cfg.shares {
+        cmd.args(shares.qemu_args(cfg.hw.memory_mib()));
     }
 
     apply_qemu_runtime_env(&mut cmd, root, window)?;
     Ok(cmd)
 }
 
-fn apply_qemu_console_mode(cmd: &mut Command, window: Option<&WindowConfig>) {
+/// Attaches the boot firmware: OVMF pflash (plus `vars` when given), nothing for SeaBIOS, or the
+/// Secure Boot OVMF with the session's enrolled vars and a test ESP booted ahead of other media.
+fn apply_firmware(
+    cmd: &mut Command,
+    root: &Path,
+    cfg: &BootConfig,
+    vars: Option<&Path>,
+) -> Result<()> {
+    match cfg.firmware {
+        crate::cli::Firmware::Uefi if cfg.arch == crate::cli::Arch::Aarch64 => {
+            let code = crate::util::repo::aavmf_path(root)?;
+            if !code.is_file() {
+                bail!(
+                    "missing AAVMF firmware: {} (install the edk2 aarch64 firmware into the tools prefix)",
+                    code.display()
+                );
+            }
+            cmd.args([
+                "-drive",
+                &format!("if=pflash,format=raw,readonly=on,file={}", code.display()),
+            ]);
+            // The virt machine expects a vars flash; without a per-boot copy, the pristine
+            // template is attached with writes discarded at exit.
+            let vars = match vars {
+                Some(vars) => format!("if=pflash,format=raw,file={}", vars.display()),
+                None => format!(
+                    "if=pflash,format=raw,snapshot=on,file={}",
+                    crate::util::repo::aavmf_vars_path(root)?.display()
+                ),
+            };
+            cmd.args(["-drive", &vars]);
+        }
+        crate::cli::Firmware::Uefi => {
+            let ovmf = crate::util::repo::ovmf_path(root)?;
+            cmd.args([
+                "-drive",
+                &format!("if=pflash,format=raw,readonly=on,file={}", ovmf.display()),
+            ]);
+            if let Some(vars) = vars {
+                cmd.args([
+                    "-drive",
+                    &format!("if=pflash,format=raw,file={}", vars.display()),
+                ]);
+            }
+        }
+        // Without pflash QEMU falls back to its built-in SeaBIOS.
+        crate::cli::Firmware::Bios => {}
+        crate::cli::Firmware::Secboot => {
+            let Some(session) = &cfg.secure_boot else {
+                bail!("Secure Boot firmware selected without a prepared Secure Boot session");
+            };
+            cmd.args([
+                "-global",
+                "driver=cfi.pflash01,property=secure,value=on",
+                "-drive",
+                &format!(
+                    "if=pflash,format=raw,unit=0,readonly=on,file={}",
+                    session.code.display()
+                ),
+                "-drive",
+                &format!(
+                    "if=pflash,format=raw,unit=1,file={}",
+                    session.vars.display()
+                ),
+                "-drive",
+                &format!(
+                    "id=secboot-esp,if=none,format=raw,readonly=on,file=fat:{}",
+                    session.esp_dir.display()
+                ),
+                "-device",
+                "virtio-blk-pci,drive=secboot-esp,bootindex=0",
+            ]);
+        }
+    }
+    Ok(())
+}
+
+/// Exposes the injection payload to the guest both as fw_cfg `opt/levitate/boot-injection`
+/// and, when built, as a read-only injection media CD.
+fn apply_boot_injection(cmd: &mut Command, injection: &BootInjection) {
+    let fw_cfg = format!(
+        "name=opt/levitate/boot-injection,file={}",
+        injection.path.display()
+    );
+    cmd.args(["-fw_cfg", &fw_cfg]);
+    if let Some(media_iso) = &injection.media_iso {
+        cmd.args([
+            "-device",
+            "virtio-scsi-pci,id=scsi2",
+            "-device",
+            "scsi-cd,drive=inject0,bus=scsi2.0",
+            "-drive",
+            &format!(
+                "id=inject0,if=none,format=raw,readonly=on,file={}",
+                media_iso.display()
+            ),
+        ]);
+    }
+}
+
+/// `visual` keeps a display adapter on headless boots so QMP screendumps have a screen to read.
+fn apply_qemu_console_mode(cmd: &mut Command, window: Option<&WindowConfig>, visual: bool) {
     if let Some(window_cfg) = window {
         match window_cfg.mode {
             WindowMode::RemoteVnc => {
@@ -1004,7 +2718,17 @@ fn apply_qemu_console_mode(cmd: &mut Command, window: Option<&WindowConfig>) {
             }
         }
     } else {
-        cmd.args(["-vga", "none", "-nographic", "-serial", "mon:stdio"]);
+        let vga = if visual { visual::VISUAL_VGA } else { "none" };
+        cmd.args(["-vga", vga, "-nographic", "-serial", "mon:stdio"]);
+    }
+}
+
+fn register_window_console(cfg: &BootConfig, window: Option<&WindowConfig>) -> Result<()> {
+    match (&cfg.vm, window) {
+        (Some(vm), Some(window_cfg)) => {
+            vm.register_console(&window_cfg.serial_log, window_cfg.vnc_endpoint())
+        }
+        _ => Ok(()),
     }
 }
 
@@ -1082,7 +2806,10 @@ fn detect_window_mode() -> Result<WindowMode> {
     }
 }
 
-fn qemu_command_for_window(window: Option<&WindowConfig>) -> Result<Command> {
+fn qemu_command_for_window(
+    window: Option<&WindowConfig>,
+    arch: crate::cli::Arch,
+) -> Result<Command> {
     if let Some(window_cfg) = window {
         if window_cfg.mode == WindowMode::LocalGui {
             let qemu_bin = window_cfg.local_qemu_bin.as_ref().ok_or_else(|| {
@@ -1091,7 +2818,7 @@ fn qemu_command_for_window(window: Option<&WindowConfig>) -> Result<Command> {
             return Ok(Command::new(qemu_bin));
         }
     }
-    Ok(Command::new("qemu-system-x86_64"))
+    Ok(Command::new(format!("qemu-system-{}", arch.as_str())))
 }
 
 fn apply_qemu_runtime_env(
@@ -1247,8 +2974,17 @@ fn detect_ssh_server_ipv4() -> Option<Ipv4Addr> {
     Some(parsed)
 }
 
-fn allocate_local_port(host: Ipv4Addr, start: u16, end: u16) -> Result<u16> {
+/// Returns the first bindable port in `start..=end` that is not already handed out in `exclude`.
+pub(super) fn allocate_local_port(
+    host: Ipv4Addr,
+    start: u16,
+    end: u16,
+    exclude: &[u16],
+) -> Result<u16> {
     for port in start..=end {
+        if exclude.contains(&port) {
+            continue;
+        }
         if let Ok(listener) = TcpListener::bind((host, port)) {
             drop(listener);
             return Ok(port);
@@ -1257,7 +2993,8 @@ fn allocate_local_port(host: Ipv4Addr, start: u16, end: u16) -> Result<u16> {
     bail!("No free local TCP port on {host} in range {start}..={end}")
 }
 
-fn create_boot_injection_iso(payload_path: &Path) -> Result<PathBuf> {
+fn create_boot_injection_iso(injection: &BootInjection) -> Result<PathBuf> {
+    let payload_path = &injection.path;
     let ts = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .context("system clock before UNIX_EPOCH")?
@@ -1321,29 +3058,86 @@ fn create_boot_injection_iso(payload_path: &Path) -> Result<PathBuf> {
     )
 }
 
-fn spawn_qemu_with_log(cmd: &mut Command, log_path: &Path, allow_stdin: bool) -> Result<Child> {
+/// Spawns QEMU with its serial console (stdout) and stderr in `log_path`. With `record`, stdout
+/// is teed through a thread that also writes it to an asciicast at `record`.
+fn spawn_qemu_with_log(
+    cmd: &mut Command,
+    log_path: &Path,
+    allow_stdin: bool,
+    record: Option<&Path>,
+) -> Result<Child> {
     let log_out = File::create(log_path)
         .with_context(|| format!("creating QEMU log file '{}'", log_path.display()))?;
     let log_err = log_out
         .try_clone()
         .with_context(|| format!("duplicating QEMU log file '{}'", log_path.display()))?;
-    cmd.stdout(Stdio::from(log_out));
+    let recorder = match record {
+        Some(path) => {
+            let (columns, rows) = crate::util::asciicast::terminal_size();
+            let cast = CastWriter::create(path, columns, rows, SERIAL_CAST_TITLE)?;
+            eprintln!("  recording serial console: {}", path.display());
+            cmd.stdout(Stdio::piped());
+            Some((cast, log_out))
+        }
+        None => {
+            cmd.stdout(Stdio::from(log_out));
+            None
+        }
+    };
     cmd.stderr(Stdio::from(log_err));
     if allow_stdin {
         cmd.stdin(Stdio::piped());
     } else {
         cmd.stdin(Stdio::null());
     }
-    let child = cmd.spawn().context("Spawning QEMU for SSH boot")?;
+    let mut child = cmd.spawn().context("Spawning QEMU for SSH boot")?;
+    if let (Some((mut cast, log_out)), Some(stdout)) = (recorder, child.stdout.take()) {
+        // Detached: the thread ends at EOF when QEMU exits, and every event is written as it
+        // arrives, so the cast is complete without joining.
+        std::thread::spawn(move || {
+            if let Err(err) = crate::util::asciicast::tee(stdout, log_out, &mut cast) {
+                eprintln!("warning: serial recording stopped: {err:#}");
+            }
+        });
+    }
     Ok(child)
 }
 
+/// Runs QEMU with the serial console on the terminal. With `record`, stdout is forwarded through
+/// an asciicast recorder; QEMU still owns the terminal's stdin and raw mode.
+fn run_serial_console(cmd: &mut Command, record: Option<&Path>) -> Result<()> {
+    let Some(path) = record else {
+        return run_checked(cmd);
+    };
+    let (columns, rows) = crate::util::asciicast::terminal_size();
+    let mut cast = CastWriter::create(path, columns, rows, SERIAL_CAST_TITLE)?;
+    cmd.stdout(Stdio::piped());
+    let mut child = cmd.spawn().with_context(|| "Spawning command")?;
+    let stdout = child.stdout.take().expect("QEMU stdout is piped");
+    let forwarded = crate::util::asciicast::tee(stdout, std::io::stdout(), &mut cast);
+    let status = child.wait().context("waiting for QEMU")?;
+    forwarded?;
+    eprintln!("Serial console recorded to {}", path.display());
+    if !status.success() {
+        bail!("Command failed with status {status}");
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::sync::atomic::{AtomicU64, Ordering};
+    use crate::tasks::testing::test_temp_dir;
 
-    static TEST_COUNTER: AtomicU64 = AtomicU64::new(0);
+    #[test]
+    fn arch_iso_path_renames_the_x86_64_iso() {
+        let x86 = Path::new("/out/levitate/levitateos-x86_64.iso");
+        assert_eq!(
+            arch_iso_path(x86, crate::cli::Arch::Aarch64).unwrap(),
+            Path::new("/out/levitate/levitateos-aarch64.iso")
+        );
+        assert!(arch_iso_path(Path::new("/out/live.iso"), crate::cli::Arch::Aarch64).is_err());
+    }
 
     #[test]
     fn detect_disk_image_format_recognizes_qcow2_magic() {
@@ -1357,15 +3151,6 @@ mod tests {
 
         let _ = fs::remove_dir_all(root);
     }
-
-    fn test_temp_dir(tag: &str) -> PathBuf {
-        let id = TEST_COUNTER.fetch_add(1, Ordering::Relaxed);
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .expect("clock")
-            .as_nanos();
-        std::env::temp_dir().join(format!("levitate-xtask-{tag}-{nanos}-{id}"))
-    }
 }
 
 fn collect_guest_ssh_debug(child: &mut Child) -> Result<()> {
@@ -1396,36 +3181,6 @@ fn collect_guest_ssh_debug(child: &mut Child) -> Result<()> {
     Ok(())
 }
 
-fn detect_boot_regression(log_path: &Path) -> Result<Option<String>> {
-    if !log_path.is_file() {
-        return Ok(None);
-    }
-
-    let content = fs::read_to_string(log_path).unwrap_or_default();
-    if content.is_empty() {
-        return Ok(None);
-    }
-
-    for line in content.lines() {
-        let lower = line.to_lowercase();
-        if lower.contains("could not set up host forwarding rule") {
-            return Ok(Some(format!("hostfwd setup failed: {line}")));
-        }
-        if lower.contains("warning") && lower.contains("locale") {
-            return Ok(Some(format!("locale warning: {line}")));
-        }
-        if lower.contains("failed to start sshd.service")
-            || lower.contains("sshd.service: failed with result")
-            || lower.contains("start request repeated too quickly")
-            || lower.contains("failed to start ssh.service")
-        {
-            return Ok(Some(format!("sshd failure: {line}")));
-        }
-    }
-
-    Ok(None)
-}
-
 fn ensure_ssh_port_available(ssh_port: u16) -> Result<()> {
     match TcpListener::bind(("127.0.0.1", ssh_port)) {
         Ok(listener) => {
@@ -0,0 +1,364 @@
+use anyhow::{Context, Result, bail};
+use distro_contract::load_variant_contract_bundle_for_distro_from;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+/// Owner GUID recorded for the harness test keys in PK/KEK/db.
+const TEST_KEY_OWNER_GUID: &str = "4c657669-7461-7465-5465-73744b657973";
+const KEY_NAMES: &[(&str, &str)] = &[
+    ("PK", "LevitateOS scenario test PK"),
+    ("KEK", "LevitateOS scenario test KEK"),
+    ("db", "LevitateOS scenario test db"),
+];
+const VARS_TEMPLATE_FILE: &str = "OVMF_VARS.secboot.fd";
+const ESP_LOADER_PATH: &str = "EFI/BOOT/BOOTX64.EFI";
+
+/// How the UKI on the scenario ESP is prepared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum UkiVariant {
+    /// Signed with the test db key; Secure Boot must accept it.
+    Signed,
+    /// As built; Secure Boot must refuse it.
+    Unsigned,
+    /// Signed, then modified after signing; Secure Boot must refuse it.
+    Tampered,
+}
+
+impl UkiVariant {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Signed => "signed",
+            Self::Unsigned => "unsigned",
+            Self::Tampered => "tampered",
+        }
+    }
+}
+
+/// Local test PK/KEK/db plus an OVMF_VARS template with them enrolled, under
+/// `.artifacts/scenarios/secureboot`. Generated once and reused.
+pub(crate) struct SecureBootKit {
+    dir: PathBuf,
+    vars_template: PathBuf,
+}
+
+impl SecureBootKit {
+    pub(crate) fn ensure(root: &Path) -> Result<Self> {
+        let dir = root.join(".artifacts/scenarios/secureboot");
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("creating Secure Boot key dir '{}'", dir.display()))?;
+        for (name, subject) in KEY_NAMES {
+            if !dir.join(format!("{name}.crt")).is_file()
+                || !dir.join(format!("{name}.key")).is_file()
+            {
+                generate_key(root, &dir, name, subject)?;
+            }
+        }
+
+        let kit = Self {
+            vars_template: dir.join(VARS_TEMPLATE_FILE),
+            dir,
+        };
+        // Re-enroll when a key or the stock OVMF_VARS the template was built from changed.
+        let mut inputs: Vec<PathBuf> = KEY_NAMES.iter().map(|(name, _)| kit.cert(name)).collect();
+        inputs.push(crate::util::repo::ovmf_vars_path(root)?);
+        if template_stale(&kit.vars_template, &inputs) {
+            kit.enroll(root)?;
+        }
+        Ok(kit)
+    }
+
+    fn cert(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{name}.crt"))
+    }
+
+    /// Writes the vars template: the stock OVMF_VARS with the test keys enrolled and Secure Boot on.
+    fn enroll(&self, root: &Path) -> Result<()> {
+        let input = crate::util::repo::ovmf_vars_path(root)?;
+        let partial = self.vars_template.with_extension("fd.partial");
+        let mut cmd = Command::new("virt-fw-vars");
+        cmd.arg("--input")
+            .arg(&input)
+            .arg("--output")
+            .arg(&partial)
+            .args(["--set-pk", TEST_KEY_OWNER_GUID])
+            .arg(self.cert("PK"))
+            .args(["--add-kek", TEST_KEY_OWNER_GUID])
+            .arg(self.cert("KEK"))
+            .args(["--add-db", TEST_KEY_OWNER_GUID])
+            .arg(self.cert("db"))
+            .arg("--secure-boot");
+        run_tool(root, &mut cmd, "virt-fw-vars (python3-virt-firmware)")?;
+        fs::rename(&partial, &self.vars_template).with_context(|| {
+            format!(
+                "publishing Secure Boot vars template '{}'",
+                self.vars_template.display()
+            )
+        })?;
+        eprintln!(
+            "[secureboot] enrolled test PK/KEK/db into {}",
+            self.vars_template.display()
+        );
+        Ok(())
+    }
+
+    fn sign(&self, root: &Path, input: &Path, output: &Path) -> Result<()> {
+        let mut cmd = Command::new("sbsign");
+        cmd.arg("--key")
+            .arg(self.dir.join("db.key"))
+            .arg("--cert")
+            .arg(self.cert("db"))
+            .arg("--output")
+            .arg(output)
+            .arg(input);
+        run_tool(root, &mut cmd, "sbsign (sbsigntools)")
+    }
+}
+
+/// Per-boot Secure Boot state: secboot OVMF code, a writable vars copy, and an ESP directory
+/// holding the prepared UKI as the removable-media loader. Removed on drop.
+pub(crate) struct SecureBootSession {
+    pub(crate) code: PathBuf,
+    pub(crate) vars: PathBuf,
+    pub(crate) esp_dir: PathBuf,
+    dir: PathBuf,
+}
+
+impl SecureBootSession {
+    pub(crate) fn prepare(
+        root: &Path,
+        uki: &Path,
+        variant: UkiVariant,
+        dir: PathBuf,
+    ) -> Result<Self> {
+        let kit = SecureBootKit::ensure(root)?;
+        let code = crate::util::repo::ovmf_secboot_path(root)?;
+        if !code.is_file() {
+            bail!(
+                "missing Secure Boot OVMF build: {} (install the edk2 secboot firmware into the tools prefix)",
+                code.display()
+            );
+        }
+        let esp_dir = dir.join("esp");
+        let loader = esp_dir.join(ESP_LOADER_PATH);
+        fs::create_dir_all(loader.parent().expect("loader has a parent"))
+            .with_context(|| format!("creating ESP staging '{}'", esp_dir.display()))?;
+        let session = Self {
+            code,
+            vars: dir.join("OVMF_VARS.fd"),
+            esp_dir,
+            dir,
+        };
+        fs::copy(&kit.vars_template, &session.vars).with_context(|| {
+            format!(
+                "copying Secure Boot vars template '{}'",
+                kit.vars_template.display()
+            )
+        })?;
+
+        match variant {
+            UkiVariant::Signed => kit.sign(root, uki, &loader)?,
+            UkiVariant::Unsigned => {
+                fs::copy(uki, &loader)
+                    .with_context(|| format!("copying UKI '{}'", uki.display()))?;
+            }
+            UkiVariant::Tampered => {
+                kit.sign(root, uki, &loader)?;
+                tamper(&loader)?;
+            }
+        }
+        eprintln!(
+            "[secureboot] {} UKI {} staged as {ESP_LOADER_PATH}",
+            variant.as_str(),
+            uki.display()
+        );
+        Ok(session)
+    }
+}
+
+impl Drop for SecureBootSession {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Finds the staged primary UKI (first ring1 `output_names` entry) of the live or installed
+/// transform.
+pub(crate) fn staged_uki(root: &Path, distro_id: &str, installed: bool) -> Result<PathBuf> {
+    let bundle = load_variant_contract_bundle_for_distro_from(root, distro_id)
+        .with_context(|| format!("loading canonical variant contract for '{}'", distro_id))?;
+    let transforms = &bundle.contract.ring1_transforms;
+    let (key, transform) = if installed {
+        ("installed_uki", &transforms.installed_uki)
+    } else {
+        ("live_uki", &transforms.live_uki)
+    };
+    let Some(file_name) = transform.output_names.first() else {
+        bail!("ring1_transforms.{key} for '{distro_id}' declares no output_names");
+    };
+    find_staged_uki(root, distro_id, file_name)
+}
+
+/// Finds the newest staged UKI named `file_name` under `.artifacts/out/<distro>`.
+fn find_staged_uki(root: &Path, distro_id: &str, file_name: &str) -> Result<PathBuf> {
+    let out_dir = root.join(".artifacts/out").join(distro_id);
+    let mut found: Option<(SystemTime, PathBuf)> = None;
+    let mut pending = vec![out_dir.clone()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() && !path.is_symlink() {
+                pending.push(path);
+            } else if path.file_name().is_some_and(|name| name == file_name) {
+                let mtime = modified(&path).unwrap_or(SystemTime::UNIX_EPOCH);
+                if found.as_ref().is_none_or(|(newest, _)| mtime > *newest) {
+                    found = Some((mtime, path));
+                }
+            }
+        }
+    }
+    match found {
+        Some((_, path)) => Ok(path),
+        None => bail!(
+            "no staged UKI '{file_name}' under '{}'; build the release products first",
+            out_dir.display()
+        ),
+    }
+}
+
+fn generate_key(root: &Path, dir: &Path, name: &str, subject: &str) -> Result<()> {
+    let mut cmd = Command::new("openssl");
+    cmd.args([
+        "req",
+        "-new",
+        "-x509",
+        "-newkey",
+        "rsa:2048",
+        "-nodes",
+        "-sha256",
+        "-days",
+        "3650",
+        "-subj",
+        &format!("/CN={subject}/"),
+        "-keyout",
+    ])
+    .arg(dir.join(format!("{name}.key")))
+    .arg("-out")
+    .arg(dir.join(format!("{name}.crt")));
+    run_tool(root, &mut cmd, "openssl")?;
+    eprintln!("[secureboot] generated test {name} in {}", dir.display());
+    Ok(())
+}
+
+/// Flips one byte in the middle of the signed image so its Authenticode digest no longer matches.
+fn tamper(path: &Path) -> Result<()> {
+    let mut bytes =
+        fs::read(path).with_context(|| format!("reading signed UKI '{}'", path.display()))?;
+    if bytes.is_empty() {
+        bail!("signed UKI '{}' is empty", path.display());
+    }
+    let idx = bytes.len() / 2;
+    bytes[idx] ^= 0xff;
+    fs::write(path, bytes).with_context(|| format!("writing tampered UKI '{}'", path.display()))
+}
+
+fn run_tool(root: &Path, cmd: &mut Command, tool: &str) -> Result<()> {
+    crate::util::tools_env::apply_to_command(cmd, root)?;
+    let status = cmd
+        .status()
+        .with_context(|| format!("running {tool}; is it installed?"))?;
+    if !status.success() {
+        bail!("{tool} failed with status {status}");
+    }
+    Ok(())
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Whether `template` is missing or older than any of the `inputs` it was generated from.
+fn template_stale(template: &Path, inputs: &[PathBuf]) -> bool {
+    let Some(built) = modified(template) else {
+        return true;
+    };
+    inputs
+        .iter()
+        .filter_map(|input| modified(input))
+        .any(|changed| changed > built)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn write_at(path: &Path, contents: &[u8], mtime: SystemTime) {
+        fs::create_dir_all(path.parent().expect("parent")).expect("create parent");
+        fs::write(path, contents).expect("write file");
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(mtime))
+            .expect("set mtime");
+    }
+
+    #[test]
+    fn tamper_flips_the_middle_byte() {
+        let dir = crate::tasks::testing::test_temp_dir("secboot-tamper");
+        let uki = dir.join("BOOTX64.EFI");
+        let empty = dir.join("empty.efi");
+        write_at(&uki, &[1, 2, 3, 4], SystemTime::now());
+        write_at(&empty, &[], SystemTime::now());
+        tamper(&uki).expect("tamper");
+        let tampered = fs::read(&uki).expect("read tampered");
+        let empty_err = tamper(&empty).unwrap_err();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(tampered, [1, 2, 3 ^ 0xff, 4]);
+        assert!(format!("{empty_err:#}").contains("is empty"));
+    }
+
+    #[test]
+    fn find_staged_uki_picks_the_newest_match() {
+        let root = crate::tasks::testing::test_temp_dir("secboot-uki");
+        let out = root.join(".artifacts/out/levitate");
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000);
+        let new = SystemTime::UNIX_EPOCH + Duration::from_secs(2_000);
+        write_at(&out.join("a/levitate.efi"), b"old", old);
+        write_at(&out.join("b/nested/levitate.efi"), b"new", new);
+        write_at(&out.join("c/other.efi"), b"other", new);
+        let found = find_staged_uki(&root, "levitate", "levitate.efi").expect("find UKI");
+        let missing = find_staged_uki(&root, "acorn", "acorn.efi");
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(found, out.join("b/nested/levitate.efi"));
+        assert!(format!("{:#}", missing.unwrap_err()).contains("no staged UKI 'acorn.efi'"));
+    }
+
+    #[test]
+    fn vars_template_is_stale_when_keys_or_stock_vars_are_newer() {
+        let dir = crate::tasks::testing::test_temp_dir("secboot-enroll");
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+        let template = dir.join(VARS_TEMPLATE_FILE);
+        let cert = dir.join("db.crt");
+        let stock_vars = dir.join("OVMF_VARS.fd");
+        let inputs = [cert.clone(), stock_vars.clone()];
+        let missing = template_stale(&template, &inputs);
+        write_at(&cert, b"cert", at(1_000));
+        write_at(&stock_vars, b"vars", at(1_000));
+        write_at(&template, b"template", at(2_000));
+        let fresh = template_stale(&template, &inputs);
+        write_at(&stock_vars, b"updated vars", at(3_000));
+        let vars_changed = template_stale(&template, &inputs);
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(missing);
+        assert!(!fresh);
+        assert!(vars_changed);
+    }
+}
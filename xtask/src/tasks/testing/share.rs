@@ -0,0 +1,319 @@
+use anyhow::{Context, Result, bail};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// virtio-9p limits mount tags to 31 bytes; virtiofs allows more, but one limit keeps `--share`
+/// portable between backends.
+const MAX_TAG_LEN: usize = 31;
+/// Guest mount point prefix; each share is mounted at `<prefix>/<tag>`.
+const GUEST_MOUNT_PREFIX: &str = "/mnt";
+/// Boot-injection payload key prefix of the mount requests (`<prefix><n>`).
+const PAYLOAD_KEY_PREFIX: &str = "LEVITATE_SHARE_";
+/// Overrides the backend choice: `auto` (default), `virtiofs` or `9p`.
+const BACKEND_ENV: &str = "LEVITATE_SCENARIO_SHARE_BACKEND";
+/// virtiofsd is not on PATH on Fedora-family hosts.
+const VIRTIOFSD_FALLBACK_PATHS: &[&str] = &["/usr/libexec/virtiofsd", "/usr/lib/qemu/virtiofsd"];
+const VIRTIOFSD_SOCKET_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One `--share HOST_DIR:TAG[:ro]` export.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) struct HostShare {
+    pub(super) dir: PathBuf,
+    pub(super) tag: String,
+    pub(super) readonly: bool,
+}
+
+impl HostShare {
+    pub(super) fn parse(raw: &str) -> Result<Self> {
+        let (rest, readonly) = match raw.rsplit_once(':') {
+            Some((rest, "ro")) => (rest, true),
+            Some((rest, "rw")) => (rest, false),
+            _ => (raw, false),
+        };
+        let Some((dir, tag)) = rest.rsplit_once(':') else {
+            bail!("invalid --share '{raw}'; expected HOST_DIR:TAG[:ro]");
+        };
+        if tag.is_empty()
+            || tag.len() > MAX_TAG_LEN
+            || !tag
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || "-_.".contains(ch))
+        {
+            bail!(
+                "invalid --share tag '{tag}'; use 1-{MAX_TAG_LEN} characters from [A-Za-z0-9._-]"
+            );
+        }
+        let dir = fs::canonicalize(dir)
+            .with_context(|| format!("resolving --share directory '{dir}'"))?;
+        if !dir.is_dir() {
+            bail!("--share source is not a directory: {}", dir.display());
+        }
+        Ok(Self {
+            dir,
+            tag: tag.to_string(),
+            readonly,
+        })
+    }
+
+    fn guest_mount_point(&self) -> String {
+        format!("{GUEST_MOUNT_PREFIX}/{}", self.tag)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ShareBackend {
+    Virtiofs,
+    NineP,
+}
+
+impl ShareBackend {
+    /// Guest filesystem type named in the mount request.
+    fn fstype(self) -> &'static str {
+        match self {
+            Self::Virtiofs => "virtiofs",
+            Self::NineP => "9p",
+        }
+    }
+
+    /// Backend-specific guest mount options.
+    fn mount_options(self) -> &'static str {
+        match self {
+            Self::Virtiofs => "",
+            Self::NineP => "trans=virtio,version=9p2000.L,",
+        }
+    }
+}
+
+/// Host side of the `--share` exports for one boot: the chosen backend plus any virtiofsd
+/// daemons, which are stopped on drop.
+pub(super) struct ShareSession {
+    shares: Vec<HostShare>,
+    backend: ShareBackend,
+    dir: PathBuf,
+    daemons: Vec<Child>,
+}
+
+impl ShareSession {
+    /// Starts one virtiofsd per share when virtiofsd is available, else falls back to 9p.
+    pub(super) fn start(shares: Vec<HostShare>, dir: PathBuf) -> Result<Self> {
+        let virtiofsd = match std::env::var(BACKEND_ENV).as_deref().unwrap_or("auto") {
+            "9p" => None,
+            "virtiofs" => Some(find_virtiofsd().with_context(|| {
+                format!("{BACKEND_ENV}=virtiofs requested but virtiofsd was not found")
+            })?),
+            "auto" | "" => find_virtiofsd(),
+            other => {
+                bail!("unsupported {BACKEND_ENV} value '{other}'; expected auto, virtiofs or 9p")
+            }
+        };
+        let mut session = Self {
+            shares,
+            backend: if virtiofsd.is_some() {
+                ShareBackend::Virtiofs
+            } else {
+                ShareBackend::NineP
+            },
+            dir,
+            daemons: Vec::new(),
+        };
+        let Some(virtiofsd) = virtiofsd else {
+            return Ok(session);
+        };
+
+        fs::create_dir_all(&session.dir).with_context(|| {
+            format!("creating virtiofsd socket dir '{}'", session.dir.display())
+        })?;
+        for idx in 0..session.shares.len() {
+            let share = &session.shares[idx];
+            let socket = session.socket_path(idx);
+            let mut cmd = Command::new(&virtiofsd);
+            cmd.arg(format!("--socket-path={}", socket.display()))
+                .arg(format!("--shared-dir={}", share.dir.display()))
+                .arg("--cache=auto")
+                .stdout(Stdio::null());
+            if share.readonly {
+                cmd.arg("--readonly");
+            }
+            // The namespace sandbox needs root or unprivileged user namespaces.
+            // SAFETY: getuid has no preconditions and cannot fail.
+            if unsafe { libc::getuid() } != 0 {
+                cmd.arg("--sandbox=none");
+            }
+            let child = cmd
+                .spawn()
+                .with_context(|| format!("starting {}", virtiofsd.display()))?;
+            session.daemons.push(child);
+            wait_for_socket(&socket, session.daemons.last_mut().expect("just pushed"))?;
+        }
+        Ok(session)
+    }
+
+    fn socket_path(&self, idx: usize) -> PathBuf {
+        self.dir.join(format!("share{idx}.sock"))
+    }
+
+    /// QEMU arguments for the exports. virtiofs needs guest RAM in a shared memfd, so it also
+    /// sets up the memory backend for `memory_mib`.
+    pub(super) fn qemu_args(&self, memory_mib: u64) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.backend == ShareBackend::Virtiofs {
+            args.extend([
+                "-object".to_string(),
+                format!("memory-backend-memfd,id=sharemem,size={memory_mib}M,share=on"),
+                "-numa".to_string(),
+                "node,memdev=sharemem".to_string(),
+            ]);
+        }
+        for (idx, share) in self.shares.iter().enumerate() {
+            match self.backend {
+                ShareBackend::Virtiofs => args.extend([
+                    "-chardev".to_string(),
+                    format!(
+                        "socket,id=share{idx},path={}",
+                        self.socket_path(idx).display()
+                    ),
+                    "-device".to_string(),
+                    format!("vhost-user-fs-pci,chardev=share{idx},tag={}", share.tag),
+                ]),
+                ShareBackend::NineP => args.extend([
+                    "-virtfs".to_string(),
+                    format!(
+                        "local,path={},mount_tag={},security_model=none,id=share{idx}{}",
+                        share.dir.display(),
+                        share.tag,
+                        if share.readonly { ",readonly=on" } else { "" }
+                    ),
+                ]),
+            }
+        }
+        args
+    }
+
+    /// Boot-injection payload lines asking the live system to mount each share:
+    /// `LEVITATE_SHARE_<n>=<virtiofs|9p>:<tag>:<ro|rw>:<mount point>`.
+    pub(super) fn payload_lines(&self) -> Vec<String> {
+        self.shares
+            .iter()
+            .enumerate()
+            .map(|(idx, share)| {
+                format!(
+                    "{PAYLOAD_KEY_PREFIX}{idx}={}:{}:{}:{}",
+                    self.backend.fstype(),
+                    share.tag,
+                    if share.readonly { "ro" } else { "rw" },
+                    share.guest_mount_point()
+                )
+            })
+            .collect()
+    }
+
+    /// Guest command that mounts `share` by hand, for images that do not act on the mount
+    /// requests.
+    fn mount_command(&self, share: &HostShare) -> String {
+        let mount_point = share.guest_mount_point();
+        format!(
+            "mkdir -p {mount_point} && mount -t {} -o {}{} {} {mount_point}",
+            self.backend.fstype(),
+            self.backend.mount_options(),
+            if share.readonly { "ro" } else { "rw" },
+            share.tag
+        )
+    }
+
+    pub(super) fn print_summary(&self) {
+        for share in &self.shares {
+            eprintln!(
+                "  share: {} -> {} ({}, tag {}); to mount it by hand:\n    {}",
+                share.dir.display(),
+                share.guest_mount_point(),
+                self.backend.fstype(),
+                share.tag,
+                self.mount_command(share)
+            );
+        }
+    }
+}
+
+impl Drop for ShareSession {
+    fn drop(&mut self) {
+        for daemon in &mut self.daemons {
+            let _ = daemon.kill();
+            let _ = daemon.wait();
+        }
+        if !self.daemons.is_empty() {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+}
+
+fn find_virtiofsd() -> Option<PathBuf> {
+    which::which("virtiofsd").ok().or_else(|| {
+        VIRTIOFSD_FALLBACK_PATHS
+            .iter()
+            .map(PathBuf::from)
+            .find(|path| path.is_file())
+    })
+}
+
+fn wait_for_socket(socket: &Path, daemon: &mut Child) -> Result<()> {
+    let deadline = Instant::now() + VIRTIOFSD_SOCKET_TIMEOUT;
+    while !socket.exists() {
+        if let Some(status) = daemon.try_wait()? {
+            bail!("virtiofsd exited with status {status} before creating its socket");
+        }
+        if Instant::now() > deadline {
+            bail!(
+                "virtiofsd did not create '{}' within {}s",
+                socket.display(),
+                VIRTIOFSD_SOCKET_TIMEOUT.as_secs()
+            );
+        }
+        sleep(Duration::from_millis(50));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_share_specs() {
+        let dir = std::env::temp_dir();
+        let canonical = fs::canonicalize(&dir).unwrap();
+        let share = HostShare::parse(&format!("{}:tools:ro", dir.display())).unwrap();
+        assert_eq!(
+            share,
+            HostShare {
+                dir: canonical.clone(),
+                tag: "tools".to_string(),
+                readonly: true,
+            }
+        );
+        assert!(
+            !HostShare::parse(&format!("{}:tools", dir.display()))
+                .unwrap()
+                .readonly
+        );
+        assert!(HostShare::parse(&format!("{}:bad tag", dir.display())).is_err());
+        assert!(HostShare::parse("tools").is_err());
+
+        let session = ShareSession {
+            shares: vec![share],
+            backend: ShareBackend::NineP,
+            dir: PathBuf::new(),
+            daemons: Vec::new(),
+        };
+        assert_eq!(
+            session.payload_lines(),
+            vec!["LEVITATE_SHARE_0=9p:tools:ro:/mnt/tools".to_string()]
+        );
+        assert_eq!(
+            session.mount_command(&session.shares[0]),
+            "mkdir -p /mnt/tools && mount -t 9p -o trans=virtio,version=9p2000.L,ro tools /mnt/tools"
+        );
+    }
+}
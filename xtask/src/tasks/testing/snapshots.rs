@@ -0,0 +1,370 @@
+use anyhow::{Context, Result, bail};
+use install_tests::scenarios::resolve_latest_install_runtime;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Disk-backed checkpoints (03Install onward) whose passed state is recorded as a spawn point.
+const DISK_CHECKPOINTS: &[(&str, &str)] = &[
+    ("install", "03Install"),
+    ("installed-boot", "04LoginGate"),
+    ("automated-login", "05Harness"),
+    ("runtime", "06Runtime"),
+    ("update", "07Update"),
+    ("package", "08Package"),
+];
+
+const DISK_FILE: &str = "disk.qcow2";
+const VARS_FILE: &str = "OVMF_VARS.fd";
+const META_FILE: &str = "snapshot.json";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct SnapshotMeta {
+    pub(crate) name: String,
+    pub(crate) distro: String,
+    /// `checkpoint` for bases recorded by passing scenarios, `persisted` for `boot --persist`.
+    pub(crate) kind: String,
+    pub(crate) checkpoint: Option<String>,
+    pub(crate) source: String,
+    pub(crate) created_unix: u64,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct Snapshot {
+    pub(crate) dir: PathBuf,
+    pub(crate) meta: SnapshotMeta,
+}
+
+impl Snapshot {
+    pub(crate) fn disk(&self) -> PathBuf {
+        self.dir.join(DISK_FILE)
+    }
+
+    pub(crate) fn vars(&self) -> PathBuf {
+        self.dir.join(VARS_FILE)
+    }
+}
+
+/// Identifies the install runtime disk state so cached (skipped) scenario runs are not re-recorded.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct RuntimeFingerprint {
+    run_id: String,
+    disk_mtime: Option<SystemTime>,
+}
+
+pub(crate) fn checkpoint_label(scenario_key: &str) -> Option<&'static str> {
+    DISK_CHECKPOINTS
+        .iter()
+        .find(|(key, _)| *key == scenario_key)
+        .map(|(_, label)| *label)
+}
+
+pub(crate) fn store_dir(root: &Path, distro_id: &str) -> PathBuf {
+    root.join(".artifacts/scenarios")
+        .join(distro_id)
+        .join("snapshots")
+}
+
+pub(crate) fn runtime_fingerprint(distro_id: &str) -> Option<RuntimeFingerprint> {
+    let runtime = resolve_latest_install_runtime(distro_id).ok()?;
+    let disk_mtime = fs::metadata(&runtime.disk_path)
+        .and_then(|meta| meta.modified())
+        .ok();
+    Some(RuntimeFingerprint {
+        run_id: runtime.run_id,
+        disk_mtime,
+    })
+}
+
+/// Records the latest install runtime disk as the immutable base for a passed checkpoint.
+///
+/// Skips recording when the runtime disk is unchanged since `before`, which means the scenario
+/// was served from the pass cache and the disk may already reflect a later checkpoint.
+pub(crate) fn record_checkpoint(
+    root: &Path,
+    distro_id: &str,
+    scenario_key: &str,
+    before: Option<&RuntimeFingerprint>,
+) -> Result<()> {
+    let Some(label) = checkpoint_label(scenario_key) else {
+        return Ok(());
+    };
+    let after = runtime_fingerprint(distro_id);
+    if after.is_none() || after.as_ref() == before {
+        eprintln!(
+            "[snapshots] {distro_id} {label}: install runtime unchanged; keeping existing spawn point"
+        );
+        return Ok(());
+    }
+
+    let runtime = resolve_latest_install_runtime(distro_id)?;
+    let meta = SnapshotMeta {
+        name: scenario_key.to_string(),
+        distro: distro_id.to_string(),
+        kind: "checkpoint".to_string(),
+        checkpoint: Some(label.to_string()),
+        source: format!("install runtime {}", runtime.run_id),
+        created_unix: unix_now(),
+    };
+    let snapshot = write_snapshot(root, &meta, &runtime.disk_path, &runtime.ovmf_vars_path)?;
+    eprintln!(
+        "[snapshots] {distro_id} {label}: recorded spawn point {}",
+        snapshot.disk().display()
+    );
+    Ok(())
+}
+
+/// Flattens `disk` into a standalone read-only qcow2 base and stores it under `meta.name`.
+pub(crate) fn write_snapshot(
+    root: &Path,
+    meta: &SnapshotMeta,
+    disk: &Path,
+    vars: &Path,
+) -> Result<Snapshot> {
+    validate_name(&meta.name)?;
+    let store = store_dir(root, &meta.distro);
+    let dir = store.join(&meta.name);
+    let staging = store.join(format!(".{}.partial", meta.name));
+    if staging.exists() {
+        fs::remove_dir_all(&staging)
+            .with_context(|| format!("removing stale snapshot staging '{}'", staging.display()))?;
+    }
+    fs::create_dir_all(&staging)
+        .with_context(|| format!("creating snapshot staging '{}'", staging.display()))?;
+
+    let staged_disk = staging.join(DISK_FILE);
+    let mut cmd = Command::new("qemu-img");
+    cmd.args(["convert", "-O", "qcow2"])
+        .arg(disk)
+        .arg(&staged_disk);
+    crate::util::tools_env::apply_to_command(&mut cmd, root)?;
+    let status = cmd
+        .status()
+        .context("running qemu-img convert for snapshot")?;
+    if !status.success() {
+        bail!(
+            "qemu-img convert '{}' -> '{}' failed with status {status}",
+            disk.display(),
+            staged_disk.display()
+        );
+    }
+    fs::copy(vars, staging.join(VARS_FILE))
+        .with_context(|| format!("copying OVMF vars '{}'", vars.display()))?;
+    fs::write(
+        staging.join(META_FILE),
+        serde_json::to_string_pretty(meta).context("encoding snapshot metadata")?,
+    )
+    .context("writing snapshot metadata")?;
+    for file in [DISK_FILE, VARS_FILE] {
+        fs::set_permissions(staging.join(file), fs::Permissions::from_mode(0o444))
+            .with_context(|| format!("marking snapshot {file} read-only"))?;
+    }
+
+    if dir.exists() {
+        fs::remove_dir_all(&dir)
+            .with_context(|| format!("replacing snapshot '{}'", dir.display()))?;
+    }
+    fs::rename(&staging, &dir)
+        .with_context(|| format!("publishing snapshot '{}'", dir.display()))?;
+    Ok(Snapshot {
+        dir,
+        meta: meta.clone(),
+    })
+}
+
+pub(crate) fn find(root: &Path, distro_id: &str, name: &str) -> Result<Option<Snapshot>> {
+    validate_name(name)?;
+    let dir = store_dir(root, distro_id).join(name);
+    if !dir.join(DISK_FILE).is_file() {
+        return Ok(None);
+    }
+    let meta = read_meta(&dir)?;
+    Ok(Some(Snapshot { dir, meta }))
+}
+
+pub(crate) fn list(root: &Path, distro_id: &str) -> Result<Vec<Snapshot>> {
+    let store = store_dir(root, distro_id);
+    let entries = match fs::read_dir(&store) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("reading snapshot store '{}'", store.display()));
+        }
+    };
+    let mut snapshots = Vec::new();
+    for entry in entries {
+        let dir = entry?.path();
+        if !dir.is_dir() || !dir.join(DISK_FILE).is_file() {
+            continue;
+        }
+        let meta = read_meta(&dir)?;
+        snapshots.push(Snapshot { dir, meta });
+    }
+    snapshots.sort_by(|a, b| a.meta.name.cmp(&b.meta.name));
+    Ok(snapshots)
+}
+
+/// Creates a throwaway qcow2 overlay backed by `base`; the base is never written.
+pub(crate) fn create_overlay(
+    root: &Path,
+    base: &Path,
+    base_format: &str,
+    overlay: &Path,
+) -> Result<()> {
+    let mut cmd = Command::new("qemu-img");
+    cmd.args(["create", "-q", "-f", "qcow2", "-F", base_format, "-b"])
+        .arg(base)
+        .arg(overlay);
+    crate::util::tools_env::apply_to_command(&mut cmd, root)?;
+    let status = cmd
+        .status()
+        .context("running qemu-img create for overlay")?;
+    if !status.success() {
+        bail!(
+            "qemu-img create overlay '{}' on '{}' failed with status {status}",
+            overlay.display(),
+            base.display()
+        );
+    }
+    Ok(())
+}
+
+pub fn ls(distro: crate::cli::HarnessDistro) -> Result<()> {
+    let root = crate::util::repo::repo_root()?;
+    let snapshots = list(&root, distro.id())?;
+    if snapshots.is_empty() {
+        println!(
+            "No snapshots for {} under {}",
+            distro.id(),
+            store_dir(&root, distro.id()).display()
+        );
+        return Ok(());
+    }
+    println!("{:<24} {:<11} {:<12} SOURCE", "NAME", "KIND", "CHECKPOINT");
+    for snapshot in snapshots {
+        let meta = &snapshot.meta;
+        println!(
+            "{:<24} {:<11} {:<12} {}",
+            meta.name,
+            meta.kind,
+            meta.checkpoint.as_deref().unwrap_or("-"),
+            meta.source
+        );
+    }
+    Ok(())
+}
+
+pub fn rm(distro: crate::cli::HarnessDistro, name: &str) -> Result<()> {
+    let root = crate::util::repo::repo_root()?;
+    let Some(snapshot) = find(&root, distro.id(), name)? else {
+        bail!("no snapshot '{name}' for {}", distro.id());
+    };
+    fs::remove_dir_all(&snapshot.dir)
+        .with_context(|| format!("removing snapshot '{}'", snapshot.dir.display()))?;
+    eprintln!("[snapshots] removed {} ({})", name, snapshot.dir.display());
+    Ok(())
+}
+
+fn read_meta(dir: &Path) -> Result<SnapshotMeta> {
+    let path = dir.join(META_FILE);
+    let raw = fs::read_to_string(&path)
+        .with_context(|| format!("reading snapshot metadata '{}'", path.display()))?;
+    serde_json::from_str(&raw)
+        .with_context(|| format!("parsing snapshot metadata '{}'", path.display()))
+}
+
+/// `boot --persist` names live next to the checkpoint spawn points, so a checkpoint key would
+/// replace (or later be replaced by) a recorded checkpoint.
+pub(crate) fn validate_persist_name(name: &str) -> Result<()> {
+    validate_name(name)?;
+    if let Some(label) = checkpoint_label(name) {
+        bail!("snapshot name '{name}' is reserved for the {label} spawn point; pick another name");
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' || ch == '.')
+        && !name.starts_with('.');
+    if !valid {
+        bail!("invalid snapshot name '{name}'; use letters, digits, '-', '_' or '.'");
+    }
+    Ok(())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|dur| dur.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_fake_snapshot(root: &Path, meta: &SnapshotMeta) {
+        let dir = store_dir(root, &meta.distro).join(&meta.name);
+        fs::create_dir_all(&dir).expect("create snapshot dir");
+        fs::write(dir.join(DISK_FILE), b"qcow2").expect("write disk");
+        fs::write(
+            dir.join(META_FILE),
+            serde_json::to_string(meta).expect("encode meta"),
+        )
+        .expect("write meta");
+    }
+
+    fn meta(name: &str, kind: &str, checkpoint: Option<&str>) -> SnapshotMeta {
+        SnapshotMeta {
+            name: name.to_string(),
+            distro: "levitate".to_string(),
+            kind: kind.to_string(),
+            checkpoint: checkpoint.map(str::to_string),
+            source: "test".to_string(),
+            created_unix: 0,
+        }
+    }
+
+    #[test]
+    fn persist_names_cannot_shadow_checkpoints() {
+        assert!(validate_persist_name("debug-session").is_ok());
+        assert!(validate_persist_name("installed-boot").is_err());
+        assert!(validate_persist_name("runtime").is_err());
+        assert!(validate_persist_name("../escape").is_err());
+        assert!(validate_name(".hidden").is_err());
+        assert_eq!(checkpoint_label("install"), Some("03Install"));
+        assert_eq!(checkpoint_label("live-boot"), None);
+    }
+
+    #[test]
+    fn list_and_find_read_the_store() {
+        let root = crate::tasks::testing::test_temp_dir("snapshots");
+        write_fake_snapshot(&root, &meta("runtime", "checkpoint", Some("06Runtime")));
+        write_fake_snapshot(&root, &meta("debug", "persisted", Some("06Runtime")));
+        // Interrupted writes leave a staging dir without a published disk.
+        fs::create_dir_all(store_dir(&root, "levitate").join(".install.partial"))
+            .expect("create staging dir");
+
+        let names: Vec<String> = list(&root, "levitate")
+            .expect("list snapshots")
+            .into_iter()
+            .map(|snapshot| snapshot.meta.name)
+            .collect();
+        let found = find(&root, "levitate", "debug").expect("find snapshot");
+        let missing = find(&root, "levitate", "install").expect("find missing snapshot");
+        let empty = list(&root, "acorn").expect("list empty store");
+        let _ = fs::remove_dir_all(&root);
+
+        assert_eq!(names, ["debug", "runtime"]);
+        assert_eq!(found.expect("debug snapshot").meta.kind, "persisted");
+        assert!(missing.is_none());
+        assert!(empty.is_empty());
+    }
+}
@@ -0,0 +1,439 @@
+use super::boot_patterns::{BootPattern, scope_to_firmware};
+use crate::cli::{Firmware, VisualMode};
+use crate::util::qmp::{QmpClient, ScreendumpFormat};
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const VISUAL_SCHEMA_VERSION: u32 = 1;
+/// Per-variant checkpoint file; references live next to it in `visual/<name>.png`.
+const VISUAL_SPEC_PATH: &str = "scenarios/visual.toml";
+const REFERENCES_DIR: &str = "scenarios/visual";
+/// Perceived color distance (0-1) above which a pixel counts as different.
+const DEFAULT_PIXEL_THRESHOLD: f64 = 0.1;
+/// Share of unmasked pixels allowed to differ before a checkpoint fails.
+const DEFAULT_MAX_DIFF_PERCENT: f64 = 0.5;
+/// YIQ distance between black and white; normalizes [`pixel_delta`] to 0-1.
+const MAX_YIQ_DELTA: f64 = 35215.0;
+/// Display adapter of visual boots. References depend on it, so it is fixed rather than taken
+/// from the hardware profile.
+pub(super) const VISUAL_VGA: &str = "std";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VisualFile {
+    schema_version: u32,
+    #[serde(default)]
+    pixel_threshold: Option<f64>,
+    #[serde(default)]
+    max_diff_percent: Option<f64>,
+    #[serde(default)]
+    checkpoints: Vec<CheckpointSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CheckpointSpec {
+    name: String,
+    /// Scenario key the checkpoint belongs to, such as `live-boot`.
+    scenario: String,
+    /// Serial pattern (scenarios.toml syntax, including `uefi:`/`bios:` scopes) that triggers
+    /// the capture.
+    after: String,
+    /// Settle time between the pattern and the screendump.
+    #[serde(default)]
+    delay_ms: u64,
+    #[serde(default)]
+    pixel_threshold: Option<f64>,
+    #[serde(default)]
+    max_diff_percent: Option<f64>,
+    /// `[x, y, width, height]` regions ignored by the comparison (clocks, version strings).
+    #[serde(default)]
+    masks: Vec<[u32; 4]>,
+}
+
+struct Checkpoint {
+    name: String,
+    after: BootPattern,
+    delay: Duration,
+    pixel_threshold: f64,
+    max_diff_percent: f64,
+    masks: Vec<[u32; 4]>,
+}
+
+#[derive(Clone, Debug)]
+enum CaptureState {
+    Waiting,
+    Due(Instant),
+    Captured(PathBuf),
+}
+
+/// `--visual` screendump checks for one boot: captures each checkpoint over QMP once its serial
+/// pattern shows up, then compares the captures with the stored references.
+pub(super) struct VisualSession {
+    mode: VisualMode,
+    references: PathBuf,
+    out_dir: PathBuf,
+    checkpoints: Vec<Checkpoint>,
+    state: RefCell<Vec<CaptureState>>,
+}
+
+impl VisualSession {
+    /// Loads the checkpoints of `scenario_key` from `distro-variants/<distro>/scenarios/visual.toml`.
+    pub(super) fn load(
+        root: &Path,
+        distro_id: &str,
+        scenario_key: &str,
+        firmware: Firmware,
+        mode: VisualMode,
+    ) -> Result<Self> {
+        let variant_dir = root.join("distro-variants").join(distro_id);
+        let path = variant_dir.join(VISUAL_SPEC_PATH);
+        let raw = fs::read_to_string(&path)
+            .with_context(|| format!("reading visual checkpoints '{}'", path.display()))?;
+        let file: VisualFile = toml::from_str(&raw)
+            .with_context(|| format!("parsing visual checkpoints '{}'", path.display()))?;
+        if file.schema_version != VISUAL_SCHEMA_VERSION {
+            bail!(
+                "unsupported visual checkpoints schema_version {} in '{}' (expected {VISUAL_SCHEMA_VERSION})",
+                file.schema_version,
+                path.display()
+            );
+        }
+
+        let mut checkpoints = Vec::new();
+        for spec in file.checkpoints {
+            if spec.scenario != scenario_key {
+                continue;
+            }
+            if spec.name.is_empty()
+                || !spec
+                    .name
+                    .chars()
+                    .all(|ch| ch.is_ascii_alphanumeric() || "-_".contains(ch))
+            {
+                bail!(
+                    "visual checkpoint name '{}' in '{}' must use [A-Za-z0-9_-]",
+                    spec.name,
+                    path.display()
+                );
+            }
+            // Checkpoints scoped to the other firmware do not apply to this boot.
+            let Some(after) = scope_to_firmware(&[spec.after], firmware).pop() else {
+                continue;
+            };
+            checkpoints.push(Checkpoint {
+                after: BootPattern::parse(&after, "visual checkpoint")?,
+                delay: Duration::from_millis(spec.delay_ms),
+                pixel_threshold: spec
+                    .pixel_threshold
+                    .or(file.pixel_threshold)
+                    .unwrap_or(DEFAULT_PIXEL_THRESHOLD),
+                max_diff_percent: spec
+                    .max_diff_percent
+                    .or(file.max_diff_percent)
+                    .unwrap_or(DEFAULT_MAX_DIFF_PERCENT),
+                masks: spec.masks,
+                name: spec.name,
+            });
+        }
+        if checkpoints.is_empty() {
+            bail!(
+                "no visual checkpoints for '{scenario_key}' ({} firmware) in '{}'",
+                firmware.as_str(),
+                path.display()
+            );
+        }
+
+        let stamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|dur| dur.as_secs())
+            .unwrap_or(0);
+        let out_dir = root
+            .join(".artifacts/scenarios")
+            .join(distro_id)
+            .join("visual")
+            .join(stamp.to_string());
+        fs::create_dir_all(&out_dir)
+            .with_context(|| format!("creating visual capture dir '{}'", out_dir.display()))?;
+        Ok(Self {
+            mode,
+            references: variant_dir.join(REFERENCES_DIR),
+            out_dir,
+            state: RefCell::new(vec![CaptureState::Waiting; checkpoints.len()]),
+            checkpoints,
+        })
+    }
+
+    pub(super) fn print_summary(&self) {
+        let names: Vec<&str> = self.checkpoints.iter().map(|cp| cp.name.as_str()).collect();
+        eprintln!(
+            "Visual checks ({}): {} -> {}",
+            match self.mode {
+                VisualMode::Check => "check",
+                VisualMode::Update => "update references",
+            },
+            names.join(", "),
+            self.out_dir.display()
+        );
+    }
+
+    /// Schedules checkpoints whose pattern appeared in `content` and captures the ones that are
+    /// due.
+    pub(super) fn observe(&self, content: &str, qmp_socket: &Path) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        for (checkpoint, state) in self.checkpoints.iter().zip(state.iter_mut()) {
+            if matches!(state, CaptureState::Waiting) && checkpoint.after.find(content).is_some() {
+                *state = CaptureState::Due(Instant::now() + checkpoint.delay);
+            }
+            let CaptureState::Due(at) = state else {
+                continue;
+            };
+            if Instant::now() < *at {
+                continue;
+            }
+            let path = self.out_dir.join(format!("{}.png", checkpoint.name));
+            QmpClient::connect(qmp_socket, Duration::from_secs(5))
+                .and_then(|mut client| client.screendump(&path, ScreendumpFormat::Png))
+                .with_context(|| format!("capturing visual checkpoint '{}'", checkpoint.name))?;
+            eprintln!("[visual] captured '{}'", checkpoint.name);
+            *state = CaptureState::Captured(path);
+        }
+        Ok(())
+    }
+
+    /// Whether some checkpoint has not been captured yet.
+    pub(super) fn pending(&self) -> bool {
+        self.state
+            .borrow()
+            .iter()
+            .any(|state| !matches!(state, CaptureState::Captured(_)))
+    }
+
+    /// Compares every capture with its reference (or replaces the references in update mode),
+    /// writing `<name>.diff.png` next to failing captures.
+    pub(super) fn finish(&self) -> Result<()> {
+        let state = self.state.borrow();
+        let mut failures = Vec::new();
+        for (checkpoint, state) in self.checkpoints.iter().zip(state.iter()) {
+            let reference = self.references.join(format!("{}.png", checkpoint.name));
+            let CaptureState::Captured(actual) = state else {
+                failures.push(format!(
+                    "{}: never captured; no serial line matched '{}'",
+                    checkpoint.name,
+                    checkpoint.after.source()
+                ));
+                continue;
+            };
+            if self.mode == VisualMode::Update {
+                fs::create_dir_all(&self.references).with_context(|| {
+                    format!("creating reference dir '{}'", self.references.display())
+                })?;
+                fs::copy(actual, &reference)
+                    .with_context(|| format!("updating reference '{}'", reference.display()))?;
+                eprintln!("[visual] {}: reference updated", checkpoint.name);
+                continue;
+            }
+            if !reference.is_file() {
+                failures.push(format!(
+                    "{}: missing reference '{}'; review the capture {} and record it with `--visual update`",
+                    checkpoint.name,
+                    reference.display(),
+                    actual.display()
+                ));
+                continue;
+            }
+
+            let expected = RgbImage::read_png(&reference)?;
+            let captured = RgbImage::read_png(actual)?;
+            if (expected.width, expected.height) != (captured.width, captured.height) {
+                failures.push(format!(
+                    "{}: captured {}x{}, reference is {}x{}",
+                    checkpoint.name,
+                    captured.width,
+                    captured.height,
+                    expected.width,
+                    expected.height
+                ));
+                continue;
+            }
+            let comparison = compare(
+                &expected,
+                &captured,
+                &checkpoint.masks,
+                checkpoint.pixel_threshold,
+            );
+            let percent = comparison.percent();
+            if percent <= checkpoint.max_diff_percent {
+                eprintln!(
+                    "[visual] {}: ok ({percent:.3}% differs, limit {}%)",
+                    checkpoint.name, checkpoint.max_diff_percent
+                );
+                continue;
+            }
+            let diff_path = self.out_dir.join(format!("{}.diff.png", checkpoint.name));
+            comparison.diff.write_png(&diff_path)?;
+            failures.push(format!(
+                "{}: {percent:.3}% of pixels differ (limit {}%); diff: {}",
+                checkpoint.name,
+                checkpoint.max_diff_percent,
+                diff_path.display()
+            ));
+        }
+        if !failures.is_empty() {
+            bail!("Visual check failed:\n  {}", failures.join("\n  "));
+        }
+        Ok(())
+    }
+}
+
+/// 8-bit RGB pixels, row-major.
+struct RgbImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl RgbImage {
+    fn read_png(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("opening '{}'", path.display()))?;
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder
+            .read_info()
+            .with_context(|| format!("decoding '{}'", path.display()))?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader
+            .next_frame(&mut buf)
+            .with_context(|| format!("decoding '{}'", path.display()))?;
+        buf.truncate(info.buffer_size());
+        let pixels = match info.color_type {
+            png::ColorType::Rgb => buf,
+            png::ColorType::Rgba => buf
+                .chunks_exact(4)
+                .flat_map(|px| [px[0], px[1], px[2]])
+                .collect(),
+            png::ColorType::Grayscale => buf.iter().flat_map(|&v| [v, v, v]).collect(),
+            png::ColorType::GrayscaleAlpha => {
+                buf.chunks_exact(2).flat_map(|px| [px[0]; 3]).collect()
+            }
+            png::ColorType::Indexed => bail!("'{}': unexpanded palette PNG", path.display()),
+        };
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+
+    fn write_png(&self, path: &Path) -> Result<()> {
+        let file = File::create(path).with_context(|| format!("creating '{}'", path.display()))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&self.pixels))
+            .with_context(|| format!("writing '{}'", path.display()))
+    }
+}
+
+struct Comparison {
+    differing: usize,
+    compared: usize,
+    /// Faded grayscale capture with differing pixels in red and masks tinted blue.
+    diff: RgbImage,
+}
+
+impl Comparison {
+    fn percent(&self) -> f64 {
+        if self.compared == 0 {
+            return 0.0;
+        }
+        self.differing as f64 * 100.0 / self.compared as f64
+    }
+}
+
+fn compare(
+    expected: &RgbImage,
+    actual: &RgbImage,
+    masks: &[[u32; 4]],
+    pixel_threshold: f64,
+) -> Comparison {
+    let limit = MAX_YIQ_DELTA * pixel_threshold * pixel_threshold;
+    let mut diff = Vec::with_capacity(actual.pixels.len());
+    let (mut differing, mut compared) = (0, 0);
+    for (idx, (want, got)) in expected
+        .pixels
+        .chunks_exact(3)
+        .zip(actual.pixels.chunks_exact(3))
+        .enumerate()
+    {
+        let (x, y) = (idx as u32 % actual.width, idx as u32 / actual.width);
+        let faded = 255 - (255 - luma(got)) / 4;
+        let masked = masks
+            .iter()
+            .any(|&[mx, my, mw, mh]| x >= mx && x < mx + mw && y >= my && y < my + mh);
+        if masked {
+            diff.extend([faded / 2, faded / 2, faded]);
+            continue;
+        }
+        compared += 1;
+        if pixel_delta(want, got) > limit {
+            differing += 1;
+            diff.extend([255, 0, 0]);
+        } else {
+            diff.extend([faded, faded, faded]);
+        }
+    }
+    Comparison {
+        differing,
+        compared,
+        diff: RgbImage {
+            width: actual.width,
+            height: actual.height,
+            pixels: diff,
+        },
+    }
+}
+
+fn luma(px: &[u8]) -> u8 {
+    ((u32::from(px[0]) * 299 + u32::from(px[1]) * 587 + u32::from(px[2]) * 114) / 1000) as u8
+}
+
+/// Squared YIQ color distance, which tracks perceived difference better than RGB distance
+/// (Kotsarenko & Ramos, as used by pixelmatch).
+fn pixel_delta(a: &[u8], b: &[u8]) -> f64 {
+    let [r, g, bl] = [0, 1, 2].map(|ch| f64::from(a[ch]) - f64::from(b[ch]));
+    let y = r * 0.298_895_31 + g * 0.586_622_47 + bl * 0.114_482_23;
+    let i = r * 0.595_977_99 - g * 0.274_176_10 - bl * 0.321_801_89;
+    let q = r * 0.211_470_17 - g * 0.522_617_11 + bl * 0.311_146_94;
+    0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_and_perceptual_threshold() {
+        let image = |pixels: Vec<u8>| RgbImage {
+            width: 2,
+            height: 2,
+            pixels,
+        };
+        let expected = image(vec![0, 0, 0, 255, 255, 255, 40, 40, 40, 200, 0, 0]);
+        // Pixel 1 is a small shade change, pixel 2 flips to white, pixel 3 is masked.
+        let actual = image(vec![0, 0, 0, 250, 250, 250, 255, 255, 255, 0, 0, 200]);
+        let comparison = compare(&expected, &actual, &[[1, 1, 1, 1]], DEFAULT_PIXEL_THRESHOLD);
+        assert_eq!((comparison.differing, comparison.compared), (1, 3));
+        assert_eq!(&comparison.diff.pixels[6..9], &[255, 0, 0]);
+
+        let strict = compare(&expected, &actual, &[], 0.01);
+        assert_eq!(strict.differing, 3);
+    }
+}
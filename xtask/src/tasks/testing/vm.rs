@@ -0,0 +1,327 @@
+use super::registry::{self, SshEndpoint, VmRecord};
+use crate::util::qmp::{QmpClient, ScreendumpFormat};
+use anyhow::{Context, Result, bail};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+const QMP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Serial output replayed by `attach --serial` before following new lines.
+const SERIAL_BACKLOG_BYTES: u64 = 16 * 1024;
+
+pub fn shutdown(id: &str, timeout: u64) -> Result<()> {
+    let socket = resolve_socket(id)?;
+    let mut client = QmpClient::connect(&socket, QMP_TIMEOUT)?;
+    client.system_powerdown()?;
+    eprintln!("[vm] {id}: ACPI powerdown requested; waiting up to {timeout}s...");
+
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    while Instant::now() < deadline {
+        // QEMU closes the QMP socket when it exits after guest poweroff.
+        if client.query_status().is_err() {
+            eprintln!("[vm] {id}: powered off");
+            return Ok(());
+        }
+        sleep(Duration::from_millis(500));
+    }
+
+    eprintln!("[vm] {id}: guest did not power off within {timeout}s; quitting QEMU");
+    client.quit()?;
+    Ok(())
+}
+
+pub fn screenshot(id: &str, path: &Path) -> Result<()> {
+    let socket = resolve_socket(id)?;
+    let mut client = QmpClient::connect(&socket, QMP_TIMEOUT)?;
+    let format = ScreendumpFormat::for_path(path);
+    client.screendump(path, format).with_context(|| {
+        format!(
+            "capturing screendump for VM '{id}' (headless serial boots have no display; use `--window`)"
+        )
+    })?;
+    eprintln!("[vm] {id}: screendump written to {}", path.display());
+    Ok(())
+}
+
+pub fn keys(id: &str, sequence: Option<String>, text: Option<String>, delay_ms: u64) -> Result<()> {
+    let chords = match (sequence, text) {
+        (_, Some(text)) => crate::util::qmp::text_to_key_chords(&text)?,
+        (Some(sequence), None) => crate::util::qmp::parse_key_sequence(&sequence)?,
+        (None, None) => bail!("provide a key SEQUENCE or --text"),
+    };
+
+    let socket = resolve_socket(id)?;
+    let mut client = QmpClient::connect(&socket, QMP_TIMEOUT)?;
+    for chord in &chords {
+        client
+            .send_key_chord(chord, 100)
+            .with_context(|| format!("sending key chord '{}'", chord.join("-")))?;
+        sleep(Duration::from_millis(delay_ms));
+    }
+    eprintln!("[vm] {id}: sent {} key chord(s)", chords.len());
+    Ok(())
+}
+
+pub fn status(id: &str) -> Result<()> {
+    let socket = resolve_socket(id)?;
+    let mut client = QmpClient::connect(&socket, QMP_TIMEOUT)?;
+    let status = client.query_status()?;
+    println!("{id}: status={} running={}", status.status, status.running);
+    Ok(())
+}
+
+/// Non-zero exit code of a command run in the guest by [`exec`]; `main` turns it into the xtask
+/// exit code once everything has been dropped.
+#[derive(Debug)]
+pub struct GuestExit {
+    pub code: u8,
+}
+
+impl std::fmt::Display for GuestExit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "guest command exited with status {}", self.code)
+    }
+}
+
+impl std::error::Error for GuestExit {}
+
+/// Runs `command` in the guest as root over SSH with output streamed through; a non-zero guest
+/// exit code becomes the xtask exit code through [`GuestExit`].
+pub fn exec(id: &str, command: &[String]) -> Result<()> {
+    let endpoint = ssh_endpoint(id)?;
+    let remote: Vec<String> = command.iter().map(|arg| shell_quote(arg)).collect();
+    let status = Command::new("ssh")
+        .args(endpoint.args())
+        .arg("root@127.0.0.1")
+        .arg("--")
+        .arg(remote.join(" "))
+        .status()
+        .context("launching ssh")?;
+    match status.code() {
+        Some(0) => Ok(()),
+        // ssh reports its own failures as 255, so it cannot be told apart from the guest's.
+        Some(255) => bail!("ssh to VM '{id}' failed (status 255); the command may not have run"),
+        Some(code) => {
+            eprintln!("[vm] {id}: command exited with status {code}");
+            Err(GuestExit {
+                code: u8::try_from(code).unwrap_or(1),
+            }
+            .into())
+        }
+        None => bail!("ssh to VM '{id}' was terminated by a signal"),
+    }
+}
+
+/// Copies between the host and a scenario VM; exactly one side is written `<vm-id>:<path>`.
+pub fn cp(src: &str, dst: &str, recursive: bool) -> Result<()> {
+    let (id, src, dst) = match (guest_path(src), guest_path(dst)) {
+        (Some((id, path)), None) => (id, format!("root@127.0.0.1:{path}"), dst.to_string()),
+        (None, Some((id, path))) => (id, src.to_string(), format!("root@127.0.0.1:{path}")),
+        _ => bail!(
+            "exactly one of SRC and DST must be a guest path `<vm-id>:<path>` (got '{src}' and '{dst}')"
+        ),
+    };
+    let endpoint = ssh_endpoint(id)?;
+    let mut cmd = Command::new("scp");
+    cmd.args(endpoint.args());
+    if recursive {
+        cmd.arg("-r");
+    }
+    let status = cmd.arg(&src).arg(&dst).status().context("launching scp")?;
+    if !status.success() {
+        bail!("scp {src} -> {dst} failed with status {status}");
+    }
+    eprintln!("[vm] {id}: copied {src} -> {dst}");
+    Ok(())
+}
+
+/// Lists running scenario VMs, pruning registry entries whose QEMU is gone.
+pub fn ps() -> Result<()> {
+    let records = registry::list()?;
+    if records.is_empty() {
+        println!("No running scenario VMs.");
+        return Ok(());
+    }
+    println!(
+        "{:<28} {:<8} {:<10} {:<14} ENDPOINTS",
+        "ID", "PID", "DISTRO", "SCENARIO"
+    );
+    for record in records {
+        let pid = record
+            .qemu_pid()
+            .map_or_else(|| "-".to_string(), |pid| pid.to_string());
+        let mut endpoints = vec![format!("qmp={}", record.qmp_socket.display())];
+        if let Some(ssh) = &record.ssh {
+            endpoints.push(format!("ssh=127.0.0.1:{}", ssh.port));
+        }
+        if let Some(vnc) = &record.vnc {
+            endpoints.push(format!("vnc={vnc}"));
+        }
+        if let Some(log) = &record.serial_log {
+            endpoints.push(format!("serial={}", log.display()));
+        }
+        println!(
+            "{:<28} {:<8} {:<10} {:<14} {}",
+            record.id,
+            pid,
+            record.distro,
+            record.scenario,
+            endpoints.join(" ")
+        );
+    }
+    Ok(())
+}
+
+/// Opens an SSH shell on the VM, or follows its serial log with `serial` (or when the VM has no
+/// SSH endpoint). Leaving the session does not stop the VM.
+pub fn attach(id: &str, serial: bool) -> Result<()> {
+    let record = VmRecord::lookup(id)?;
+    match (&record.ssh, serial) {
+        (Some(ssh), false) => {
+            let status = Command::new("ssh")
+                .env("TERM", "vt100")
+                .args(super::scenarios::common_ssh_args(
+                    &ssh.private_key,
+                    ssh.port,
+                    &ssh.known_hosts,
+                ))
+                .args(["-tt", "-o", "BatchMode=no", "root@127.0.0.1"])
+                .status()
+                .context("launching interactive SSH session")?;
+            if !status.success() {
+                bail!("SSH session to VM '{id}' exited with status {status}");
+            }
+            Ok(())
+        }
+        _ => follow_serial_log(&record),
+    }
+}
+
+/// Stops a VM's QEMU with SIGTERM, then SIGKILL after `timeout` seconds.
+pub fn kill(id: &str, timeout: u64) -> Result<()> {
+    let record = VmRecord::lookup(id)?;
+    let Some(pid) = record.qemu_pid() else {
+        bail!("scenario VM '{id}' has not started QEMU yet (no pidfile)");
+    };
+    signal(pid, libc::SIGTERM)?;
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    while registry::process_alive(pid) && Instant::now() < deadline {
+        sleep(Duration::from_millis(200));
+    }
+    if registry::process_alive(pid) {
+        eprintln!("[vm] {id}: QEMU (pid {pid}) ignored SIGTERM for {timeout}s; sending SIGKILL");
+        signal(pid, libc::SIGKILL)?;
+    }
+    registry::remove(id);
+    eprintln!("[vm] {id}: killed QEMU (pid {pid})");
+    Ok(())
+}
+
+fn signal(pid: u32, sig: libc::c_int) -> Result<()> {
+    let raw = libc::pid_t::try_from(pid).context("QEMU pid out of range")?;
+    // SAFETY: kill only sends a signal to the given pid.
+    if unsafe { libc::kill(raw, sig) } != 0 {
+        let err = std::io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::ESRCH) {
+            return Err(err).with_context(|| format!("signalling QEMU pid {pid}"));
+        }
+    }
+    Ok(())
+}
+
+/// Prints the tail of the serial log, then new output until QEMU exits (Ctrl-C detaches).
+fn follow_serial_log(record: &VmRecord) -> Result<()> {
+    let Some(log_path) = &record.serial_log else {
+        bail!(
+            "scenario VM '{}' has its serial console on the terminal that booted it and no SSH \
+             endpoint; boot with `--ssh`, `--window` or `--no-shell` to attach from elsewhere",
+            record.id
+        );
+    };
+    let mut log = File::open(log_path)
+        .with_context(|| format!("opening serial log '{}'", log_path.display()))?;
+    let len = log.metadata().map(|meta| meta.len()).unwrap_or(0);
+    log.seek(SeekFrom::Start(len.saturating_sub(SERIAL_BACKLOG_BYTES)))
+        .context("seeking serial log")?;
+    eprintln!(
+        "[vm] {}: following {} (Ctrl-C to detach)",
+        record.id,
+        log_path.display()
+    );
+    let mut out = std::io::stdout().lock();
+    let mut buf = [0u8; 8192];
+    loop {
+        let read = log.read(&mut buf).context("reading serial log")?;
+        if read > 0 {
+            out.write_all(&buf[..read])?;
+            out.flush()?;
+            continue;
+        }
+        if !record.is_alive() {
+            eprintln!("[vm] {}: QEMU exited", record.id);
+            return Ok(());
+        }
+        sleep(Duration::from_millis(250));
+    }
+}
+
+fn ssh_endpoint(id: &str) -> Result<SshEndpoint> {
+    match VmRecord::lookup(id)?.ssh {
+        Some(ssh) => Ok(ssh),
+        None => bail!(
+            "scenario VM '{id}' has no SSH endpoint; boot it with `cargo xtask scenarios boot <scenario> <distro> --ssh`"
+        ),
+    }
+}
+
+/// Splits `<vm-id>:<path>`; host paths containing `:` must include a `/` before it.
+fn guest_path(raw: &str) -> Option<(&str, &str)> {
+    let (id, path) = raw.split_once(':')?;
+    (!id.is_empty() && !id.contains('/')).then_some((id, path))
+}
+
+/// Quotes one argv element for the guest's POSIX shell.
+pub(super) fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || "-_./=:,+@%".contains(ch));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+fn resolve_socket(id: &str) -> Result<PathBuf> {
+    let socket = crate::util::qmp::socket_path(id);
+    if !socket.exists() {
+        bail!(
+            "no running scenario VM '{id}' (missing QMP socket {}).\n\
+             Boot one with `cargo xtask scenarios boot <scenario> <distro>`; it prints its VM id.",
+            socket.display()
+        );
+    }
+    Ok(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guest_paths_and_quoting() {
+        assert_eq!(
+            guest_path("levitate-live-boot:/etc/os-release"),
+            Some(("levitate-live-boot", "/etc/os-release"))
+        );
+        assert_eq!(guest_path("./dir/a:b"), None);
+        assert_eq!(guest_path("/tmp/file"), None);
+        assert_eq!(shell_quote("/etc/os-release"), "/etc/os-release");
+        assert_eq!(shell_quote("it's here"), "'it'\\''s here'");
+    }
+}
@@ -35,6 +35,17 @@ pub fn run() -> Result<()> {
         ok = false;
     }
 
+    // Only `--arch aarch64` scenario boots need AAVMF, so its absence does not fail doctor.
+    let aavmf = crate::util::repo::aavmf_path(&root)?;
+    if aavmf.is_file() {
+        eprintln!("[OK] {}", aavmf.display());
+    } else {
+        eprintln!(
+            "[WARN] missing AAVMF firmware (needed for `--arch aarch64`): {}",
+            aavmf.display()
+        );
+    }
+
     if !ok {
         bail!("doctor checks failed");
     }
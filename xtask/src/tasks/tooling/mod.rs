@@ -2,3 +2,4 @@ pub mod doctor;
 pub mod env;
 pub mod hooks;
 pub mod policy;
+pub mod replay;
@@ -0,0 +1,30 @@
+use anyhow::Result;
+use std::path::Path;
+
+pub fn run(cast: &Path, speed: f64, max_idle: Option<f64>) -> Result<()> {
+    let (header, events) = crate::util::asciicast::read(cast)?;
+    let duration = events.last().map_or(0.0, |event| event.time);
+    eprintln!(
+        "Replaying {} ({}x{}, {duration:.1}s{}){}",
+        cast.display(),
+        header.width,
+        header.height,
+        if speed == 1.0 {
+            String::new()
+        } else {
+            format!(" at {speed}x")
+        },
+        header
+            .title
+            .as_deref()
+            .map(|title| format!(": {title}"))
+            .unwrap_or_default()
+    );
+    let (columns, rows) = crate::util::asciicast::terminal_size();
+    if columns < header.width || rows < header.height {
+        eprintln!(
+            "  note: terminal is {columns}x{rows}; output may wrap differently than recorded."
+        );
+    }
+    crate::util::asciicast::play(&events, speed, max_idle)
+}
@@ -17,6 +17,7 @@ pub struct Options {
     pub stdout: bool,
     pub ansi: bool,
     pub keep_transcript: bool,
+    pub cast: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -52,6 +53,10 @@ pub fn run(options: Options) -> Result<()> {
         options.rows,
     );
 
+    let cast_path = options
+        .cast
+        .then(|| run_dir.join(format!("001-{}.cast", target.name)));
+
     eprintln!(
         "[tui.inspect] target={} cwd={}",
         target.name,
@@ -65,6 +70,7 @@ pub fn run(options: Options) -> Result<()> {
         rows,
         seconds,
         &transcript_path,
+        cast_path.as_deref(),
     )?;
 
     let transcript = fs::read(&transcript_path)
@@ -234,6 +240,7 @@ fn render_transcript(
     rows: u16,
     seconds: u64,
     transcript_path: &Path,
+    cast_path: Option<&Path>,
 ) -> Result<()> {
     let base = target.command.to_string();
     let command = if let Some(sequence) = input {
@@ -253,6 +260,7 @@ fn render_transcript(
         rows,
         seconds,
         transcript_path,
+        cast_path,
     })
 }
 
@@ -0,0 +1,220 @@
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Header line of an asciicast v2 file.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CastHeader {
+    pub version: u32,
+    pub width: u16,
+    pub height: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+}
+
+/// Writes terminal output as asciicast v2 `[elapsed, "o", data]` events, one line per chunk, so
+/// a recording is usable up to the last chunk even when the writer is never dropped.
+pub struct CastWriter {
+    file: File,
+    started: Instant,
+    /// Trailing bytes of a UTF-8 sequence split across chunks.
+    pending: Vec<u8>,
+}
+
+impl CastWriter {
+    pub fn create(path: &Path, width: u16, height: u16, title: &str) -> Result<Self> {
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("creating cast dir '{}'", parent.display()))?;
+        }
+        let mut file =
+            File::create(path).with_context(|| format!("creating cast '{}'", path.display()))?;
+        let header = CastHeader {
+            version: 2,
+            width: width.max(1),
+            height: height.max(1),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|dur| dur.as_secs()),
+            title: Some(title.to_string()),
+        };
+        let mut line = serde_json::to_string(&header).context("encoding cast header")?;
+        line.push('\n');
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("writing cast header '{}'", path.display()))?;
+        Ok(Self {
+            file,
+            started: Instant::now(),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Records `chunk` as one output event stamped with the time since [`CastWriter::create`].
+    pub fn output(&mut self, chunk: &[u8]) -> Result<()> {
+        self.pending.extend_from_slice(chunk);
+        let data = take_utf8(&mut self.pending);
+        if data.is_empty() {
+            return Ok(());
+        }
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let mut line =
+            serde_json::to_string(&(elapsed, "o", data)).context("encoding cast event")?;
+        line.push('\n');
+        self.file
+            .write_all(line.as_bytes())
+            .context("writing cast event")
+    }
+}
+
+/// Drains the decodable prefix of `buf`, keeping an incomplete trailing UTF-8 sequence for the
+/// next chunk. Invalid bytes are replaced rather than dropped.
+fn take_utf8(buf: &mut Vec<u8>) -> String {
+    let keep = match std::str::from_utf8(buf) {
+        Ok(_) => 0,
+        Err(err) if err.error_len().is_none() => buf.len() - err.valid_up_to(),
+        Err(_) => 0,
+    };
+    let rest = buf.split_off(buf.len() - keep);
+    let data = String::from_utf8_lossy(buf).into_owned();
+    *buf = rest;
+    data
+}
+
+/// Copies `reader` into `sink` until EOF, recording every chunk into `cast` as well. Used to tee
+/// a child's output into its usual destination and a recording.
+pub fn tee(mut reader: impl Read, mut sink: impl Write, cast: &mut CastWriter) -> Result<()> {
+    let mut buf = [0_u8; 8192];
+    loop {
+        let read = match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            // A PTY master reports EIO once the child side has closed.
+            Err(err) if err.raw_os_error() == Some(libc::EIO) => return Ok(()),
+            Err(err) => return Err(err).context("reading recorded stream"),
+        };
+        sink.write_all(&buf[..read])
+            .and_then(|()| sink.flush())
+            .context("forwarding recorded stream")?;
+        cast.output(&buf[..read])?;
+    }
+}
+
+/// Size of the controlling terminal, or 80x24 when stdout is not a terminal.
+pub fn terminal_size() -> (u16, u16) {
+    // SAFETY: TIOCGWINSZ only writes into the provided winsize struct.
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let rc = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    if rc == 0 && size.ws_col > 0 && size.ws_row > 0 {
+        (size.ws_col, size.ws_row)
+    } else {
+        (80, 24)
+    }
+}
+
+/// One output event of a parsed cast.
+#[derive(Debug, PartialEq)]
+pub struct CastEvent {
+    pub time: f64,
+    pub data: String,
+}
+
+/// Reads the header and output events of an asciicast v2 file; input and marker events are
+/// skipped.
+pub fn read(path: &Path) -> Result<(CastHeader, Vec<CastEvent>)> {
+    let raw =
+        fs::read_to_string(path).with_context(|| format!("reading cast '{}'", path.display()))?;
+    parse(&raw).with_context(|| format!("parsing cast '{}'", path.display()))
+}
+
+fn parse(raw: &str) -> Result<(CastHeader, Vec<CastEvent>)> {
+    let mut lines = raw.lines().filter(|line| !line.trim().is_empty());
+    let Some(first) = lines.next() else {
+        bail!("empty cast file");
+    };
+    let header: CastHeader = serde_json::from_str(first).context("invalid cast header")?;
+    if header.version != 2 {
+        bail!(
+            "unsupported asciicast version {} (expected 2)",
+            header.version
+        );
+    }
+    let mut events = Vec::new();
+    for (idx, line) in lines.enumerate() {
+        let (time, kind, data): (f64, String, String) = serde_json::from_str(line)
+            .with_context(|| format!("invalid cast event on line {}", idx + 2))?;
+        if kind == "o" {
+            events.push(CastEvent { time, data });
+        }
+    }
+    Ok((header, events))
+}
+
+/// Plays `events` to stdout in real time divided by `speed`, capping pauses at `max_idle`
+/// seconds when set.
+pub fn play(events: &[CastEvent], speed: f64, max_idle: Option<f64>) -> Result<()> {
+    if speed <= 0.0 || !speed.is_finite() {
+        bail!("replay speed must be a positive number, got {speed}");
+    }
+    let mut stdout = std::io::stdout().lock();
+    let mut previous = 0.0;
+    for event in events {
+        let mut pause = (event.time - previous).max(0.0);
+        if let Some(max_idle) = max_idle {
+            pause = pause.min(max_idle);
+        }
+        previous = event.time;
+        thread::sleep(Duration::from_secs_f64(pause / speed));
+        stdout
+            .write_all(event.data.as_bytes())
+            .and_then(|()| stdout.flush())
+            .context("writing replay output")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_utf8_is_carried_and_events_round_trip() {
+        let mut buf = "ok \u{2500}".as_bytes().to_vec();
+        let tail = buf.split_off(buf.len() - 1);
+        assert_eq!(take_utf8(&mut buf), "ok ");
+        buf.extend_from_slice(&tail);
+        assert_eq!(take_utf8(&mut buf), "\u{2500}");
+        assert!(buf.is_empty());
+
+        let raw = "{\"version\":2,\"width\":80,\"height\":24}\n\
+                   [0.5,\"o\",\"login: \"]\n\
+                   [0.7,\"i\",\"root\\r\"]\n\
+                   [1.25,\"o\",\"\\u001b[1mroot\\u001b[0m\\r\\n\"]\n";
+        let (header, events) = parse(raw).unwrap();
+        assert_eq!((header.width, header.height), (80, 24));
+        assert_eq!(
+            events,
+            vec![
+                CastEvent {
+                    time: 0.5,
+                    data: "login: ".to_string()
+                },
+                CastEvent {
+                    time: 1.25,
+                    data: "\u{1b}[1mroot\u{1b}[0m\r\n".to_string()
+                },
+            ]
+        );
+        assert!(parse("{\"version\":1,\"width\":80,\"height\":24}").is_err());
+    }
+}
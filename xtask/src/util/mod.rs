@@ -1,3 +1,5 @@
+pub mod asciicast;
 pub mod pty_capture;
+pub mod qmp;
 pub mod repo;
 pub mod tools_env;
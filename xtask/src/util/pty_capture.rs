@@ -1,3 +1,4 @@
+use crate::util::asciicast::{self, CastWriter};
 use anyhow::{Context, Result, anyhow, bail};
 use portable_pty::{CommandBuilder, PtySize, native_pty_system};
 use std::env;
@@ -15,6 +16,8 @@ pub struct ShellCapture<'a> {
     pub rows: u16,
     pub seconds: u64,
     pub transcript_path: &'a Path,
+    /// Also record the session as an asciicast v2 file with output timing.
+    pub cast_path: Option<&'a Path>,
 }
 
 pub fn capture_shell_transcript(request: ShellCapture<'_>) -> Result<()> {
@@ -45,11 +48,20 @@ pub fn capture_shell_transcript(request: ShellCapture<'_>) -> Result<()> {
         .try_clone_reader()
         .with_context(|| format!("{}: clone PTY reader", request.label))?;
     let reader_label = request.label.to_string();
+    let mut cast = request
+        .cast_path
+        .map(|path| CastWriter::create(path, request.columns, request.rows, request.label))
+        .transpose()?;
     let reader_handle = thread::spawn(move || -> Result<Vec<u8>> {
         let mut transcript = Vec::new();
-        reader
-            .read_to_end(&mut transcript)
-            .with_context(|| format!("{reader_label}: read PTY transcript"))?;
+        match &mut cast {
+            Some(cast) => asciicast::tee(reader, &mut transcript, cast),
+            None => reader
+                .read_to_end(&mut transcript)
+                .map(|_| ())
+                .map_err(Into::into),
+        }
+        .with_context(|| format!("{reader_label}: read PTY transcript"))?;
         Ok(transcript)
     });
 
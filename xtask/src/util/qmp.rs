@@ -0,0 +1,286 @@
+use anyhow::{Context, Result, anyhow, bail};
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Minimal QEMU Machine Protocol client over a unix socket.
+pub struct QmpClient {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScreendumpFormat {
+    Ppm,
+    Png,
+}
+
+impl ScreendumpFormat {
+    /// Picks the format from the output file extension (`.png` -> PNG, anything else -> PPM).
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("png") => Self::Png,
+            _ => Self::Ppm,
+        }
+    }
+
+    fn qmp_name(self) -> &'static str {
+        match self {
+            Self::Ppm => "ppm",
+            Self::Png => "png",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct VmStatus {
+    pub status: String,
+    pub running: bool,
+}
+
+impl QmpClient {
+    pub fn connect(socket: &Path, timeout: Duration) -> Result<Self> {
+        let stream = UnixStream::connect(socket)
+            .with_context(|| format!("connecting to QMP socket '{}'", socket.display()))?;
+        stream
+            .set_read_timeout(Some(timeout))
+            .context("setting QMP read timeout")?;
+        let writer = stream.try_clone().context("cloning QMP socket")?;
+        let mut client = Self {
+            reader: BufReader::new(stream),
+            writer,
+        };
+
+        let greeting = client.read_message()?;
+        if greeting.get("QMP").is_none() {
+            bail!("unexpected QMP greeting: {greeting}");
+        }
+        client.execute("qmp_capabilities", None)?;
+        Ok(client)
+    }
+
+    /// Runs one QMP command and returns its `return` payload. Asynchronous events are skipped.
+    pub fn execute(&mut self, command: &str, arguments: Option<Value>) -> Result<Value> {
+        let mut request = json!({ "execute": command });
+        if let Some(arguments) = arguments {
+            request["arguments"] = arguments;
+        }
+        let mut line = serde_json::to_string(&request).context("encoding QMP request")?;
+        line.push('\n');
+        self.writer
+            .write_all(line.as_bytes())
+            .with_context(|| format!("sending QMP command '{command}'"))?;
+
+        loop {
+            let message = self.read_message()?;
+            if message.get("event").is_some() {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                let class = error
+                    .get("class")
+                    .and_then(Value::as_str)
+                    .unwrap_or("GenericError");
+                let desc = error.get("desc").and_then(Value::as_str).unwrap_or("");
+                bail!("QMP command '{command}' failed: {class}: {desc}");
+            }
+            if let Some(result) = message.get("return") {
+                return Ok(result.clone());
+            }
+        }
+    }
+
+    pub fn query_status(&mut self) -> Result<VmStatus> {
+        let result = self.execute("query-status", None)?;
+        Ok(VmStatus {
+            status: result
+                .get("status")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown")
+                .to_string(),
+            running: result
+                .get("running")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+        })
+    }
+
+    pub fn system_powerdown(&mut self) -> Result<()> {
+        self.execute("system_powerdown", None).map(|_| ())
+    }
+
+    pub fn quit(&mut self) -> Result<()> {
+        self.execute("quit", None).map(|_| ())
+    }
+
+    pub fn screendump(&mut self, path: &Path, format: ScreendumpFormat) -> Result<()> {
+        let absolute = absolute_path(path)?;
+        self.execute(
+            "screendump",
+            Some(json!({
+                "filename": absolute.display().to_string(),
+                "format": format.qmp_name(),
+            })),
+        )
+        .map(|_| ())
+    }
+
+    /// Sends one key chord, for example `["ctrl", "alt", "f2"]`, using QEMU qcode names.
+    pub fn send_key_chord(&mut self, chord: &[String], hold_time_ms: u64) -> Result<()> {
+        if chord.is_empty() {
+            bail!("empty key chord");
+        }
+        let keys: Vec<Value> = chord
+            .iter()
+            .map(|key| json!({ "type": "qcode", "data": key }))
+            .collect();
+        self.execute(
+            "send-key",
+            Some(json!({ "keys": keys, "hold-time": hold_time_ms })),
+        )
+        .map(|_| ())
+    }
+
+    fn read_message(&mut self) -> Result<Value> {
+        let mut line = String::new();
+        let read = self
+            .reader
+            .read_line(&mut line)
+            .context("reading QMP response")?;
+        if read == 0 {
+            bail!("QMP socket closed by QEMU");
+        }
+        serde_json::from_str(line.trim()).with_context(|| format!("decoding QMP message '{line}'"))
+    }
+}
+
+/// Parses a whitespace-separated key sequence such as `ctrl-alt-f2 ret` into chords.
+pub fn parse_key_sequence(raw: &str) -> Result<Vec<Vec<String>>> {
+    let mut chords = Vec::new();
+    for token in raw.split_whitespace() {
+        let chord: Vec<String> = token
+            .split('-')
+            .map(|key| key.trim().to_ascii_lowercase())
+            .collect();
+        if chord.iter().any(String::is_empty) {
+            bail!("invalid key chord '{token}'; expected qcode names joined by '-'");
+        }
+        chords.push(chord);
+    }
+    if chords.is_empty() {
+        bail!("key sequence is empty");
+    }
+    Ok(chords)
+}
+
+/// Converts printable text into key chords (US layout), for typing into a guest console.
+pub fn text_to_key_chords(text: &str) -> Result<Vec<Vec<String>>> {
+    let mut chords = Vec::new();
+    for ch in text.chars() {
+        let chord = match ch {
+            'a'..='z' | '0'..='9' => vec![ch.to_string()],
+            'A'..='Z' => vec!["shift".to_string(), ch.to_ascii_lowercase().to_string()],
+            ' ' => vec!["spc".to_string()],
+            '\n' => vec!["ret".to_string()],
+            '\t' => vec!["tab".to_string()],
+            _ => match punctuation_qcode(ch) {
+                Some((true, code)) => vec!["shift".to_string(), code.to_string()],
+                Some((false, code)) => vec![code.to_string()],
+                None => return Err(anyhow!("cannot type character {ch:?} via send-key")),
+            },
+        };
+        chords.push(chord);
+    }
+    Ok(chords)
+}
+
+fn punctuation_qcode(ch: char) -> Option<(bool, &'static str)> {
+    let mapped = match ch {
+        '-' => (false, "minus"),
+        '_' => (true, "minus"),
+        '=' => (false, "equal"),
+        '+' => (true, "equal"),
+        '.' => (false, "dot"),
+        '>' => (true, "dot"),
+        ',' => (false, "comma"),
+        '<' => (true, "comma"),
+        '/' => (false, "slash"),
+        '?' => (true, "slash"),
+        ';' => (false, "semicolon"),
+        ':' => (true, "semicolon"),
+        '\'' => (false, "apostrophe"),
+        '"' => (true, "apostrophe"),
+        '[' => (false, "bracket_left"),
+        '{' => (true, "bracket_left"),
+        ']' => (false, "bracket_right"),
+        '}' => (true, "bracket_right"),
+        '\\' => (false, "backslash"),
+        '|' => (true, "backslash"),
+        '`' => (false, "grave_accent"),
+        '~' => (true, "grave_accent"),
+        '!' => (true, "1"),
+        '@' => (true, "2"),
+        '#' => (true, "3"),
+        '$' => (true, "4"),
+        '%' => (true, "5"),
+        '^' => (true, "6"),
+        '&' => (true, "7"),
+        '*' => (true, "8"),
+        '(' => (true, "9"),
+        ')' => (true, "0"),
+        _ => return None,
+    };
+    Some(mapped)
+}
+
+/// Per-user directory holding QMP sockets for scenario VMs.
+pub fn runtime_dir() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir).join("levitate-scenarios"),
+        _ => {
+            // SAFETY: getuid has no preconditions and cannot fail.
+            let uid = unsafe { libc::getuid() };
+            std::env::temp_dir().join(format!("levitate-scenarios-{uid}"))
+        }
+    }
+}
+
+pub fn socket_path(vm_id: &str) -> PathBuf {
+    runtime_dir().join(format!("{vm_id}.qmp"))
+}
+
+fn absolute_path(path: &Path) -> Result<PathBuf> {
+    if path.is_absolute() {
+        return Ok(path.to_path_buf());
+    }
+    Ok(std::env::current_dir()
+        .context("resolving current directory")?
+        .join(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_key_sequence, text_to_key_chords};
+
+    #[test]
+    fn parse_key_sequence_splits_chords() {
+        let chords = parse_key_sequence("ctrl-alt-F2  ret").expect("parse");
+        assert_eq!(
+            chords,
+            vec![vec!["ctrl", "alt", "f2"], vec!["ret"]]
+                .into_iter()
+                .map(|chord| chord.into_iter().map(String::from).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        );
+        assert!(parse_key_sequence("ctrl--c").is_err());
+    }
+
+    #[test]
+    fn text_to_key_chords_uses_shift_for_symbols() {
+        let chords = text_to_key_chords("A:").expect("text");
+        assert_eq!(chords[0], vec!["shift", "a"]);
+        assert_eq!(chords[1], vec!["shift", "semicolon"]);
+    }
+}
@@ -68,3 +68,27 @@ pub fn ovmf_path(root: &Path) -> Result<PathBuf> {
     let tools_prefix = tools_prefix(root)?;
     Ok(tools_prefix.join("usr/share/edk2/ovmf/OVMF_CODE.fd"))
 }
+
+/// OVMF build with Secure Boot and SMM support; needs `-machine q35,smm=on`.
+pub fn ovmf_secboot_path(root: &Path) -> Result<PathBuf> {
+    let tools_prefix = tools_prefix(root)?;
+    Ok(tools_prefix.join("usr/share/edk2/ovmf/OVMF_CODE.secboot.fd"))
+}
+
+/// Pristine OVMF variable store shipped next to the firmware code.
+pub fn ovmf_vars_path(root: &Path) -> Result<PathBuf> {
+    let tools_prefix = tools_prefix(root)?;
+    Ok(tools_prefix.join("usr/share/edk2/ovmf/OVMF_VARS.fd"))
+}
+
+/// AAVMF (edk2 for the arm64 virt machine), padded to the 64 MiB pflash size QEMU expects.
+pub fn aavmf_path(root: &Path) -> Result<PathBuf> {
+    let tools_prefix = tools_prefix(root)?;
+    Ok(tools_prefix.join("usr/share/edk2/aarch64/QEMU_EFI-pflash.raw"))
+}
+
+/// Pristine AAVMF variable store matching [`aavmf_path`].
+pub fn aavmf_vars_path(root: &Path) -> Result<PathBuf> {
+    let tools_prefix = tools_prefix(root)?;
+    Ok(tools_prefix.join("usr/share/edk2/aarch64/vars-template-pflash.raw"))
+}